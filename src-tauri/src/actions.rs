@@ -1,20 +1,23 @@
-#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-use crate::apple_intelligence;
 use crate::audio_feedback::{play_feedback_sound, play_feedback_sound_blocking, SoundType};
 use crate::cloud_stt;
+use crate::commands::profiles;
+use crate::helpers::active_app::focused_app_id;
 use crate::managers::audio::AudioRecordingManager;
-use crate::managers::history::HistoryManager;
+use crate::managers::history::{HistoryManager, TranscriptionStats};
+use crate::managers::meeting::MeetingManager;
 use crate::managers::transcription::TranscriptionManager;
-use crate::settings::{get_settings, AppSettings, APPLE_INTELLIGENCE_PROVIDER_ID};
+use crate::output_sinks;
+use crate::post_process;
+use crate::secrets;
+use crate::settings::{self, get_settings, AppSettings};
 use crate::shortcut;
 use crate::tray::{change_tray_icon, TrayIconState};
 use crate::utils::{self, show_recording_overlay, show_transcribing_overlay};
 use crate::ManagedToggleState;
-use ferrous_opencc::{config::BuiltinConfig, OpenCC};
 use log::{debug, error, info};
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use tauri::AppHandle;
 use tauri::Manager;
@@ -23,198 +26,18 @@ use tauri::Manager;
 pub trait ShortcutAction: Send + Sync {
     fn start(&self, app: &AppHandle, binding_id: &str, shortcut_str: &str);
     fn stop(&self, app: &AppHandle, binding_id: &str, shortcut_str: &str);
-}
-
-// Transcribe Action
-struct TranscribeAction;
-
-async fn maybe_post_process_transcription(
-    settings: &AppSettings,
-    transcription: &str,
-) -> Option<String> {
-    if !settings.post_process_enabled {
-        return None;
-    }
-
-    let provider = match settings.active_post_process_provider().cloned() {
-        Some(provider) => provider,
-        None => {
-            debug!("Post-processing enabled but no provider is selected");
-            return None;
-        }
-    };
-
-    let model = settings
-        .post_process_models
-        .get(&provider.id)
-        .cloned()
-        .unwrap_or_default();
-
-    if model.trim().is_empty() {
-        debug!(
-            "Post-processing skipped because provider '{}' has no model configured",
-            provider.id
-        );
-        return None;
-    }
 
-    let selected_prompt_id = match &settings.post_process_selected_prompt_id {
-        Some(id) => id.clone(),
-        None => {
-            debug!("Post-processing skipped because no prompt is selected");
-            return None;
-        }
-    };
-
-    let prompt = match settings
-        .post_process_prompts
-        .iter()
-        .find(|prompt| prompt.id == selected_prompt_id)
-    {
-        Some(prompt) => prompt.prompt.clone(),
-        None => {
-            debug!(
-                "Post-processing skipped because prompt '{}' was not found",
-                selected_prompt_id
-            );
-            return None;
-        }
-    };
-
-    if prompt.trim().is_empty() {
-        debug!("Post-processing skipped because the selected prompt is empty");
-        return None;
-    }
-
-    debug!(
-        "Starting LLM post-processing with provider '{}' (model: {})",
-        provider.id, model
-    );
-
-    // Replace ${output} variable in the prompt with the actual text
-    let processed_prompt = prompt.replace("${output}", transcription);
-    debug!("Processed prompt length: {} chars", processed_prompt.len());
-
-    if provider.id == APPLE_INTELLIGENCE_PROVIDER_ID {
-        #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-        {
-            if !apple_intelligence::check_apple_intelligence_availability() {
-                debug!("Apple Intelligence selected but not currently available on this device");
-                return None;
-            }
-
-            let token_limit = model.trim().parse::<i32>().unwrap_or(0);
-            return match apple_intelligence::process_text(&processed_prompt, token_limit) {
-                Ok(result) => {
-                    if result.trim().is_empty() {
-                        debug!("Apple Intelligence returned an empty response");
-                        None
-                    } else {
-                        debug!(
-                            "Apple Intelligence post-processing succeeded. Output length: {} chars",
-                            result.len()
-                        );
-                        Some(result)
-                    }
-                }
-                Err(err) => {
-                    error!("Apple Intelligence post-processing failed: {}", err);
-                    None
-                }
-            };
-        }
-
-        #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
-        {
-            debug!("Apple Intelligence provider selected on unsupported platform");
-            return None;
-        }
-    }
-
-    let api_key = settings
-        .post_process_api_keys
-        .get(&provider.id)
-        .cloned()
-        .unwrap_or_default();
-
-    // Send the chat completion request
-    match crate::llm_client::send_chat_completion(&provider, api_key, &model, processed_prompt)
-        .await
-    {
-        Ok(Some(content)) => {
-            // Strip invisible Unicode characters that some LLMs (e.g., Qwen) may insert
-            let content = content
-                .replace('\u{200B}', "") // Zero-Width Space
-                .replace('\u{200C}', "") // Zero-Width Non-Joiner
-                .replace('\u{200D}', "") // Zero-Width Joiner
-                .replace('\u{FEFF}', ""); // Byte Order Mark / Zero-Width No-Break Space
-            debug!(
-                "LLM post-processing succeeded for provider '{}'. Output length: {} chars",
-                provider.id,
-                content.len()
-            );
-            Some(content)
-        }
-        Ok(None) => {
-            error!("LLM API response has no content");
-            None
-        }
-        Err(e) => {
-            error!(
-                "LLM post-processing failed for provider '{}': {}. Falling back to original transcription.",
-                provider.id,
-                e
-            );
-            None
-        }
+    /// Whether the action records audio between `start` and `stop`. Only these
+    /// take part in gesture mode.
+    fn records_audio(&self) -> bool {
+        false
     }
 }
 
-async fn maybe_convert_chinese_variant(
-    settings: &AppSettings,
-    transcription: &str,
-) -> Option<String> {
-    // Check if language is set to Simplified or Traditional Chinese
-    let is_simplified = settings.selected_language == "zh-Hans";
-    let is_traditional = settings.selected_language == "zh-Hant";
-
-    if !is_simplified && !is_traditional {
-        debug!("selected_language is not Simplified or Traditional Chinese; skipping translation");
-        return None;
-    }
-
-    debug!(
-        "Starting Chinese translation using OpenCC for language: {}",
-        settings.selected_language
-    );
-
-    // Use OpenCC to convert based on selected language
-    let config = if is_simplified {
-        // Convert Traditional Chinese to Simplified Chinese
-        BuiltinConfig::Tw2sp
-    } else {
-        // Convert Simplified Chinese to Traditional Chinese
-        BuiltinConfig::S2twp
-    };
-
-    match OpenCC::from_config(config) {
-        Ok(converter) => {
-            let converted = converter.convert(transcription);
-            debug!(
-                "OpenCC translation completed. Input length: {}, Output length: {}",
-                transcription.len(),
-                converted.len()
-            );
-            Some(converted)
-        }
-        Err(e) => {
-            error!("Failed to initialize OpenCC converter: {}. Falling back to original transcription.", e);
-            None
-        }
-    }
-}
+// Transcribe Action
+struct TranscribeAction;
 
-async fn perform_transcription(
+pub(crate) async fn perform_transcription(
     settings: &AppSettings,
     tm: &Arc<TranscriptionManager>,
     samples: Vec<f32>,
@@ -225,22 +48,12 @@ async fn perform_transcription(
             .as_ref()
             .ok_or_else(|| "No cloud provider selected".to_string())?;
 
-        let api_key = settings
-            .cloud_stt_api_keys
-            .get(provider_id)
-            .filter(|k| !k.is_empty())
-            .ok_or_else(|| "No API key configured for cloud provider".to_string())?;
+        let api_key = secrets::resolve_api_key(&settings.cloud_stt_api_keys, provider_id);
+        if api_key.is_empty() {
+            return Err("No API key configured for cloud provider".to_string());
+        }
 
-        let default_model = match provider_id.as_str() {
-            "openai" => "whisper-1",
-            "gemini" => "gemini-2.0-flash",
-            _ => "",
-        };
-        let model = settings
-            .cloud_stt_models
-            .get(provider_id)
-            .map(|s| s.as_str())
-            .unwrap_or(default_model);
+        let model = cloud_stt_model(settings, provider_id);
 
         let language = if settings.selected_language != "auto" {
             Some(settings.selected_language.as_str())
@@ -253,78 +66,159 @@ async fn perform_transcription(
             provider_id, model, language
         );
 
-        cloud_stt::transcribe(provider_id, api_key, samples, model, language).await
+        cloud_stt::transcribe(provider_id, &api_key, samples, model, language).await
     } else {
         tm.transcribe(samples).map_err(|e| e.to_string())
     }
 }
 
-impl ShortcutAction for TranscribeAction {
-    fn start(&self, app: &AppHandle, binding_id: &str, _shortcut_str: &str) {
-        let start_time = Instant::now();
-        debug!("TranscribeAction::start called for binding: {}", binding_id);
+fn cloud_stt_model<'a>(settings: &'a AppSettings, provider_id: &str) -> &'a str {
+    let default_model = match provider_id {
+        "openai" => "whisper-1",
+        "gemini" => "gemini-2.0-flash",
+        _ => "",
+    };
+    settings
+        .cloud_stt_models
+        .get(provider_id)
+        .map(|s| s.as_str())
+        .unwrap_or(default_model)
+}
 
-        // Get the microphone mode to determine audio feedback timing
-        let settings = get_settings(app);
+/// The model `perform_transcription` uses, as recorded in history: the local
+/// model id or `provider/model` for cloud transcription.
+fn transcription_model(settings: &AppSettings, tm: &TranscriptionManager) -> Option<String> {
+    if settings.cloud_stt_enabled {
+        let provider_id = settings.cloud_stt_provider.as_ref()?;
+        Some(format!(
+            "{}/{}",
+            provider_id,
+            cloud_stt_model(settings, provider_id)
+        ))
+    } else {
+        tm.get_current_model()
+    }
+}
 
-        // Load model in the background only if not using cloud STT
-        if !settings.cloud_stt_enabled {
-            let tm = app.state::<Arc<TranscriptionManager>>();
-            tm.initiate_model_load();
-        }
+/// The language recorded in history for a transcription, when it is known:
+/// the selected one if the engine was told to use it. Automatic detection
+/// doesn't report what it found, so it's left unknown.
+fn transcription_language(settings: &AppSettings, tm: &TranscriptionManager) -> Option<String> {
+    if settings.selected_language == "auto" {
+        return None;
+    }
+    (settings.cloud_stt_enabled || tm.follows_language_setting())
+        .then(|| settings.selected_language.clone())
+}
 
-        let binding_id = binding_id.to_string();
-        change_tray_icon(app, TrayIconState::Recording);
-        show_recording_overlay(app);
+/// Current settings with the rule for the focused application applied. The
+/// focused application is only looked up when there are rules, as that runs
+/// xdotool on Linux and needs the Automation permission on macOS.
+fn settings_for_focused_app(app: &AppHandle) -> AppSettings {
+    let settings = get_settings(app);
+    if settings.app_rules.is_empty() {
+        return settings;
+    }
+    settings.with_app_overrides(focused_app_id().as_deref())
+}
 
-        let rm = app.state::<Arc<AudioRecordingManager>>();
+/// Starts recording for `binding_id`: preloads the model, updates the tray and overlay,
+/// and sequences the start sound with muting. Returns whether recording started.
+fn start_recording_session(app: &AppHandle, binding_id: &str) -> bool {
+    // Get the microphone mode to determine audio feedback timing
+    let settings = get_settings(app);
 
-        let is_always_on = settings.always_on_microphone;
-        debug!("Microphone mode - always_on: {}", is_always_on);
+    // Load model in the background only if not using cloud STT
+    if !settings.cloud_stt_enabled {
+        let tm = app.state::<Arc<TranscriptionManager>>();
+        tm.initiate_model_load();
+    }
 
-        let mut recording_started = false;
-        if is_always_on {
-            // Always-on mode: Play audio feedback immediately, then apply mute after sound finishes
-            debug!("Always-on mode: Playing audio feedback immediately");
-            let rm_clone = Arc::clone(&rm);
+    change_tray_icon(app, TrayIconState::Recording);
+    show_recording_overlay(app);
+
+    let rm = app.state::<Arc<AudioRecordingManager>>();
+
+    let is_always_on = settings.always_on_microphone;
+    debug!("Microphone mode - always_on: {}", is_always_on);
+
+    let mut recording_started = false;
+    if is_always_on {
+        // Always-on mode: Play audio feedback immediately, then apply mute after sound finishes
+        debug!("Always-on mode: Playing audio feedback immediately");
+        let rm_clone = Arc::clone(&rm);
+        let app_clone = app.clone();
+        // The blocking helper exits immediately if audio feedback is disabled,
+        // so we can always reuse this thread to ensure mute happens right after playback.
+        std::thread::spawn(move || {
+            play_feedback_sound_blocking(&app_clone, SoundType::Start);
+            rm_clone.apply_mute();
+        });
+
+        recording_started = rm.try_start_recording(binding_id);
+        debug!("Recording started: {}", recording_started);
+    } else {
+        // On-demand mode: Start recording first, then play audio feedback, then apply mute
+        // This allows the microphone to be activated before playing the sound
+        debug!("On-demand mode: Starting recording first, then audio feedback");
+        let recording_start_time = Instant::now();
+        if rm.try_start_recording(binding_id) {
+            recording_started = true;
+            debug!("Recording started in {:?}", recording_start_time.elapsed());
+            // Small delay to ensure microphone stream is active
             let app_clone = app.clone();
-            // The blocking helper exits immediately if audio feedback is disabled,
-            // so we can always reuse this thread to ensure mute happens right after playback.
+            let rm_clone = Arc::clone(&rm);
             std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                debug!("Handling delayed audio feedback/mute sequence");
+                // Helper handles disabled audio feedback by returning early, so we reuse it
+                // to keep mute sequencing consistent in every mode.
                 play_feedback_sound_blocking(&app_clone, SoundType::Start);
                 rm_clone.apply_mute();
             });
-
-            recording_started = rm.try_start_recording(&binding_id);
-            debug!("Recording started: {}", recording_started);
         } else {
-            // On-demand mode: Start recording first, then play audio feedback, then apply mute
-            // This allows the microphone to be activated before playing the sound
-            debug!("On-demand mode: Starting recording first, then audio feedback");
-            let recording_start_time = Instant::now();
-            if rm.try_start_recording(&binding_id) {
-                recording_started = true;
-                debug!("Recording started in {:?}", recording_start_time.elapsed());
-                // Small delay to ensure microphone stream is active
-                let app_clone = app.clone();
-                let rm_clone = Arc::clone(&rm);
-                std::thread::spawn(move || {
-                    std::thread::sleep(std::time::Duration::from_millis(100));
-                    debug!("Handling delayed audio feedback/mute sequence");
-                    // Helper handles disabled audio feedback by returning early, so we reuse it
-                    // to keep mute sequencing consistent in every mode.
-                    play_feedback_sound_blocking(&app_clone, SoundType::Start);
-                    rm_clone.apply_mute();
-                });
-            } else {
-                debug!("Failed to start recording");
-            }
+            debug!("Failed to start recording");
         }
+    }
 
-        if recording_started {
-            // Dynamically register the cancel shortcut in a separate task to avoid deadlock
-            shortcut::register_cancel_shortcut(app);
+    if recording_started {
+        // Dynamically register the cancel shortcut in a separate task to avoid deadlock
+        shortcut::register_cancel_shortcut(app);
+    }
+
+    recording_started
+}
+
+/// Pastes `text` and hands it to the output sinks, then hides the overlay and
+/// returns the tray to idle. Runs on a blocking thread because pasting waits on
+/// the main thread for each keystroke batch.
+fn paste_and_finish(ah: &AppHandle, text: String, settings: AppSettings) {
+    let ah = ah.clone();
+    let paste_time = Instant::now();
+    tauri::async_runtime::spawn_blocking(move || {
+        match utils::paste(text.clone(), ah.clone(), &settings) {
+            Ok(()) => debug!("Text pasted successfully in {:?}", paste_time.elapsed()),
+            Err(e) => error!("Failed to paste transcription: {}", e),
         }
+        // Extra destinations run after the paste and never block it
+        output_sinks::dispatch(&settings, &text);
+
+        // Hide the overlay after transcription is complete
+        utils::hide_recording_overlay(&ah);
+        change_tray_icon(&ah, TrayIconState::Idle);
+    });
+}
+
+impl ShortcutAction for TranscribeAction {
+    fn records_audio(&self) -> bool {
+        true
+    }
+
+    fn start(&self, app: &AppHandle, binding_id: &str, _shortcut_str: &str) {
+        let start_time = Instant::now();
+        debug!("TranscribeAction::start called for binding: {}", binding_id);
+
+        start_recording_session(app, binding_id);
 
         debug!(
             "TranscribeAction::start completed in {:?}",
@@ -370,61 +264,59 @@ impl ShortcutAction for TranscribeAction {
                     samples.len()
                 );
 
-                let transcription_time = Instant::now();
                 let samples_clone = samples.clone(); // Clone for history saving
-                let settings = get_settings(&ah);
+                let microphone = rm.active_microphone();
+                // Apply any per-application rule for the window that will receive the text
+                let settings = settings_for_focused_app(&ah);
+                // A model loaded on demand isn't part of the transcription time
+                tm.wait_for_model_load();
+                // Read before the model may be unloaded right after use
+                let model = transcription_model(&settings, &tm);
+                let language = transcription_language(&settings, &tm);
+                let transcription_time = Instant::now();
                 match perform_transcription(&settings, &tm, samples).await {
                     Ok(transcription) => {
+                        let transcription_elapsed = transcription_time.elapsed();
                         debug!(
                             "Transcription completed in {:?}: '{}'",
-                            transcription_time.elapsed(),
-                            transcription
+                            transcription_elapsed, transcription
                         );
                         if !transcription.is_empty() {
-                            let mut final_text = transcription.clone();
-                            let mut post_processed_text: Option<String> = None;
-                            let mut post_process_prompt: Option<String> = None;
-
-                            // First, check if Chinese variant conversion is needed
-                            if let Some(converted_text) =
-                                maybe_convert_chinese_variant(&settings, &transcription).await
-                            {
-                                final_text = converted_text;
-                            }
-
-                            // Then apply regular post-processing if enabled
-                            // Uses final_text which may already have Chinese conversion applied
-                            if let Some(processed_text) =
-                                maybe_post_process_transcription(&settings, &final_text).await
-                            {
-                                post_processed_text = Some(processed_text.clone());
-                                final_text = processed_text;
-
-                                // Get the prompt that was used
-                                if let Some(prompt_id) = &settings.post_process_selected_prompt_id {
-                                    if let Some(prompt) = settings
-                                        .post_process_prompts
-                                        .iter()
-                                        .find(|p| &p.id == prompt_id)
-                                    {
-                                        post_process_prompt = Some(prompt.prompt.clone());
-                                    }
-                                }
-                            } else if final_text != transcription {
-                                // Chinese conversion was applied but no LLM post-processing
-                                post_processed_text = Some(final_text.clone());
-                            }
+                            // Run the configured post-processing stages in order
+                            let post_process_time = Instant::now();
+                            let pipeline =
+                                post_process::run_pipeline(&settings, &transcription).await;
+                            let stats = TranscriptionStats {
+                                model,
+                                language,
+                                transcription_ms: transcription_elapsed.as_millis() as u64,
+                                post_process_ms: (!pipeline.stages.is_empty())
+                                    .then(|| post_process_time.elapsed().as_millis() as u64),
+                            };
+                            let final_text = pipeline.text;
+                            let post_processed_text = if final_text != transcription {
+                                Some(final_text.clone())
+                            } else {
+                                None
+                            };
+                            let post_process_prompt = pipeline.prompt;
+                            let post_process_stages = pipeline.stages;
 
                             // Save to history with post-processed text and prompt
                             let hm_clone = Arc::clone(&hm);
                             let transcription_for_history = transcription.clone();
+                            let history_settings = settings.clone();
                             tauri::async_runtime::spawn(async move {
                                 if let Err(e) = hm_clone
                                     .save_transcription(
+                                        &history_settings,
                                         samples_clone,
                                         transcription_for_history,
                                         post_processed_text,
                                         post_process_prompt,
+                                        post_process_stages,
+                                        microphone,
+                                        stats,
                                     )
                                     .await
                                 {
@@ -433,25 +325,7 @@ impl ShortcutAction for TranscribeAction {
                             });
 
                             // Paste the final text (either processed or original)
-                            let ah_clone = ah.clone();
-                            let paste_time = Instant::now();
-                            ah.run_on_main_thread(move || {
-                                match utils::paste(final_text, ah_clone.clone()) {
-                                    Ok(()) => debug!(
-                                        "Text pasted successfully in {:?}",
-                                        paste_time.elapsed()
-                                    ),
-                                    Err(e) => error!("Failed to paste transcription: {}", e),
-                                }
-                                // Hide the overlay after transcription is complete
-                                utils::hide_recording_overlay(&ah_clone);
-                                change_tray_icon(&ah_clone, TrayIconState::Idle);
-                            })
-                            .unwrap_or_else(|e| {
-                                error!("Failed to run paste on main thread: {:?}", e);
-                                utils::hide_recording_overlay(&ah);
-                                change_tray_icon(&ah, TrayIconState::Idle);
-                            });
+                            paste_and_finish(&ah, final_text, settings);
                         } else {
                             utils::hide_recording_overlay(&ah);
                             change_tray_icon(&ah, TrayIconState::Idle);
@@ -482,6 +356,331 @@ impl ShortcutAction for TranscribeAction {
     }
 }
 
+// Edit Selection Action
+//
+// Captures the selected text when recording starts, treats the spoken audio as an
+// instruction and replaces the selection with the LLM's rewrite.
+struct EditSelectionAction {
+    selection: Mutex<Option<String>>,
+}
+
+/// Shows `notification` instead of starting an edit and clears the toggle
+/// state set for `binding_id`.
+fn abort_edit_selection(app: &AppHandle, binding_id: &str, notification: &str) {
+    utils::show_overlay_notification(app, notification, &[]);
+    if let Ok(mut states) = app.state::<ManagedToggleState>().lock() {
+        states.active_toggles.insert(binding_id.to_string(), false);
+    }
+}
+
+fn build_edit_selection_prompt(template: &str, instruction: &str, selection: &str) -> String {
+    utils::expand_template(
+        template,
+        &[("instruction", instruction), ("selection", selection)],
+    )
+}
+
+async fn rewrite_selection(
+    settings: &AppSettings,
+    instruction: &str,
+    selection: &str,
+) -> Result<String, String> {
+    let (provider, model) = post_process::resolve_llm_target(settings, None, None)?;
+    let prompt =
+        build_edit_selection_prompt(&settings.edit_selection_prompt, instruction, selection);
+    let output = post_process::run_llm_prompt(settings, &provider, &model, prompt).await?;
+    if output.trim().is_empty() {
+        return Err("LLM returned an empty response".to_string());
+    }
+    Ok(output)
+}
+
+impl ShortcutAction for EditSelectionAction {
+    fn records_audio(&self) -> bool {
+        true
+    }
+
+    fn start(&self, app: &AppHandle, binding_id: &str, shortcut_str: &str) {
+        debug!(
+            "EditSelectionAction::start called for binding: {}",
+            binding_id
+        );
+
+        // Copy the selection before the overlay appears and steals focus
+        let selection = match utils::capture_selection(app, shortcut_str) {
+            Ok(selection) if !selection.trim().is_empty() => selection,
+            Ok(_) => {
+                debug!("Nothing selected, not starting an edit");
+                abort_edit_selection(app, binding_id, "noSelection");
+                return;
+            }
+            Err(e) => {
+                error!("Failed to capture selection: {}", e);
+                abort_edit_selection(app, binding_id, "selectionFailed");
+                return;
+            }
+        };
+        debug!("Captured selection of {} chars", selection.len());
+
+        if let Ok(mut guard) = self.selection.lock() {
+            *guard = Some(selection);
+        }
+
+        start_recording_session(app, binding_id);
+    }
+
+    fn stop(&self, app: &AppHandle, binding_id: &str, _shortcut_str: &str) {
+        // No selection means start gave up before recording
+        let Some(selection) = self
+            .selection
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take())
+        else {
+            return;
+        };
+        shortcut::unregister_cancel_shortcut(app);
+
+        let ah = app.clone();
+        let rm = Arc::clone(&app.state::<Arc<AudioRecordingManager>>());
+        let tm = Arc::clone(&app.state::<Arc<TranscriptionManager>>());
+        let hm = Arc::clone(&app.state::<Arc<HistoryManager>>());
+
+        change_tray_icon(app, TrayIconState::Transcribing);
+        show_transcribing_overlay(app);
+
+        rm.remove_mute();
+        play_feedback_sound(app, SoundType::Stop);
+
+        let binding_id = binding_id.to_string();
+
+        tauri::async_runtime::spawn(async move {
+            let settings = settings_for_focused_app(&ah);
+            let mut pasted = false;
+
+            if let Some(samples) = rm.stop_recording(&binding_id) {
+                let samples_clone = samples.clone();
+                let microphone = rm.active_microphone();
+                // A model loaded on demand isn't part of the transcription time
+                tm.wait_for_model_load();
+                // Read before the model may be unloaded right after use
+                let model = transcription_model(&settings, &tm);
+                let language = transcription_language(&settings, &tm);
+                let transcription_time = Instant::now();
+                match perform_transcription(&settings, &tm, samples).await {
+                    Ok(instruction) if !instruction.trim().is_empty() => {
+                        debug!("Edit instruction: '{}'", instruction);
+                        let transcription_ms = transcription_time.elapsed().as_millis() as u64;
+                        let rewrite_time = Instant::now();
+                        match rewrite_selection(&settings, &instruction, &selection).await {
+                            Ok(output) => {
+                                let stats = TranscriptionStats {
+                                    model,
+                                    language,
+                                    transcription_ms,
+                                    post_process_ms: Some(rewrite_time.elapsed().as_millis() as u64),
+                                };
+                                let hm_clone = Arc::clone(&hm);
+                                let output_for_history = output.clone();
+                                let prompt = settings.edit_selection_prompt.clone();
+                                let history_settings = settings.clone();
+                                tauri::async_runtime::spawn(async move {
+                                    if let Err(e) = hm_clone
+                                        .save_transcription(
+                                            &history_settings,
+                                            samples_clone,
+                                            instruction,
+                                            Some(output_for_history),
+                                            Some(prompt),
+                                            Vec::new(),
+                                            microphone,
+                                            stats,
+                                        )
+                                        .await
+                                    {
+                                        error!("Failed to save edit to history: {}", e);
+                                    }
+                                });
+
+                                paste_and_finish(&ah, output, settings.clone());
+                                pasted = true;
+                            }
+                            Err(e) => error!("Failed to rewrite selection: {}", e),
+                        }
+                    }
+                    Ok(_) => debug!("Empty edit instruction, leaving selection untouched"),
+                    Err(err) => debug!("Edit selection transcription error: {}", err),
+                }
+            } else {
+                debug!("No samples retrieved from recording stop");
+            }
+
+            if !pasted {
+                utils::hide_recording_overlay(&ah);
+                change_tray_icon(&ah, TrayIconState::Idle);
+            }
+
+            if let Ok(mut states) = ah.state::<ManagedToggleState>().lock() {
+                states.active_toggles.insert(binding_id, false);
+            }
+        });
+    }
+}
+
+// Meeting Action
+//
+// Toggles a meeting session on press. Meetings run far longer than a key is
+// held, so push-to-talk and gestures don't apply.
+struct MeetingAction;
+
+impl ShortcutAction for MeetingAction {
+    fn start(&self, app: &AppHandle, _binding_id: &str, _shortcut_str: &str) {
+        let mm = app.state::<Arc<MeetingManager>>();
+        if mm.stop().is_some() {
+            play_feedback_sound(app, SoundType::Stop);
+            return;
+        }
+
+        match mm.start() {
+            Ok(_) => play_feedback_sound(app, SoundType::Start),
+            Err(e) => {
+                error!("Failed to start meeting: {}", e);
+                utils::show_overlay_notification(app, "meetingFailed", &[("error", &e)]);
+            }
+        }
+    }
+
+    fn stop(&self, _app: &AppHandle, _binding_id: &str, _shortcut_str: &str) {
+        // Nothing to do on release
+    }
+}
+
+// Quick Actions
+//
+// Instant actions that run once on key press. They never record, so the
+// shortcut handler fires them on press regardless of push-to-talk or gestures.
+struct QuickAction {
+    run: fn(&AppHandle),
+}
+
+impl ShortcutAction for QuickAction {
+    fn start(&self, app: &AppHandle, _binding_id: &str, _shortcut_str: &str) {
+        (self.run)(app);
+    }
+
+    fn stop(&self, _app: &AppHandle, _binding_id: &str, _shortcut_str: &str) {
+        // Nothing to do on release
+    }
+}
+
+/// Returns the item after the one matching `is_current`, wrapping around, or
+/// the first item when none matches.
+fn next_in_cycle<T>(items: &[T], is_current: impl Fn(&T) -> bool) -> Option<&T> {
+    let next = items
+        .iter()
+        .position(is_current)
+        .map_or(0, |i| (i + 1) % items.len());
+    items.get(next)
+}
+
+fn paste_last_transcript(app: &AppHandle) {
+    let hm = app.state::<Arc<HistoryManager>>();
+    let text = match hm.get_latest_entry() {
+        Ok(Some(entry)) => utils::last_transcript_text(&entry).to_string(),
+        Ok(None) => {
+            utils::show_overlay_notification(app, "noTranscript", &[]);
+            return;
+        }
+        Err(e) => {
+            error!("Failed to fetch last transcription entry: {}", e);
+            return;
+        }
+    };
+
+    let settings = settings_for_focused_app(app);
+    let ah = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = utils::paste(text, ah, &settings) {
+            error!("Failed to paste last transcript: {}", e);
+        }
+    });
+}
+
+fn undo_last_paste(app: &AppHandle) {
+    let ah = app.clone();
+    tauri::async_runtime::spawn_blocking(move || match utils::undo_last_paste(&ah) {
+        Ok(0) => utils::show_overlay_notification(&ah, "nothingToUndo", &[]),
+        Ok(count) => debug!("Removed {} pasted characters", count),
+        Err(e) => error!("Failed to undo last paste: {}", e),
+    });
+}
+
+fn cycle_language(app: &AppHandle) {
+    let next = settings::update_settings(app, |settings| {
+        let language = next_in_cycle(&settings.language_cycle, |l| {
+            *l == settings.selected_language
+        })
+        .cloned()?;
+        settings.selected_language = language.clone();
+        Some(language)
+    });
+    let Some(language) = next else {
+        utils::show_overlay_notification(app, "noLanguages", &[]);
+        return;
+    };
+
+    if language == "auto" {
+        utils::show_overlay_notification(app, "languageAuto", &[]);
+    } else {
+        utils::show_overlay_notification(app, "language", &[("language", &language)]);
+    }
+}
+
+fn toggle_post_processing(app: &AppHandle) {
+    let key = settings::update_settings(app, |settings| {
+        settings.post_process_enabled = !settings.post_process_enabled;
+        if settings.post_process_enabled {
+            "postProcessingOn"
+        } else {
+            "postProcessingOff"
+        }
+    });
+    utils::show_overlay_notification(app, key, &[]);
+}
+
+fn cycle_post_process_prompt(app: &AppHandle) {
+    let next = settings::update_settings(app, |settings| {
+        let selected = settings.post_process_selected_prompt_id.clone();
+        let prompt = next_in_cycle(&settings.post_process_prompts, |p| {
+            Some(&p.id) == selected.as_ref()
+        })
+        .cloned()?;
+        settings.post_process_selected_prompt_id = Some(prompt.id.clone());
+        Some(prompt)
+    });
+    let Some(prompt) = next else {
+        utils::show_overlay_notification(app, "noPrompts", &[]);
+        return;
+    };
+
+    utils::show_overlay_notification(app, "prompt", &[("name", &prompt.name)]);
+}
+
+fn cycle_profile(app: &AppHandle) {
+    let settings = get_settings(app);
+    let Some(profile) = next_in_cycle(&settings.profiles, |p| {
+        settings.active_profile_id.as_deref() == Some(p.id.as_str())
+    }) else {
+        utils::show_overlay_notification(app, "noProfiles", &[]);
+        return;
+    };
+
+    match profiles::activate_profile(app, &profile.id) {
+        Ok(profile) => utils::show_overlay_notification(app, "profile", &[("name", &profile.name)]),
+        Err(e) => error!("Failed to switch profile: {}", e),
+    }
+}
+
 // Cancel Action
 struct CancelAction;
 
@@ -525,6 +724,32 @@ pub static ACTION_MAP: Lazy<HashMap<String, Arc<dyn ShortcutAction>>> = Lazy::ne
         "transcribe".to_string(),
         Arc::new(TranscribeAction) as Arc<dyn ShortcutAction>,
     );
+    map.insert(
+        "edit_selection".to_string(),
+        Arc::new(EditSelectionAction {
+            selection: Mutex::new(None),
+        }) as Arc<dyn ShortcutAction>,
+    );
+    map.insert(
+        "meeting".to_string(),
+        Arc::new(MeetingAction) as Arc<dyn ShortcutAction>,
+    );
+    for (id, run) in [
+        (
+            "paste_last_transcript",
+            paste_last_transcript as fn(&AppHandle),
+        ),
+        ("undo_last_paste", undo_last_paste),
+        ("cycle_language", cycle_language),
+        ("toggle_post_processing", toggle_post_processing),
+        ("cycle_post_process_prompt", cycle_post_process_prompt),
+        ("cycle_profile", cycle_profile),
+    ] {
+        map.insert(
+            id.to_string(),
+            Arc::new(QuickAction { run }) as Arc<dyn ShortcutAction>,
+        );
+    }
     map.insert(
         "cancel".to_string(),
         Arc::new(CancelAction) as Arc<dyn ShortcutAction>,
@@ -535,3 +760,31 @@ pub static ACTION_MAP: Lazy<HashMap<String, Arc<dyn ShortcutAction>>> = Lazy::ne
     );
     map
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_to_next_item_and_wraps() {
+        let languages = vec!["en".to_string(), "de".to_string(), "fr".to_string()];
+        assert_eq!(
+            next_in_cycle(&languages, |l| l == "en"),
+            Some(&languages[1])
+        );
+        assert_eq!(
+            next_in_cycle(&languages, |l| l == "fr"),
+            Some(&languages[0])
+        );
+    }
+
+    #[test]
+    fn cycle_starts_at_first_item_when_current_is_missing() {
+        let languages = vec!["en".to_string(), "de".to_string()];
+        assert_eq!(
+            next_in_cycle(&languages, |l| l == "ja"),
+            Some(&languages[0])
+        );
+        assert_eq!(next_in_cycle(&Vec::<String>::new(), |_| true), None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Turning interleaved multi-channel input into the mono signal we record.
+
+/// How the input channels of a device are combined into mono.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChannelSelection {
+    /// Mean of all channels
+    #[default]
+    Average,
+    /// Only this channel (zero-based). Out of range falls back to the first.
+    Channel(u16),
+    /// Whichever channel currently carries the most energy
+    Loudest,
+}
+
+/// Smoothing of the per-channel energy used by `Loudest`
+const ENERGY_SMOOTHING: f32 = 0.9;
+/// How much louder another channel has to be before `Loudest` switches to it
+const SWITCH_RATIO: f32 = 2.0;
+
+pub struct ChannelMixer {
+    selection: ChannelSelection,
+    channels: usize,
+    energy: Vec<f32>,
+    loudest: usize,
+}
+
+impl ChannelMixer {
+    pub fn new(selection: ChannelSelection, channels: usize) -> Self {
+        let channels = channels.max(1);
+        if let ChannelSelection::Channel(channel) = selection {
+            if channel as usize >= channels {
+                log::warn!(
+                    "Channel {} not available on a {}-channel device, using channel 0",
+                    channel,
+                    channels
+                );
+            }
+        }
+
+        Self {
+            selection,
+            channels,
+            energy: vec![0.0; channels],
+            loudest: 0,
+        }
+    }
+
+    /// Appends one mono sample per interleaved frame of `data` to `out`.
+    pub fn mix(&mut self, data: &[f32], out: &mut Vec<f32>) {
+        let channels = self.channels;
+        if channels == 1 {
+            out.extend_from_slice(data);
+            return;
+        }
+
+        let frames = data.chunks_exact(channels);
+        out.reserve(frames.len());
+        match self.selection {
+            ChannelSelection::Average => {
+                out.extend(frames.map(|frame| frame.iter().sum::<f32>() / channels as f32));
+            }
+            ChannelSelection::Channel(channel) => {
+                let channel = if (channel as usize) < channels {
+                    channel as usize
+                } else {
+                    0
+                };
+                out.extend(frames.map(|frame| frame[channel]));
+            }
+            ChannelSelection::Loudest => {
+                let channel = self.update_loudest(data);
+                out.extend(frames.map(|frame| frame[channel]));
+            }
+        }
+    }
+
+    /// Tracks the energy of every channel and returns the one to use for
+    /// this buffer. Switching needs a clear margin so the output doesn't flip
+    /// between channels of similar level.
+    fn update_loudest(&mut self, data: &[f32]) -> usize {
+        let frame_count = data.len() / self.channels;
+        if frame_count == 0 {
+            return self.loudest;
+        }
+
+        for (channel, energy) in self.energy.iter_mut().enumerate() {
+            let power = data
+                .iter()
+                .skip(channel)
+                .step_by(self.channels)
+                .take(frame_count)
+                .map(|s| s * s)
+                .sum::<f32>()
+                / frame_count as f32;
+            *energy = ENERGY_SMOOTHING * *energy + (1.0 - ENERGY_SMOOTHING) * power;
+        }
+
+        let (candidate, &energy) = self
+            .energy
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .unwrap();
+        if energy > SWITCH_RATIO * self.energy[self.loudest] {
+            self.loudest = candidate;
+        }
+        self.loudest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two channels: a quiet constant on the first, a louder one on the second
+    fn stereo(frames: usize, left: f32, right: f32) -> Vec<f32> {
+        (0..frames).flat_map(|_| [left, right]).collect()
+    }
+
+    fn mix(mixer: &mut ChannelMixer, data: &[f32]) -> Vec<f32> {
+        let mut out = Vec::new();
+        mixer.mix(data, &mut out);
+        out
+    }
+
+    #[test]
+    fn average_and_specific_channel() {
+        let data = stereo(4, 0.2, 0.6);
+
+        let mut average = ChannelMixer::new(ChannelSelection::Average, 2);
+        assert!(mix(&mut average, &data)
+            .iter()
+            .all(|s| (s - 0.4).abs() < 1e-6));
+
+        let mut second = ChannelMixer::new(ChannelSelection::Channel(1), 2);
+        assert_eq!(mix(&mut second, &data), vec![0.6; 4]);
+
+        // Missing channels fall back to the first one
+        let mut missing = ChannelMixer::new(ChannelSelection::Channel(5), 2);
+        assert_eq!(mix(&mut missing, &data), vec![0.2; 4]);
+    }
+
+    #[test]
+    fn loudest_follows_the_active_channel_with_hysteresis() {
+        let mut mixer = ChannelMixer::new(ChannelSelection::Loudest, 2);
+
+        let mut last = Vec::new();
+        for _ in 0..20 {
+            last = mix(&mut mixer, &stereo(160, 0.01, 0.5));
+        }
+        assert_eq!(last, vec![0.5; 160]);
+
+        // A slightly louder first channel isn't enough to switch back
+        for _ in 0..50 {
+            last = mix(&mut mixer, &stereo(160, 0.6, 0.5));
+        }
+        assert_eq!(last, vec![0.5; 160]);
+
+        for _ in 0..50 {
+            last = mix(&mut mixer, &stereo(160, 0.6, 0.01));
+        }
+        assert_eq!(last, vec![0.6; 160]);
+    }
+}
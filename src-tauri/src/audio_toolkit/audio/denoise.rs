@@ -0,0 +1,410 @@
+//! Noise suppression for 16 kHz mono speech.
+//!
+//! `SpectralSubtraction` tracks the stationary noise spectrum (fans, hum,
+//! keyboard hiss) and removes it frame by frame. With the `rnnoise` feature an
+//! RNNoise model is available as well, which also copes with non-stationary
+//! noise at the cost of a larger binary.
+
+use rustfft::{num_complex::Complex32, Fft, FftPlanner};
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use super::visualizer::hann_window;
+
+const WINDOW_SIZE: usize = 512;
+const HOP_SIZE: usize = WINDOW_SIZE / 2;
+/// How much more noise than estimated is subtracted, to keep musical noise down
+const OVER_SUBTRACTION: f32 = 3.0;
+/// Lowest gain applied to a bin (about -20 dB), so speech never drops out
+const SPECTRAL_FLOOR: f32 = 0.1;
+/// Frames averaged into the initial noise estimate
+const NOISE_INIT_FRAMES: usize = 8;
+/// Smoothing of the noise estimate in bins that look like noise
+const NOISE_SMOOTHING: f32 = 0.98;
+/// Bins this far above the noise estimate are taken as speech and don't update it
+const SPEECH_RATIO: f32 = 4.0;
+
+/// A streaming noise suppressor. Output lags the input by a fixed latency, so
+/// every call returns exactly as many samples as it was given.
+pub trait NoiseSuppressor: Send {
+    fn process(&mut self, frame: &mut [f32]);
+    /// Samples the output lags behind the input.
+    fn latency(&self) -> usize;
+    /// Clears the buffered audio before a new stream.
+    fn reset(&mut self);
+
+    /// Ends the stream and returns the last `latency()` samples still held back.
+    fn flush(&mut self) -> Vec<f32> {
+        let mut tail = vec![0.0; self.latency()];
+        self.process(&mut tail);
+        tail
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoiseSuppressionMethod {
+    #[default]
+    Off,
+    SpectralSubtraction,
+    /// Falls back to spectral subtraction when built without `rnnoise`
+    Rnnoise,
+}
+
+/// Creates the suppressor for `method`, or `None` when it's off.
+pub fn create_noise_suppressor(
+    method: NoiseSuppressionMethod,
+    sample_rate: u32,
+) -> Option<Box<dyn NoiseSuppressor>> {
+    match method {
+        NoiseSuppressionMethod::Off => None,
+        NoiseSuppressionMethod::SpectralSubtraction => {
+            Some(Box::new(SpectralSubtraction::new(sample_rate)))
+        }
+        #[cfg(feature = "rnnoise")]
+        NoiseSuppressionMethod::Rnnoise => Some(Box::new(rnnoise::Rnnoise::new(sample_rate))),
+        #[cfg(not(feature = "rnnoise"))]
+        NoiseSuppressionMethod::Rnnoise => {
+            log::warn!("Built without RNNoise support, using spectral subtraction");
+            Some(Box::new(SpectralSubtraction::new(sample_rate)))
+        }
+    }
+}
+
+/// Short-time spectral subtraction with 50% overlap-add.
+///
+/// The noise estimate outlives `reset()`. Recordings usually start with
+/// speech, so re-learning the room from their first frames would take the
+/// voice for noise.
+pub struct SpectralSubtraction {
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    /// Square-root Hann, applied on analysis and synthesis
+    window: Vec<f32>,
+    spectrum: Vec<Complex32>,
+    noise_power: Vec<f32>,
+    noise_frames: usize,
+    /// Last `WINDOW_SIZE` input samples
+    input: Vec<f32>,
+    /// Samples waiting for the next hop
+    pending: Vec<f32>,
+    overlap: Vec<f32>,
+    output: VecDeque<f32>,
+}
+
+impl SpectralSubtraction {
+    /// The analysis is tuned for 16 kHz; other rates work with coarser bins.
+    pub fn new(_sample_rate: u32) -> Self {
+        let mut planner = FftPlanner::<f32>::new();
+        let mut suppressor = Self {
+            fft: planner.plan_fft_forward(WINDOW_SIZE),
+            ifft: planner.plan_fft_inverse(WINDOW_SIZE),
+            window: hann_window(WINDOW_SIZE).iter().map(|w| w.sqrt()).collect(),
+            spectrum: vec![Complex32::new(0.0, 0.0); WINDOW_SIZE],
+            noise_power: vec![0.0; WINDOW_SIZE / 2 + 1],
+            noise_frames: 0,
+            input: vec![0.0; WINDOW_SIZE],
+            pending: Vec::with_capacity(HOP_SIZE),
+            overlap: vec![0.0; WINDOW_SIZE],
+            output: VecDeque::with_capacity(WINDOW_SIZE * 2),
+        };
+        suppressor.reset();
+        suppressor
+    }
+
+    fn process_window(&mut self) {
+        self.input.copy_within(HOP_SIZE.., 0);
+        self.input[WINDOW_SIZE - HOP_SIZE..].copy_from_slice(&self.pending);
+        self.pending.clear();
+
+        for (i, bin) in self.spectrum.iter_mut().enumerate() {
+            *bin = Complex32::new(self.input[i] * self.window[i], 0.0);
+        }
+        self.fft.process(&mut self.spectrum);
+
+        let bins = WINDOW_SIZE / 2 + 1;
+        let initializing = self.noise_frames < NOISE_INIT_FRAMES;
+        for k in 0..bins {
+            let power = self.spectrum[k].norm_sqr();
+
+            let noise = &mut self.noise_power[k];
+            if initializing {
+                *noise += power / NOISE_INIT_FRAMES as f32;
+            } else if power < SPEECH_RATIO * *noise {
+                *noise = NOISE_SMOOTHING * *noise + (1.0 - NOISE_SMOOTHING) * power;
+            }
+
+            let gain = if power > 0.0 {
+                (1.0 - OVER_SUBTRACTION * *noise / power)
+                    .max(SPECTRAL_FLOOR * SPECTRAL_FLOOR)
+                    .sqrt()
+            } else {
+                SPECTRAL_FLOOR
+            };
+            self.spectrum[k] *= gain;
+            // Keep the spectrum conjugate-symmetric so the output stays real
+            if k > 0 && k < WINDOW_SIZE / 2 {
+                self.spectrum[WINDOW_SIZE - k] = self.spectrum[k].conj();
+            }
+        }
+        self.noise_frames += 1;
+
+        self.ifft.process(&mut self.spectrum);
+        let scale = 1.0 / WINDOW_SIZE as f32;
+        for (i, overlap) in self.overlap.iter_mut().enumerate() {
+            *overlap += self.spectrum[i].re * scale * self.window[i];
+        }
+
+        // The first hop is complete now
+        self.output.extend(self.overlap.drain(..HOP_SIZE));
+        self.overlap.resize(WINDOW_SIZE, 0.0);
+    }
+}
+
+impl NoiseSuppressor for SpectralSubtraction {
+    fn process(&mut self, frame: &mut [f32]) {
+        for &sample in frame.iter() {
+            self.pending.push(sample);
+            if self.pending.len() == HOP_SIZE {
+                self.process_window();
+            }
+        }
+
+        for sample in frame.iter_mut() {
+            *sample = self.output.pop_front().unwrap_or(0.0);
+        }
+    }
+
+    /// One hop waiting for the window to fill, plus one hop queued so the
+    /// output never runs dry between window boundaries.
+    fn latency(&self) -> usize {
+        2 * HOP_SIZE
+    }
+
+    fn reset(&mut self) {
+        self.input.fill(0.0);
+        self.pending.clear();
+        self.overlap.fill(0.0);
+        self.output.clear();
+        self.output.extend(std::iter::repeat(0.0).take(HOP_SIZE));
+    }
+
+    fn flush(&mut self) -> Vec<f32> {
+        // The padding is silence, not noise, so keep it out of the estimate
+        let noise_power = self.noise_power.clone();
+        let noise_frames = self.noise_frames;
+        let mut tail = vec![0.0; self.latency()];
+        self.process(&mut tail);
+        self.noise_power = noise_power;
+        self.noise_frames = noise_frames;
+        tail
+    }
+}
+
+#[cfg(feature = "rnnoise")]
+mod rnnoise {
+    use super::NoiseSuppressor;
+    use nnnoiseless::DenoiseState;
+    use std::collections::VecDeque;
+
+    /// RNNoise runs at 48 kHz; 16 kHz input is upsampled by 3 around it
+    const UPSAMPLE: usize = 3;
+    const INPUT_FRAME: usize = DenoiseState::FRAME_SIZE / UPSAMPLE;
+
+    pub struct Rnnoise {
+        state: Box<DenoiseState<'static>>,
+        input: Vec<f32>,
+        last_sample: f32,
+        upsampled: Vec<f32>,
+        denoised: Vec<f32>,
+        output: VecDeque<f32>,
+    }
+
+    impl Rnnoise {
+        pub fn new(sample_rate: u32) -> Self {
+            if sample_rate != 16000 {
+                log::warn!("RNNoise expects 16 kHz input, got {} Hz", sample_rate);
+            }
+            let mut rnnoise = Self {
+                state: DenoiseState::new(),
+                input: Vec::with_capacity(INPUT_FRAME),
+                last_sample: 0.0,
+                upsampled: vec![0.0; DenoiseState::FRAME_SIZE],
+                denoised: vec![0.0; DenoiseState::FRAME_SIZE],
+                output: VecDeque::new(),
+            };
+            rnnoise.reset();
+            rnnoise
+        }
+
+        fn process_frame(&mut self) {
+            // Linear interpolation up, scaled to the i16 range RNNoise expects
+            for (i, &sample) in self.input.iter().enumerate() {
+                for step in 0..UPSAMPLE {
+                    let t = (step + 1) as f32 / UPSAMPLE as f32;
+                    let value = self.last_sample + (sample - self.last_sample) * t;
+                    self.upsampled[i * UPSAMPLE + step] = value * i16::MAX as f32;
+                }
+                self.last_sample = sample;
+            }
+
+            self.state
+                .process_frame(&mut self.denoised, &self.upsampled);
+
+            // Average each group of three before decimating to limit aliasing
+            for group in self.denoised.chunks_exact(UPSAMPLE) {
+                let mean = group.iter().sum::<f32>() / UPSAMPLE as f32;
+                self.output.push_back(mean / i16::MAX as f32);
+            }
+            self.input.clear();
+        }
+    }
+
+    impl NoiseSuppressor for Rnnoise {
+        fn process(&mut self, frame: &mut [f32]) {
+            for &sample in frame.iter() {
+                self.input.push(sample);
+                if self.input.len() == INPUT_FRAME {
+                    self.process_frame();
+                }
+            }
+
+            for sample in frame.iter_mut() {
+                *sample = self.output.pop_front().unwrap_or(0.0);
+            }
+        }
+
+        fn latency(&self) -> usize {
+            INPUT_FRAME
+        }
+
+        fn reset(&mut self) {
+            self.state = DenoiseState::new();
+            self.input.clear();
+            self.last_sample = 0.0;
+            self.output.clear();
+            self.output.extend(std::iter::repeat(0.0).take(INPUT_FRAME));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_toolkit::audio::rms;
+    use std::f32::consts::PI;
+
+    const RATE: u32 = 16000;
+
+    /// Deterministic white noise in [-amplitude, amplitude]
+    fn noise(len: usize, amplitude: f32) -> Vec<f32> {
+        let mut state = 0x1234_5678u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    fn run(suppressor: &mut dyn NoiseSuppressor, input: &[f32]) -> Vec<f32> {
+        let mut output = input.to_vec();
+        for frame in output.chunks_mut(480) {
+            suppressor.process(frame);
+        }
+        output
+    }
+
+    #[test]
+    fn output_length_matches_input() {
+        let mut suppressor = SpectralSubtraction::new(RATE);
+        for len in [1, 100, 480, 1000] {
+            let mut frame = vec![0.1; len];
+            suppressor.process(&mut frame);
+            assert_eq!(frame.len(), len);
+        }
+    }
+
+    #[test]
+    fn stationary_noise_is_attenuated() {
+        let input = noise(RATE as usize * 2, 0.05);
+        let mut suppressor = SpectralSubtraction::new(RATE);
+        let output = run(&mut suppressor, &input);
+
+        let tail = RATE as usize..;
+        let ratio = rms(&output[tail.clone()]) / rms(&input[tail]);
+        assert!(ratio < 0.3, "ratio {}", ratio);
+    }
+
+    #[test]
+    fn tone_over_noise_survives() {
+        let len = RATE as usize * 3;
+        let hiss = noise(len, 0.02);
+        // Noise alone for the first second, then a tone starts on top of it
+        let input: Vec<f32> = (0..len)
+            .map(|i| {
+                let tone = if i >= RATE as usize {
+                    0.3 * (2.0 * PI * 440.0 * i as f32 / RATE as f32).sin()
+                } else {
+                    0.0
+                };
+                tone + hiss[i]
+            })
+            .collect();
+
+        let mut suppressor = SpectralSubtraction::new(RATE);
+        let output = run(&mut suppressor, &input);
+
+        let tone_rms = 0.3 * std::f32::consts::FRAC_1_SQRT_2;
+        let tail = rms(&output[RATE as usize * 2..]);
+        assert!(tail > tone_rms * 0.8, "rms {}", tail);
+        assert!(tail < tone_rms * 1.2, "rms {}", tail);
+    }
+
+    #[test]
+    fn noise_profile_survives_reset() {
+        let mut suppressor = SpectralSubtraction::new(RATE);
+        run(&mut suppressor, &noise(RATE as usize, 0.02));
+        suppressor.reset();
+
+        // The next recording starts straight into the tone
+        let len = RATE as usize / 2;
+        let hiss = noise(len, 0.02);
+        let input: Vec<f32> = (0..len)
+            .map(|i| 0.3 * (2.0 * PI * 440.0 * i as f32 / RATE as f32).sin() + hiss[i])
+            .collect();
+        let output = run(&mut suppressor, &input);
+
+        let tone_rms = 0.3 * std::f32::consts::FRAC_1_SQRT_2;
+        let start = rms(&output[suppressor.latency()..RATE as usize / 4]);
+        assert!(start > tone_rms * 0.8, "rms {}", start);
+    }
+
+    #[test]
+    fn flush_returns_the_end_of_the_stream() {
+        let len = RATE as usize * 2;
+        let hiss = noise(len, 0.02);
+        let input: Vec<f32> = (0..len)
+            .map(|i| {
+                let tone = if i >= RATE as usize {
+                    0.3 * (2.0 * PI * 440.0 * i as f32 / RATE as f32).sin()
+                } else {
+                    0.0
+                };
+                tone + hiss[i]
+            })
+            .collect();
+
+        let mut suppressor = SpectralSubtraction::new(RATE);
+        let mut output = run(&mut suppressor, &input);
+        output.extend(suppressor.flush());
+
+        // Everything after the latency lines up with the input, tail included
+        let delayed = &output[suppressor.latency()..];
+        assert_eq!(delayed.len(), input.len());
+        let tone_rms = 0.3 * std::f32::consts::FRAC_1_SQRT_2;
+        let end = rms(&delayed[len - HOP_SIZE..]);
+        assert!(end > tone_rms * 0.8, "rms {}", end);
+    }
+}
@@ -0,0 +1,310 @@
+//! Level processing applied to recorded audio before VAD and transcription.
+//!
+//! Frames go through an optional high-pass filter, noise suppression and an
+//! automatic gain control with a peak limiter while recording. The finished
+//! buffer can then be normalized to a fixed loudness.
+
+use std::f32::consts::PI;
+
+use super::denoise::{create_noise_suppressor, NoiseSuppressionMethod, NoiseSuppressor};
+
+/// Rumble below this frequency is removed by the high-pass filter
+const HIGH_PASS_CUTOFF_HZ: f32 = 80.0;
+/// Loudness the AGC and the normalization aim for (about -20 dBFS)
+const TARGET_RMS: f32 = 0.1;
+/// Samples never leave the limiter louder than this
+const PEAK_CEILING: f32 = 0.9;
+/// Most the AGC or the normalization will amplify (+20 dB)
+const MAX_GAIN: f32 = 10.0;
+/// Least the AGC will attenuate to (-12 dB)
+const MIN_GAIN: f32 = 0.25;
+/// Frames quieter than this are treated as silence and don't raise the gain
+const SILENCE_RMS: f32 = 0.002;
+
+/// Which stages run. Everything is off by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DspConfig {
+    pub high_pass: bool,
+    pub noise_suppression: NoiseSuppressionMethod,
+    pub agc: bool,
+    pub normalize: bool,
+}
+
+/// Second-order Butterworth high-pass (RBJ cookbook biquad).
+pub struct HighPassFilter {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl HighPassFilter {
+    pub fn new(sample_rate: u32, cutoff_hz: f32) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * std::f32::consts::FRAC_1_SQRT_2);
+        let cos_w0 = w0.cos();
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b0: (1.0 + cos_w0) / 2.0 / a0,
+            b1: -(1.0 + cos_w0) / a0,
+            b2: (1.0 + cos_w0) / 2.0 / a0,
+            a1: -2.0 * cos_w0 / a0,
+            a2: (1.0 - alpha) / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            let x = *sample;
+            let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+                - self.a1 * self.y1
+                - self.a2 * self.y2;
+            self.x2 = self.x1;
+            self.x1 = x;
+            self.y2 = self.y1;
+            self.y1 = y;
+            *sample = y;
+        }
+    }
+}
+
+/// RMS-driven gain control followed by a peak limiter. The gain drops quickly
+/// when the input gets loud and recovers slowly, so speech isn't pumped.
+pub struct AutomaticGainControl {
+    gain: f32,
+    limiter_gain: f32,
+    /// Per-sample recovery of the limiter towards unity
+    limiter_release: f32,
+}
+
+impl AutomaticGainControl {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            gain: 1.0,
+            limiter_gain: 1.0,
+            // ~50 ms release time
+            limiter_release: 1.0 - (-1.0 / (0.05 * sample_rate as f32)).exp(),
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if samples.is_empty() {
+            return;
+        }
+
+        let rms = rms(samples);
+        let desired = if rms < SILENCE_RMS {
+            // Don't pull up the noise floor between words
+            self.gain.min(1.0)
+        } else {
+            (TARGET_RMS / rms).clamp(MIN_GAIN, MAX_GAIN)
+        };
+        // Fast attack, slow release
+        let smoothing = if desired < self.gain { 0.5 } else { 0.05 };
+        let next_gain = self.gain + (desired - self.gain) * smoothing;
+
+        // Ramp across the frame to avoid zipper noise
+        let step = (next_gain - self.gain) / samples.len() as f32;
+        for sample in samples.iter_mut() {
+            self.gain += step;
+            *sample = self.limit(*sample * self.gain);
+        }
+        self.gain = next_gain;
+    }
+
+    fn limit(&mut self, sample: f32) -> f32 {
+        let peak = sample.abs();
+        let target = if peak > PEAK_CEILING {
+            PEAK_CEILING / peak
+        } else {
+            1.0
+        };
+
+        self.limiter_gain = if target < self.limiter_gain {
+            target
+        } else {
+            self.limiter_gain + (target - self.limiter_gain) * self.limiter_release
+        };
+        sample * self.limiter_gain
+    }
+}
+
+/// Scales the whole buffer to the target loudness without exceeding the peak
+/// ceiling. Near-silent buffers are left alone.
+pub fn normalize_loudness(samples: &mut [f32]) {
+    let rms = rms(samples);
+    if rms < SILENCE_RMS {
+        return;
+    }
+
+    let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+    let gain = (TARGET_RMS / rms).min(PEAK_CEILING / peak).min(MAX_GAIN);
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+}
+
+/// Root mean square level of `samples`, 0 when empty.
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+}
+
+/// The configured stages and their state, carried from one recording to the
+/// next.
+pub struct DspChain {
+    sample_rate: u32,
+    config: DspConfig,
+    high_pass: HighPassFilter,
+    denoiser: Option<Box<dyn NoiseSuppressor>>,
+    agc: AutomaticGainControl,
+}
+
+impl DspChain {
+    pub fn new(sample_rate: u32, config: DspConfig) -> Self {
+        Self {
+            sample_rate,
+            config,
+            high_pass: HighPassFilter::new(sample_rate, HIGH_PASS_CUTOFF_HZ),
+            denoiser: create_noise_suppressor(config.noise_suppression, sample_rate),
+            agc: AutomaticGainControl::new(sample_rate),
+        }
+    }
+
+    /// Prepares for a new recording with `config`. The noise suppressor keeps
+    /// the room it has learned unless the method changed.
+    pub fn restart(&mut self, config: DspConfig) {
+        if config.noise_suppression != self.config.noise_suppression {
+            self.denoiser = create_noise_suppressor(config.noise_suppression, self.sample_rate);
+        } else if let Some(denoiser) = &mut self.denoiser {
+            denoiser.reset();
+        }
+        self.high_pass = HighPassFilter::new(self.sample_rate, HIGH_PASS_CUTOFF_HZ);
+        self.agc = AutomaticGainControl::new(self.sample_rate);
+        self.config = config;
+    }
+
+    /// Runs the per-frame stages in place.
+    pub fn process(&mut self, frame: &mut [f32]) {
+        if self.config.high_pass {
+            self.high_pass.process(frame);
+        }
+        // Before the AGC, so the gain isn't driven by noise
+        if let Some(denoiser) = &mut self.denoiser {
+            denoiser.process(frame);
+        }
+        if self.config.agc {
+            self.agc.process(frame);
+        }
+    }
+
+    /// Returns the end of the recording the noise suppressor still holds back,
+    /// processed by the stages after it.
+    pub fn flush(&mut self) -> Vec<f32> {
+        let Some(denoiser) = &mut self.denoiser else {
+            return Vec::new();
+        };
+        let mut tail = denoiser.flush();
+        if self.config.agc {
+            self.agc.process(&mut tail);
+        }
+        tail
+    }
+
+    /// Runs the stages that need the whole recording.
+    pub fn finish(&self, samples: &mut [f32]) {
+        if self.config.normalize {
+            normalize_loudness(samples);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    fn sine(freq: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+        let len = (RATE as f32 * seconds) as usize;
+        (0..len)
+            .map(|i| amplitude * (2.0 * PI * freq * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    fn process_in_frames(agc: &mut AutomaticGainControl, samples: &mut [f32]) {
+        for frame in samples.chunks_mut(480) {
+            agc.process(frame);
+        }
+    }
+
+    #[test]
+    fn high_pass_removes_rumble_and_keeps_speech() {
+        let mut rumble = sine(20.0, 0.5, 1.0);
+        let mut voice = sine(1000.0, 0.5, 1.0);
+
+        let mut filter = HighPassFilter::new(RATE, HIGH_PASS_CUTOFF_HZ);
+        filter.process(&mut rumble);
+        let mut filter = HighPassFilter::new(RATE, HIGH_PASS_CUTOFF_HZ);
+        filter.process(&mut voice);
+
+        // Skip the filter's settling time
+        let tail = RATE as usize / 2..;
+        assert!(rms(&rumble[tail.clone()]) < 0.5 * 0.1);
+        assert!(rms(&voice[tail]) > 0.5 * 0.7 * 0.95);
+    }
+
+    #[test]
+    fn agc_raises_quiet_input_towards_target() {
+        let mut quiet = sine(300.0, 0.02, 3.0);
+        let mut agc = AutomaticGainControl::new(RATE);
+        process_in_frames(&mut agc, &mut quiet);
+
+        let settled = rms(&quiet[RATE as usize * 2..]);
+        assert!(settled > TARGET_RMS * 0.8, "rms {}", settled);
+        assert!(settled < TARGET_RMS * 1.2, "rms {}", settled);
+    }
+
+    #[test]
+    fn agc_limits_hot_input() {
+        let mut hot = sine(300.0, 1.5, 1.0);
+        let mut agc = AutomaticGainControl::new(RATE);
+        process_in_frames(&mut agc, &mut hot);
+
+        assert!(hot.iter().all(|s| s.abs() <= PEAK_CEILING + 1e-6));
+    }
+
+    #[test]
+    fn agc_does_not_amplify_silence() {
+        let mut hiss = sine(3000.0, 0.001, 1.0);
+        let mut agc = AutomaticGainControl::new(RATE);
+        process_in_frames(&mut agc, &mut hiss);
+
+        assert!(rms(&hiss) <= 0.001);
+    }
+
+    #[test]
+    fn normalization_hits_target_within_peak_ceiling() {
+        let mut quiet = sine(440.0, 0.05, 1.0);
+        normalize_loudness(&mut quiet);
+        assert!((rms(&quiet) - TARGET_RMS).abs() < 0.005);
+
+        // A single spike limits how far the rest can be raised
+        let mut spiky = sine(440.0, 0.01, 1.0);
+        spiky[100] = 0.5;
+        normalize_loudness(&mut spiky);
+        assert!(spiky.iter().all(|s| s.abs() <= PEAK_CEILING + 1e-6));
+    }
+}
@@ -0,0 +1,302 @@
+//! Writing recordings to disk as WAV, FLAC or Ogg Opus.
+
+use anyhow::{anyhow, Result};
+use flacenc::component::BitRepr;
+use flacenc::error::Verify;
+use hound::{WavReader, WavSpec, WavWriter};
+use ogg::{PacketWriteEndInfo, PacketWriter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// Recordings are always mono at this rate
+const SAMPLE_RATE: u32 = 16000;
+/// 20 ms, the frame size Opus is tuned for
+const OPUS_FRAME_SAMPLES: usize = 320;
+/// Largest packet Opus recommends reserving room for
+const OPUS_MAX_PACKET: usize = 4000;
+/// Ogg Opus granule positions always count 48 kHz samples
+const OPUS_GRANULE_RATE: u64 = 48000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioFileFormat {
+    /// 16-bit PCM
+    Wav,
+    /// Lossless, roughly half the size of WAV
+    Flac,
+    /// Lossy speech codec in an Ogg container
+    Opus { bitrate_kbps: u32 },
+}
+
+impl AudioFileFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFileFormat::Wav => "wav",
+            AudioFileFormat::Flac => "flac",
+            AudioFileFormat::Opus { .. } => "opus",
+        }
+    }
+}
+
+/// Save mono 16 kHz samples to `file_path` in `format`
+pub fn save_audio_file<P: AsRef<Path>>(
+    file_path: P,
+    samples: &[f32],
+    format: AudioFileFormat,
+) -> Result<()> {
+    let path = file_path.as_ref();
+    match format {
+        AudioFileFormat::Wav => write_wav(path, samples)?,
+        AudioFileFormat::Flac => write_flac(path, samples)?,
+        AudioFileFormat::Opus { bitrate_kbps } => write_opus(path, samples, bitrate_kbps)?,
+    }
+    log::debug!("Saved {} file: {:?}", format.extension(), path);
+    Ok(())
+}
+
+/// Read a mono WAV file written by `save_audio_file` back into f32 samples
+pub fn read_wav_file<P: AsRef<Path>>(file_path: P) -> Result<Vec<f32>> {
+    let mut reader = WavReader::open(file_path.as_ref())?;
+    let spec = reader.spec();
+    if spec.channels != 1 || spec.sample_rate != SAMPLE_RATE {
+        return Err(anyhow!(
+            "Unexpected WAV layout: {} channels at {} Hz",
+            spec.channels,
+            spec.sample_rate
+        ));
+    }
+
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    Ok(samples)
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample * i16::MAX as f32).clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+fn write_wav(path: &Path, samples: &[f32]) -> Result<()> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut writer = WavWriter::create(path, spec)?;
+    for &sample in samples {
+        writer.write_sample(to_i16(sample))?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+fn write_flac(path: &Path, samples: &[f32]) -> Result<()> {
+    let pcm: Vec<i32> = samples.iter().map(|&s| to_i16(s) as i32).collect();
+
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .map_err(|(_, e)| anyhow!("Invalid FLAC encoder config: {:?}", e))?;
+    let source = flacenc::source::MemSource::from_samples(&pcm, 1, 16, SAMPLE_RATE as usize);
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| anyhow!("Failed to encode FLAC: {:?}", e))?;
+
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream
+        .write(&mut sink)
+        .map_err(|e| anyhow!("Failed to write FLAC: {:?}", e))?;
+    std::fs::write(path, sink.as_slice())?;
+    Ok(())
+}
+
+fn write_opus(path: &Path, samples: &[f32], bitrate_kbps: u32) -> Result<()> {
+    let mut encoder =
+        opus::Encoder::new(SAMPLE_RATE, opus::Channels::Mono, opus::Application::Voip)?;
+    encoder.set_bitrate(opus::Bitrate::Bits(bitrate_kbps as i32 * 1000))?;
+
+    // Granule positions are in 48 kHz samples, ours are 16 kHz
+    let granule_scale = OPUS_GRANULE_RATE / SAMPLE_RATE as u64;
+    let lookahead = encoder.get_lookahead()? as usize;
+    let pre_skip = lookahead as u64 * granule_scale;
+
+    let mut writer = PacketWriter::new(BufWriter::new(File::create(path)?));
+    let serial = 1;
+    writer.write_packet(
+        opus_head(pre_skip as u16),
+        serial,
+        PacketWriteEndInfo::EndPage,
+        0,
+    )?;
+    writer.write_packet(opus_tags(), serial, PacketWriteEndInfo::EndPage, 0)?;
+
+    // The encoder holds back `lookahead` samples, so keep feeding silence
+    // until the last real sample has come out
+    let frame_count = (samples.len() + lookahead)
+        .div_ceil(OPUS_FRAME_SAMPLES)
+        .max(1);
+    let mut frame = [0f32; OPUS_FRAME_SAMPLES];
+    let mut packet = [0u8; OPUS_MAX_PACKET];
+
+    for index in 0..frame_count {
+        let start = (index * OPUS_FRAME_SAMPLES).min(samples.len());
+        let end = (start + OPUS_FRAME_SAMPLES).min(samples.len());
+        frame.fill(0.0);
+        frame[..end - start].copy_from_slice(&samples[start..end]);
+
+        let len = encoder.encode_float(&frame, &mut packet)?;
+        let last = index + 1 == frame_count;
+        // Pages count every decoded sample, pre-skip included. The last one
+        // instead marks where the audio ends, so players drop the padding.
+        let granule = if last {
+            pre_skip + samples.len() as u64 * granule_scale
+        } else {
+            (index as u64 + 1) * OPUS_FRAME_SAMPLES as u64 * granule_scale
+        };
+        writer.write_packet(
+            packet[..len].to_vec(),
+            serial,
+            if last {
+                PacketWriteEndInfo::EndStream
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            },
+            granule,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Identification header, RFC 7845 section 5.1
+fn opus_head(pre_skip: u16) -> Vec<u8> {
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1); // version
+    head.push(1); // channels
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // mono/stereo channel mapping
+    head
+}
+
+/// Comment header, RFC 7845 section 5.2
+fn opus_tags() -> Vec<u8> {
+    let vendor = b"Handy";
+    let mut tags = Vec::with_capacity(16 + vendor.len());
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes()); // no user comments
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_toolkit::audio::rms;
+
+    fn tone(samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|i| (i as f32 * 440.0 * std::f32::consts::TAU / SAMPLE_RATE as f32).sin() * 0.5)
+            .collect()
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("handy-encoding-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn wav_round_trips() {
+        let dir = temp_dir("wav");
+        let samples = tone(SAMPLE_RATE as usize);
+        let path = dir.join("tone.wav");
+        save_audio_file(&path, &samples, AudioFileFormat::Wav).unwrap();
+
+        // Lossless up to 16-bit quantisation
+        let read = read_wav_file(&path).unwrap();
+        assert_eq!(read.len(), samples.len());
+        assert!(read.iter().zip(&samples).all(|(a, b)| (a - b).abs() < 1e-3));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn flac_round_trips() {
+        let dir = temp_dir("flac");
+        let samples = tone(SAMPLE_RATE as usize + 123);
+        let path = dir.join("tone.flac");
+        save_audio_file(&path, &samples, AudioFileFormat::Flac).unwrap();
+
+        let mut reader = claxon::FlacReader::open(&path).unwrap();
+        let info = reader.streaminfo();
+        assert_eq!(info.sample_rate, SAMPLE_RATE);
+        let decoded: Vec<i32> = reader.samples().map(|s| s.unwrap()).collect();
+        let expected: Vec<i32> = samples.iter().map(|&s| to_i16(s) as i32).collect();
+        assert_eq!(decoded, expected);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn opus_round_trips_with_exact_length() {
+        let dir = temp_dir("opus");
+        // Not a whole number of frames, so the end has to be trimmed
+        let samples = tone(SAMPLE_RATE as usize + 123);
+        let path = dir.join("tone.opus");
+        save_audio_file(&path, &samples, AudioFileFormat::Opus { bitrate_kbps: 24 }).unwrap();
+
+        let mut reader = ogg::PacketReader::new(File::open(&path).unwrap());
+        let head = reader.read_packet().unwrap().unwrap();
+        assert!(head.data.starts_with(b"OpusHead"));
+        let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as u64;
+        let tags = reader.read_packet().unwrap().unwrap();
+        assert!(tags.data.starts_with(b"OpusTags"));
+
+        let mut decoder = opus::Decoder::new(SAMPLE_RATE, opus::Channels::Mono).unwrap();
+        let mut decoded = Vec::new();
+        let mut frame = [0f32; OPUS_FRAME_SAMPLES];
+        let mut granules = Vec::new();
+        let mut last_granule = 0;
+        while let Some(packet) = reader.read_packet().unwrap() {
+            let len = decoder
+                .decode_float(&packet.data, &mut frame, false)
+                .unwrap();
+            decoded.extend_from_slice(&frame[..len]);
+            if packet.last_in_page() {
+                granules.push(packet.absgp_page());
+            }
+            if packet.last_in_stream() {
+                last_granule = packet.absgp_page();
+            }
+        }
+
+        // Intermediate pages count every decoded sample at 48 kHz
+        let granule_scale = OPUS_GRANULE_RATE / SAMPLE_RATE as u64;
+        for granule in &granules[..granules.len() - 1] {
+            assert_eq!(granule % (OPUS_FRAME_SAMPLES as u64 * granule_scale), 0);
+            assert!(*granule <= decoded.len() as u64 * granule_scale);
+        }
+
+        // The final granule position gives the exact duration
+        let duration = (last_granule - pre_skip) / granule_scale;
+        assert_eq!(duration, samples.len() as u64);
+
+        // After dropping pre-skip, the whole signal including its tail is there
+        let start = (pre_skip / granule_scale) as usize;
+        assert!(decoded.len() >= start + samples.len());
+        let trimmed = &decoded[start..start + samples.len()];
+        let tail = OPUS_FRAME_SAMPLES / 2;
+        assert!(rms(&trimmed[trimmed.len() - tail..]) > 0.5 * rms(&samples));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
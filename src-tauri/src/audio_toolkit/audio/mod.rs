@@ -1,12 +1,22 @@
 // Re-export all audio components
+mod channels;
+mod denoise;
 mod device;
+mod dsp;
+mod encoding;
+mod monitor;
 mod recorder;
 mod resampler;
-mod utils;
 mod visualizer;
 
+pub use channels::{ChannelMixer, ChannelSelection};
+pub use denoise::{
+    create_noise_suppressor, NoiseSuppressionMethod, NoiseSuppressor, SpectralSubtraction,
+};
 pub use device::{list_input_devices, list_output_devices, CpalDeviceInfo};
-pub use recorder::AudioRecorder;
+pub use dsp::{rms, DspChain, DspConfig};
+pub use encoding::{read_wav_file, save_audio_file, AudioFileFormat};
+pub use monitor::{list_monitor_sources, MonitorCapture, MonitorSource, DEFAULT_MONITOR};
+pub use recorder::{AudioRecorder, AudioSegment, CaptureSource};
 pub use resampler::FrameResampler;
-pub use utils::save_wav_file;
 pub use visualizer::AudioVisualiser;
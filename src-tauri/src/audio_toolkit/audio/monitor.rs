@@ -0,0 +1,178 @@
+//! System audio capture through PulseAudio/PipeWire monitor sources.
+//!
+//! Every output sink has a `.monitor` source carrying what is being played.
+//! cpal only talks to ALSA on Linux, so monitors are listed with `pactl` and
+//! recorded with `parec`; both also work against PipeWire's Pulse server.
+
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::JoinHandle;
+
+/// Pulse name for the monitor of the current default output
+pub const DEFAULT_MONITOR: &str = "@DEFAULT_MONITOR@";
+
+/// Samples per chunk sent to the consumer (30 ms at 16 kHz)
+const CHUNK_SAMPLES: usize = 480;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MonitorSource {
+    /// Pulse source name, e.g. `alsa_output.pci-0000_00_1f.3.analog-stereo.monitor`
+    pub name: String,
+    /// Human readable name, e.g. `Monitor of Built-in Audio`
+    pub description: String,
+}
+
+/// Lists the monitor sources of the running Pulse/PipeWire server. Empty when
+/// there is none or `pactl` isn't installed.
+pub fn list_monitor_sources() -> Vec<MonitorSource> {
+    let output = match Command::new("pactl")
+        .args(["list", "sources"])
+        // The labels we parse are translated otherwise
+        .env("LC_ALL", "C")
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(_) => return Vec::new(),
+        Err(e) => {
+            log::debug!("pactl not available: {}", e);
+            return Vec::new();
+        }
+    };
+
+    parse_monitor_sources(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_monitor_sources(pactl_output: &str) -> Vec<MonitorSource> {
+    let mut sources = Vec::new();
+    let mut name: Option<String> = None;
+
+    for line in pactl_output.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("Name:") {
+            name = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Description:") {
+            if let Some(name) = name.take().filter(|n| n.ends_with(".monitor")) {
+                sources.push(MonitorSource {
+                    name,
+                    description: value.trim().to_string(),
+                });
+            }
+        }
+    }
+
+    sources
+}
+
+/// A running `parec` process feeding mono f32 chunks into a channel. Stops
+/// the process when dropped.
+pub struct MonitorCapture {
+    child: Child,
+    reader: Option<JoinHandle<()>>,
+    stopping: Arc<AtomicBool>,
+}
+
+impl MonitorCapture {
+    /// Starts recording `source` at `sample_rate`. `on_end` is called if the
+    /// capture stops on its own, e.g. because the Pulse server went away.
+    pub fn start(
+        source: &str,
+        sample_rate: u32,
+        sample_tx: mpsc::Sender<Vec<f32>>,
+        on_end: Option<Box<dyn FnOnce() + Send>>,
+    ) -> std::io::Result<Self> {
+        let mut child = Command::new("parec")
+            .arg(format!("--device={}", source))
+            .args(["--format=float32le", "--channels=1", "--raw"])
+            .arg(format!("--rate={}", sample_rate))
+            .arg("--latency-msec=30")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| std::io::Error::other("parec has no stdout"))?;
+        let stopping = Arc::new(AtomicBool::new(false));
+        let reader_stopping = stopping.clone();
+
+        let reader = std::thread::spawn(move || {
+            let mut buf = [0u8; CHUNK_SAMPLES * 4];
+            let mut pending = Vec::<u8>::with_capacity(buf.len() + 4);
+
+            loop {
+                let read = match stdout.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => read,
+                };
+                pending.extend_from_slice(&buf[..read]);
+
+                // Reads can end in the middle of a sample
+                let whole = pending.len() / 4 * 4;
+                let samples: Vec<f32> = pending[..whole]
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect();
+                pending.drain(..whole);
+
+                if sample_tx.send(samples).is_err() {
+                    break;
+                }
+            }
+
+            if !reader_stopping.load(Ordering::SeqCst) {
+                log::warn!("System audio capture ended unexpectedly");
+                if let Some(on_end) = on_end {
+                    on_end();
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            reader: Some(reader),
+            stopping,
+        })
+    }
+}
+
+impl Drop for MonitorCapture {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_only_monitor_sources() {
+        let output = "\
+Source #53
+\tState: SUSPENDED
+\tName: alsa_output.pci-0000_00_1f.3.analog-stereo.monitor
+\tDescription: Monitor of Built-in Audio Analog Stereo
+\tDriver: PipeWire
+Source #54
+\tState: RUNNING
+\tName: alsa_input.pci-0000_00_1f.3.analog-stereo
+\tDescription: Built-in Audio Analog Stereo
+\tDriver: PipeWire
+";
+        assert_eq!(
+            parse_monitor_sources(output),
+            vec![MonitorSource {
+                name: "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor".to_string(),
+                description: "Monitor of Built-in Audio Analog Stereo".to_string(),
+            }]
+        );
+    }
+}
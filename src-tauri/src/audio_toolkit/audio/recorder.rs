@@ -1,7 +1,8 @@
 use std::{
+    collections::VecDeque,
     io::Error,
     sync::{mpsc, Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use cpal::{
@@ -10,14 +11,53 @@ use cpal::{
 };
 
 use crate::audio_toolkit::{
-    audio::{AudioVisualiser, FrameResampler},
+    audio::{
+        AudioVisualiser, ChannelMixer, ChannelSelection, DspChain, DspConfig, FrameResampler,
+        MonitorCapture,
+    },
     constants,
     vad::{self, VadFrame},
     VoiceActivityDetector,
 };
 
+/// Most system audio kept waiting for microphone frames when mixing (0.5 s).
+/// Older samples are dropped so the two sources can't drift apart.
+const MAX_MONITOR_BACKLOG: usize = constants::WHISPER_SAMPLE_RATE as usize / 2;
+
+/// What the recorder captures.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum CaptureSource {
+    /// The input device passed to `open`
+    #[default]
+    Microphone,
+    /// Only the given monitor source, see `monitor`
+    Monitor(String),
+    /// The input device with the given monitor source added on top
+    Mixed(String),
+}
+
+/// Pause after speech (in 30 ms frames, after the VAD's own hangover) that
+/// ends a segment
+const SEGMENT_PAUSE_FRAMES: usize = 10;
+/// Segments are only cut at pauses once they hold this much speech (2 s)
+const MIN_SEGMENT_SAMPLES: usize = constants::WHISPER_SAMPLE_RATE as usize * 2;
+/// Segments are cut here even without a pause (30 s)
+const MAX_SEGMENT_SAMPLES: usize = constants::WHISPER_SAMPLE_RATE as usize * 30;
+
+/// A piece of a segmented recording, see `AudioRecorder::start_segmented`.
+#[derive(Debug)]
+pub struct AudioSegment {
+    pub samples: Vec<f32>,
+    /// When the first sample was captured
+    pub started_at: Instant,
+    /// When the last speech in the segment was captured. Pauses the VAD left
+    /// out make this later than `started_at` plus the length of `samples`.
+    pub ended_at: Instant,
+}
+
 enum Cmd {
-    Start,
+    /// Start recording; with a sender the audio is handed over in segments
+    Start(Option<mpsc::Sender<AudioSegment>>),
     Stop(mpsc::Sender<Vec<f32>>),
     Shutdown,
 }
@@ -28,6 +68,21 @@ pub struct AudioRecorder {
     worker_handle: Option<std::thread::JoinHandle<()>>,
     vad: Option<Arc<Mutex<Box<dyn vad::VoiceActivityDetector>>>>,
     level_cb: Option<Arc<dyn Fn(Vec<f32>) + Send + Sync + 'static>>,
+    error_cb: Option<Arc<dyn Fn(cpal::StreamError) + Send + Sync + 'static>>,
+    /// Read by the worker at the start of each recording
+    dsp_config: Arc<Mutex<DspConfig>>,
+    /// Applied when the stream is opened
+    channel_selection: ChannelSelection,
+    capture_source: CaptureSource,
+}
+
+/// Everything the worker keeps alive while it consumes samples.
+struct OpenSources {
+    sample_rate: u32,
+    /// System audio to mix in, for `CaptureSource::Mixed`
+    monitor_rx: Option<mpsc::Receiver<Vec<f32>>>,
+    _stream: Option<cpal::Stream>,
+    _monitor: Option<MonitorCapture>,
 }
 
 impl AudioRecorder {
@@ -38,6 +93,10 @@ impl AudioRecorder {
             worker_handle: None,
             vad: None,
             level_cb: None,
+            error_cb: None,
+            dsp_config: Arc::new(Mutex::new(DspConfig::default())),
+            channel_selection: ChannelSelection::default(),
+            capture_source: CaptureSource::default(),
         })
     }
 
@@ -54,6 +113,42 @@ impl AudioRecorder {
         self
     }
 
+    /// Called from the audio thread when the stream fails, e.g. because the
+    /// device was unplugged. The stream produces no more samples afterwards.
+    pub fn with_error_callback<F>(mut self, cb: F) -> Self
+    where
+        F: Fn(cpal::StreamError) + Send + Sync + 'static,
+    {
+        self.error_cb = Some(Arc::new(cb));
+        self
+    }
+
+    /// Sets the processing stages used from the next `start` on.
+    pub fn set_dsp_config(&self, config: DspConfig) {
+        *self.dsp_config.lock().unwrap() = config;
+    }
+
+    /// Sets how input channels are mixed down from the next `open` on.
+    pub fn set_channel_selection(&mut self, selection: ChannelSelection) {
+        self.channel_selection = selection;
+    }
+
+    /// Sets what is captured from the next `open` on.
+    pub fn set_capture_source(&mut self, source: CaptureSource) {
+        self.capture_source = source;
+    }
+
+    /// Number of input channels the recorder would capture from `device`.
+    pub fn input_channels(device: &Device) -> Result<u16, Box<dyn std::error::Error>> {
+        Ok(AudioRecorder::get_preferred_config(device)?.channels())
+    }
+
+    /// Name of the input device the recorder is currently open on. `None`
+    /// while only system audio is captured.
+    pub fn device_name(&self) -> Option<String> {
+        self.device.as_ref().and_then(|d| d.name().ok())
+    }
+
     pub fn open(&mut self, device: Option<Device>) -> Result<(), Box<dyn std::error::Error>> {
         if self.worker_handle.is_some() {
             return Ok(()); // already open
@@ -63,65 +158,63 @@ impl AudioRecorder {
         let (cmd_tx, cmd_rx) = mpsc::channel::<Cmd>();
 
         let host = crate::audio_toolkit::get_cpal_host();
-        let device = match device {
-            Some(dev) => dev,
-            None => host
-                .default_input_device()
-                .ok_or_else(|| Error::new(std::io::ErrorKind::NotFound, "No input device found"))?,
+        let device = match (&self.capture_source, device) {
+            (CaptureSource::Monitor(_), _) => None,
+            (_, Some(dev)) => Some(dev),
+            (_, None) => Some(host.default_input_device().ok_or_else(|| {
+                Error::new(std::io::ErrorKind::NotFound, "No input device found")
+            })?),
         };
 
         let thread_device = device.clone();
+        let source = self.capture_source.clone();
         let vad = self.vad.clone();
         // Move the optional level callback into the worker thread
         let level_cb = self.level_cb.clone();
+        let error_cb = self.error_cb.clone();
+        let dsp_config = self.dsp_config.clone();
+        let channel_selection = self.channel_selection;
+        let (init_tx, init_rx) = mpsc::channel::<Result<(), String>>();
 
         let worker = std::thread::spawn(move || {
-            let config = AudioRecorder::get_preferred_config(&thread_device)
-                .expect("failed to fetch preferred config");
-
-            let sample_rate = config.sample_rate().0;
-            let channels = config.channels() as usize;
-
-            log::info!(
-                "Using device: {:?}\nSample rate: {}\nChannels: {}\nFormat: {:?}",
-                thread_device.name(),
-                sample_rate,
-                channels,
-                config.sample_format()
-            );
-
-            let stream = match config.sample_format() {
-                cpal::SampleFormat::U8 => {
-                    AudioRecorder::build_stream::<u8>(&thread_device, &config, sample_tx, channels)
-                        .unwrap()
-                }
-                cpal::SampleFormat::I8 => {
-                    AudioRecorder::build_stream::<i8>(&thread_device, &config, sample_tx, channels)
-                        .unwrap()
+            // The stream has to be created on this thread, report back whether it worked
+            let mut sources = match AudioRecorder::open_sources(
+                thread_device.as_ref(),
+                &source,
+                sample_tx,
+                channel_selection,
+                error_cb,
+            ) {
+                Ok(opened) => opened,
+                Err(e) => {
+                    let _ = init_tx.send(Err(e.to_string()));
+                    return;
                 }
-                cpal::SampleFormat::I16 => {
-                    AudioRecorder::build_stream::<i16>(&thread_device, &config, sample_tx, channels)
-                        .unwrap()
-                }
-                cpal::SampleFormat::I32 => {
-                    AudioRecorder::build_stream::<i32>(&thread_device, &config, sample_tx, channels)
-                        .unwrap()
-                }
-                cpal::SampleFormat::F32 => {
-                    AudioRecorder::build_stream::<f32>(&thread_device, &config, sample_tx, channels)
-                        .unwrap()
-                }
-                _ => panic!("unsupported sample format"),
             };
-
-            stream.play().expect("failed to start stream");
-
-            // keep the stream alive while we process samples
-            run_consumer(sample_rate, vad, sample_rx, cmd_rx, level_cb);
-            // stream is dropped here, after run_consumer returns
+            let _ = init_tx.send(Ok(()));
+
+            // keep the streams alive while we process samples
+            run_consumer(
+                sources.sample_rate,
+                vad,
+                sample_rx,
+                sources.monitor_rx.take(),
+                cmd_rx,
+                level_cb,
+                dsp_config,
+            );
+            drop(sources);
         });
 
-        self.device = Some(device);
+        let init_result = init_rx
+            .recv()
+            .unwrap_or_else(|_| Err("Audio worker exited during startup".to_string()));
+        if let Err(e) = init_result {
+            let _ = worker.join();
+            return Err(Box::new(Error::other(e)));
+        }
+
+        self.device = device;
         self.cmd_tx = Some(cmd_tx);
         self.worker_handle = Some(worker);
 
@@ -130,16 +223,28 @@ impl AudioRecorder {
 
     pub fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(tx) = &self.cmd_tx {
-            tx.send(Cmd::Start)?;
+            tx.send(Cmd::Start(None))?;
         }
         Ok(())
     }
 
-    pub fn stop(&self) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
-        let (resp_tx, resp_rx) = mpsc::channel();
+    /// Starts a recording that sends its audio to `segment_tx` in pieces cut
+    /// at pauses, instead of keeping it all until `stop`. `stop` sends the
+    /// rest as a last segment and returns nothing.
+    pub fn start_segmented(
+        &self,
+        segment_tx: mpsc::Sender<AudioSegment>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(tx) = &self.cmd_tx {
-            tx.send(Cmd::Stop(resp_tx))?;
+            tx.send(Cmd::Start(Some(segment_tx)))?;
         }
+        Ok(())
+    }
+
+    pub fn stop(&self) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+        let (resp_tx, resp_rx) = mpsc::channel();
+        let tx = self.cmd_tx.as_ref().ok_or("Recorder is not open")?;
+        tx.send(Cmd::Stop(resp_tx))?;
         Ok(resp_rx.recv()?) // wait for the samples
     }
 
@@ -154,38 +259,133 @@ impl AudioRecorder {
         Ok(())
     }
 
+    fn open_sources(
+        device: Option<&cpal::Device>,
+        source: &CaptureSource,
+        sample_tx: mpsc::Sender<Vec<f32>>,
+        channel_selection: ChannelSelection,
+        error_cb: Option<Arc<dyn Fn(cpal::StreamError) + Send + Sync + 'static>>,
+    ) -> Result<OpenSources, Box<dyn std::error::Error>> {
+        // A monitor capture that dies is reported like a failed device
+        let on_monitor_end = |error_cb: Option<Arc<dyn Fn(cpal::StreamError) + Send + Sync>>| {
+            error_cb.map(|cb| {
+                Box::new(move || cb(cpal::StreamError::DeviceNotAvailable))
+                    as Box<dyn FnOnce() + Send>
+            })
+        };
+
+        match (source, device) {
+            (CaptureSource::Monitor(name), _) => {
+                log::info!("Capturing system audio from {}", name);
+                let monitor = MonitorCapture::start(
+                    name,
+                    constants::WHISPER_SAMPLE_RATE,
+                    sample_tx,
+                    on_monitor_end(error_cb),
+                )?;
+                Ok(OpenSources {
+                    sample_rate: constants::WHISPER_SAMPLE_RATE,
+                    monitor_rx: None,
+                    _stream: None,
+                    _monitor: Some(monitor),
+                })
+            }
+            (_, None) => Err("No input device found".into()),
+            (CaptureSource::Microphone, Some(device)) => {
+                let (stream, sample_rate) =
+                    AudioRecorder::open_stream(device, sample_tx, channel_selection, error_cb)?;
+                Ok(OpenSources {
+                    sample_rate,
+                    monitor_rx: None,
+                    _stream: Some(stream),
+                    _monitor: None,
+                })
+            }
+            (CaptureSource::Mixed(name), Some(device)) => {
+                log::info!("Mixing system audio from {} into the microphone", name);
+                let (monitor_tx, monitor_rx) = mpsc::channel::<Vec<f32>>();
+                let monitor = MonitorCapture::start(
+                    name,
+                    constants::WHISPER_SAMPLE_RATE,
+                    monitor_tx,
+                    on_monitor_end(error_cb.clone()),
+                )?;
+                let (stream, sample_rate) =
+                    AudioRecorder::open_stream(device, sample_tx, channel_selection, error_cb)?;
+                Ok(OpenSources {
+                    sample_rate,
+                    monitor_rx: Some(monitor_rx),
+                    _stream: Some(stream),
+                    _monitor: Some(monitor),
+                })
+            }
+        }
+    }
+
+    fn open_stream(
+        device: &cpal::Device,
+        sample_tx: mpsc::Sender<Vec<f32>>,
+        channel_selection: ChannelSelection,
+        error_cb: Option<Arc<dyn Fn(cpal::StreamError) + Send + Sync + 'static>>,
+    ) -> Result<(cpal::Stream, u32), Box<dyn std::error::Error>> {
+        let config = AudioRecorder::get_preferred_config(device)?;
+
+        let sample_rate = config.sample_rate().0;
+        let channels = config.channels() as usize;
+
+        log::info!(
+            "Using device: {:?}\nSample rate: {}\nChannels: {} ({:?})\nFormat: {:?}",
+            device.name(),
+            sample_rate,
+            channels,
+            channel_selection,
+            config.sample_format()
+        );
+        let mixer = ChannelMixer::new(channel_selection, channels);
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::U8 => {
+                AudioRecorder::build_stream::<u8>(device, &config, sample_tx, mixer, error_cb)
+            }
+            cpal::SampleFormat::I8 => {
+                AudioRecorder::build_stream::<i8>(device, &config, sample_tx, mixer, error_cb)
+            }
+            cpal::SampleFormat::I16 => {
+                AudioRecorder::build_stream::<i16>(device, &config, sample_tx, mixer, error_cb)
+            }
+            cpal::SampleFormat::I32 => {
+                AudioRecorder::build_stream::<i32>(device, &config, sample_tx, mixer, error_cb)
+            }
+            cpal::SampleFormat::F32 => {
+                AudioRecorder::build_stream::<f32>(device, &config, sample_tx, mixer, error_cb)
+            }
+            other => return Err(format!("Unsupported sample format: {:?}", other).into()),
+        }?;
+
+        stream.play()?;
+        Ok((stream, sample_rate))
+    }
+
     fn build_stream<T>(
         device: &cpal::Device,
         config: &cpal::SupportedStreamConfig,
         sample_tx: mpsc::Sender<Vec<f32>>,
-        channels: usize,
+        mut mixer: ChannelMixer,
+        error_cb: Option<Arc<dyn Fn(cpal::StreamError) + Send + Sync + 'static>>,
     ) -> Result<cpal::Stream, cpal::BuildStreamError>
     where
         T: Sample + SizedSample + Send + 'static,
         f32: cpal::FromSample<T>,
     {
+        let mut input_buffer = Vec::new();
         let mut output_buffer = Vec::new();
 
         let stream_cb = move |data: &[T], _: &cpal::InputCallbackInfo| {
-            output_buffer.clear();
+            input_buffer.clear();
+            input_buffer.extend(data.iter().map(|&sample| sample.to_sample::<f32>()));
 
-            if channels == 1 {
-                // Direct conversion without intermediate Vec
-                output_buffer.extend(data.iter().map(|&sample| sample.to_sample::<f32>()));
-            } else {
-                // Convert to mono directly
-                let frame_count = data.len() / channels;
-                output_buffer.reserve(frame_count);
-
-                for frame in data.chunks_exact(channels) {
-                    let mono_sample = frame
-                        .iter()
-                        .map(|&sample| sample.to_sample::<f32>())
-                        .sum::<f32>()
-                        / channels as f32;
-                    output_buffer.push(mono_sample);
-                }
-            }
+            output_buffer.clear();
+            mixer.mix(&input_buffer, &mut output_buffer);
 
             if sample_tx.send(output_buffer.clone()).is_err() {
                 log::error!("Failed to send samples");
@@ -195,7 +395,12 @@ impl AudioRecorder {
         device.build_input_stream(
             &config.clone().into(),
             stream_cb,
-            |err| log::error!("Stream error: {}", err),
+            move |err| {
+                log::error!("Stream error: {}", err);
+                if let Some(cb) = &error_cb {
+                    cb(err);
+                }
+            },
             None,
         )
     }
@@ -239,12 +444,88 @@ impl AudioRecorder {
     }
 }
 
+/// Splits a long recording at pauses in speech so it never has to be held
+/// in memory at once.
+struct Segmenter {
+    segment_tx: mpsc::Sender<AudioSegment>,
+    silent_frames: usize,
+    /// When the recording started; positions count samples from here
+    origin: Instant,
+    /// Samples recorded so far, speech or not
+    position: usize,
+    /// Position of the first buffered sample
+    start: Option<usize>,
+    /// Position after the last speech frame
+    speech_end: usize,
+}
+
+impl Segmenter {
+    fn new(segment_tx: mpsc::Sender<AudioSegment>) -> Self {
+        Self {
+            segment_tx,
+            silent_frames: 0,
+            origin: Instant::now(),
+            position: 0,
+            start: None,
+            speech_end: 0,
+        }
+    }
+
+    /// Called after every frame of `frame_len` samples; sends the buffered
+    /// speech off once a segment is complete.
+    fn push_frame(
+        &mut self,
+        frame_len: usize,
+        speech: bool,
+        buffer: &mut Vec<f32>,
+        dsp: &DspChain,
+    ) {
+        self.position += frame_len;
+        if speech {
+            self.silent_frames = 0;
+            self.speech_end = self.position;
+        } else {
+            self.silent_frames += 1;
+        }
+        // Only speech is buffered, so the buffer ends with this frame's speech
+        if self.start.is_none() && !buffer.is_empty() {
+            self.start = Some(self.speech_end.saturating_sub(buffer.len()));
+        }
+
+        let paused = self.silent_frames >= SEGMENT_PAUSE_FRAMES;
+        if buffer.len() >= MAX_SEGMENT_SAMPLES || (paused && buffer.len() >= MIN_SEGMENT_SAMPLES) {
+            self.send(buffer, dsp);
+        }
+    }
+
+    /// Sends whatever is buffered as a segment.
+    fn send(&mut self, buffer: &mut Vec<f32>, dsp: &DspChain) {
+        let Some(start) = self.start.take() else {
+            return;
+        };
+        let mut samples = std::mem::take(buffer);
+        dsp.finish(&mut samples);
+        let _ = self.segment_tx.send(AudioSegment {
+            samples,
+            started_at: self.instant_at(start),
+            ended_at: self.instant_at(self.speech_end.max(start)),
+        });
+    }
+
+    fn instant_at(&self, position: usize) -> Instant {
+        let micros = position as u64 * 1_000_000 / constants::WHISPER_SAMPLE_RATE as u64;
+        self.origin + Duration::from_micros(micros)
+    }
+}
+
 fn run_consumer(
     in_sample_rate: u32,
     vad: Option<Arc<Mutex<Box<dyn vad::VoiceActivityDetector>>>>,
     sample_rx: mpsc::Receiver<Vec<f32>>,
+    monitor_rx: Option<mpsc::Receiver<Vec<f32>>>,
     cmd_rx: mpsc::Receiver<Cmd>,
     level_cb: Option<Arc<dyn Fn(Vec<f32>) + Send + Sync + 'static>>,
+    dsp_config: Arc<Mutex<DspConfig>>,
 ) {
     let mut frame_resampler = FrameResampler::new(
         in_sample_rate as usize,
@@ -253,7 +534,12 @@ fn run_consumer(
     );
 
     let mut processed_samples = Vec::<f32>::new();
+    let mut monitor_backlog = VecDeque::<f32>::new();
     let mut recording = false;
+    let mut segmenter: Option<Segmenter> = None;
+    // Whether the last frame was kept, so the tail held back by the DSP is too
+    let mut in_speech = false;
+    let mut dsp = DspChain::new(constants::WHISPER_SAMPLE_RATE, DspConfig::default());
 
     // ---------- spectrum visualisation setup ---------------------------- //
     const BUCKETS: usize = 16;
@@ -269,48 +555,92 @@ fn run_consumer(
     fn handle_frame(
         samples: &[f32],
         recording: bool,
+        monitor: &mut VecDeque<f32>,
+        dsp: &mut DspChain,
         vad: &Option<Arc<Mutex<Box<dyn vad::VoiceActivityDetector>>>>,
         out_buf: &mut Vec<f32>,
-    ) {
+    ) -> bool {
         if !recording {
-            return;
+            return false;
+        }
+
+        let mut frame = samples.to_vec();
+        // System audio, when mixing, goes on top of the microphone
+        for (sample, system) in frame
+            .iter_mut()
+            .zip(monitor.drain(..samples.len().min(monitor.len())))
+        {
+            *sample = (*sample + system).clamp(-1.0, 1.0);
         }
 
+        // Level processing runs before the VAD so it judges the cleaned signal
+        dsp.process(&mut frame);
+        let samples = frame.as_slice();
+
         if let Some(vad_arc) = vad {
             let mut det = vad_arc.lock().unwrap();
             match det.push_frame(samples).unwrap_or(VadFrame::Speech(samples)) {
-                VadFrame::Speech(buf) => out_buf.extend_from_slice(buf),
-                VadFrame::Noise => {}
+                VadFrame::Speech(buf) => {
+                    out_buf.extend_from_slice(buf);
+                    true
+                }
+                VadFrame::Noise => false,
             }
         } else {
             out_buf.extend_from_slice(samples);
+            true
         }
     }
 
     loop {
-        let raw = match sample_rx.recv() {
-            Ok(s) => s,
-            Err(_) => break, // stream closed
-        };
+        // Wake up regularly so commands are still answered if the stream dies
+        match sample_rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(raw) => {
+                // ---------- spectrum processing ------------------------------ //
+                if let Some(buckets) = visualizer.feed(&raw) {
+                    if let Some(cb) = &level_cb {
+                        cb(buckets);
+                    }
+                }
 
-        // ---------- spectrum processing ---------------------------------- //
-        if let Some(buckets) = visualizer.feed(&raw) {
-            if let Some(cb) = &level_cb {
-                cb(buckets);
+                // ---------- existing pipeline -------------------------------- //
+                frame_resampler.push(&raw, &mut |frame: &[f32]| {
+                    let speech = handle_frame(
+                        frame,
+                        recording,
+                        &mut monitor_backlog,
+                        &mut dsp,
+                        &vad,
+                        &mut processed_samples,
+                    );
+                    in_speech = speech;
+                    if let (true, Some(segmenter)) = (recording, &mut segmenter) {
+                        segmenter.push_frame(frame.len(), speech, &mut processed_samples, &dsp);
+                    }
+                });
             }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break, // stream closed
         }
 
-        // ---------- existing pipeline ------------------------------------ //
-        frame_resampler.push(&raw, &mut |frame: &[f32]| {
-            handle_frame(frame, recording, &vad, &mut processed_samples)
-        });
+        if let Some(rx) = &monitor_rx {
+            while let Ok(samples) = rx.try_recv() {
+                monitor_backlog.extend(samples);
+            }
+            let excess = monitor_backlog.len().saturating_sub(MAX_MONITOR_BACKLOG);
+            monitor_backlog.drain(..excess);
+        }
 
         // non-blocking check for a command
         while let Ok(cmd) = cmd_rx.try_recv() {
             match cmd {
-                Cmd::Start => {
+                Cmd::Start(segment_tx) => {
                     processed_samples.clear();
+                    segmenter = segment_tx.map(Segmenter::new);
+                    monitor_backlog.clear();
                     recording = true;
+                    in_speech = false;
+                    dsp.restart(*dsp_config.lock().unwrap());
                     visualizer.reset(); // Reset visualization buffer
                     if let Some(v) = &vad {
                         v.lock().unwrap().reset();
@@ -321,8 +651,32 @@ fn run_consumer(
 
                     frame_resampler.finish(&mut |frame: &[f32]| {
                         // we still want to process the last few frames
-                        handle_frame(frame, true, &vad, &mut processed_samples)
+                        in_speech = handle_frame(
+                            frame,
+                            true,
+                            &mut monitor_backlog,
+                            &mut dsp,
+                            &vad,
+                            &mut processed_samples,
+                        );
+                        if let Some(segmenter) = &mut segmenter {
+                            segmenter.push_frame(
+                                frame.len(),
+                                in_speech,
+                                &mut processed_samples,
+                                &dsp,
+                            );
+                        }
                     });
+                    let tail = dsp.flush();
+                    if in_speech {
+                        processed_samples.extend(tail);
+                    }
+                    match segmenter.take() {
+                        // The rest goes out as the last segment, after the others
+                        Some(mut segmenter) => segmenter.send(&mut processed_samples, &dsp),
+                        None => dsp.finish(&mut processed_samples),
+                    }
 
                     let _ = reply_tx.send(std::mem::take(&mut processed_samples));
                 }
@@ -331,3 +685,76 @@ fn run_consumer(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: usize = 480;
+
+    fn segmenter() -> (Segmenter, mpsc::Receiver<AudioSegment>, DspChain) {
+        let (tx, rx) = mpsc::channel();
+        let dsp = DspChain::new(constants::WHISPER_SAMPLE_RATE, DspConfig::default());
+        (Segmenter::new(tx), rx, dsp)
+    }
+
+    #[test]
+    fn splits_at_pause_once_long_enough() {
+        let (mut segmenter, rx, dsp) = segmenter();
+
+        // A short utterance followed by a pause stays buffered
+        let mut buffer = vec![0.1; MIN_SEGMENT_SAMPLES / 2];
+        for _ in 0..SEGMENT_PAUSE_FRAMES * 2 {
+            segmenter.push_frame(FRAME, false, &mut buffer, &dsp);
+        }
+        assert!(rx.try_recv().is_err());
+
+        buffer.resize(MIN_SEGMENT_SAMPLES, 0.1);
+        segmenter.push_frame(FRAME, true, &mut buffer, &dsp);
+        for _ in 0..SEGMENT_PAUSE_FRAMES - 1 {
+            segmenter.push_frame(FRAME, false, &mut buffer, &dsp);
+        }
+        assert!(rx.try_recv().is_err());
+
+        segmenter.push_frame(FRAME, false, &mut buffer, &dsp);
+        assert_eq!(rx.try_recv().unwrap().samples.len(), MIN_SEGMENT_SAMPLES);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn splits_long_speech_without_pause() {
+        let (mut segmenter, rx, dsp) = segmenter();
+
+        let mut buffer = vec![0.1; MAX_SEGMENT_SAMPLES];
+        segmenter.push_frame(FRAME, true, &mut buffer, &dsp);
+        assert_eq!(rx.try_recv().unwrap().samples.len(), MAX_SEGMENT_SAMPLES);
+    }
+
+    #[test]
+    fn segments_are_placed_on_the_recording_timeline() {
+        let (mut segmenter, rx, dsp) = segmenter();
+        let mut buffer = Vec::new();
+        let mut push = |segmenter: &mut Segmenter, frames: usize, speech: bool| {
+            for _ in 0..frames {
+                if speech {
+                    buffer.extend_from_slice(&[0.1; FRAME]);
+                }
+                segmenter.push_frame(FRAME, speech, &mut buffer, &dsp);
+            }
+        };
+
+        // 3 s of silence, 2.4 s of speech with a short pause the VAD drops,
+        // then a pause long enough to cut
+        push(&mut segmenter, 100, false);
+        push(&mut segmenter, 40, true);
+        push(&mut segmenter, 5, false);
+        push(&mut segmenter, 40, true);
+        push(&mut segmenter, SEGMENT_PAUSE_FRAMES, false);
+
+        let segment = rx.try_recv().unwrap();
+        let offset = |at: Instant| at.duration_since(segmenter.origin).as_millis();
+        assert_eq!(segment.samples.len(), 80 * FRAME);
+        assert_eq!(offset(segment.started_at), 3000);
+        assert_eq!(offset(segment.ended_at), 5550);
+    }
+}
@@ -6,6 +6,13 @@ const DB_MAX: f32 = -8.0;
 const GAIN: f32 = 1.3;
 const CURVE_POWER: f32 = 0.7;
 
+/// Periodic Hann window of `size` samples.
+pub(crate) fn hann_window(size: usize) -> Vec<f32> {
+    (0..size)
+        .map(|i| 0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / size as f32).cos()))
+        .collect()
+}
+
 pub struct AudioVisualiser {
     fft: Arc<dyn Fft<f32>>,
     window: Vec<f32>,
@@ -29,11 +36,7 @@ impl AudioVisualiser {
         let fft = planner.plan_fft_forward(window_size);
 
         // Pre-compute Hann window
-        let window: Vec<f32> = (0..window_size)
-            .map(|i| {
-                0.5 * (1.0 - (2.0 * std::f32::consts::PI * i as f32 / window_size as f32).cos())
-            })
-            .collect();
+        let window = hann_window(window_size);
 
         // Pre-compute bucket frequency ranges
         let nyquist = sample_rate as f32 / 2.0;
@@ -1,8 +1,12 @@
-use hound::WavWriter;
+use hound::{WavReader, WavWriter};
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
 use handy_app_lib::audio_toolkit::{
-    audio::{list_input_devices, CpalDeviceInfo},
+    audio::{
+        list_input_devices, list_monitor_sources, rms, CaptureSource, CpalDeviceInfo,
+        MonitorSource, NoiseSuppressor, SpectralSubtraction, DEFAULT_MONITOR,
+    },
     vad::SmoothedVad,
     AudioRecorder, SileroVad,
 };
@@ -28,6 +32,7 @@ struct RecorderState {
     is_recording: bool,
     is_open: bool,
     current_device_index: Option<usize>,
+    source: CaptureSource,
     recording_index: u32,
 }
 
@@ -39,6 +44,7 @@ impl RecorderState {
             is_recording: false,
             is_open: false,
             current_device_index: None,
+            source: CaptureSource::Microphone,
             recording_index: 1,
         }
     }
@@ -75,6 +81,22 @@ impl RecorderState {
         Ok(())
     }
 
+    fn switch_source(&mut self, source: CaptureSource) -> Result<(), Box<dyn std::error::Error>> {
+        if self.is_recording {
+            return Err("Stop the current recording before switching sources.".into());
+        }
+        // Takes effect when the recorder is opened again
+        if self.is_open {
+            self.recorder.close()?;
+            self.is_open = false;
+        }
+
+        self.recorder.set_capture_source(source.clone());
+        self.source = source;
+        println!("Capturing from: {:?}", self.source);
+        Ok(())
+    }
+
     fn start_recording(
         &mut self,
         device_index: Option<usize>,
@@ -182,6 +204,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut devices = list_input_devices()?;
     print_devices(&devices);
+    let mut monitors = list_monitor_sources();
 
     loop {
         print!("[{}] > ", state.mode);
@@ -256,9 +279,33 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 devices = list_input_devices()?;
                 print_devices(&devices);
             }
+            "monitors" => {
+                monitors = list_monitor_sources();
+                print_monitors(&monitors);
+            }
+            "source" => {
+                let monitor = match parts.get(2).map(|p| p.parse::<usize>()) {
+                    None => Some(DEFAULT_MONITOR.to_string()),
+                    Some(Ok(idx)) => monitors.get(idx).map(|m| m.name.clone()),
+                    Some(Err(_)) => None,
+                };
+                let source = match (parts.get(1).copied(), monitor) {
+                    (Some("mic"), _) => CaptureSource::Microphone,
+                    (Some("monitor"), Some(monitor)) => CaptureSource::Monitor(monitor),
+                    (Some("mix"), Some(monitor)) => CaptureSource::Mixed(monitor),
+                    _ => {
+                        println!("Usage: source [mic|monitor|mix] [monitor_index]");
+                        continue;
+                    }
+                };
+                if let Err(e) = state.switch_source(source) {
+                    println!("Error switching source: {}", e);
+                }
+            }
             "status" => {
                 println!("Status:");
                 println!("  Mode: {}", state.mode);
+                println!("  Source: {:?}", state.source);
                 println!(
                     "  Recording: {}",
                     if state.is_recording { "Yes" } else { "No" }
@@ -275,6 +322,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 );
                 println!("  Next Recording: recording_{}.wav", state.recording_index);
             }
+            "denoise" => {
+                if parts.len() < 2 {
+                    println!("Usage: denoise <input.wav> [output.wav]");
+                    continue;
+                }
+                let output = parts
+                    .get(2)
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| denoised_path(Path::new(parts[1])));
+                match denoise_file(parts[1], &output.to_string_lossy()) {
+                    Ok(_) => println!("Denoised audio saved as: {}", output.display()),
+                    Err(e) => println!("Error denoising {}: {}", parts[1], e),
+                }
+            }
             "help" | "h" => {
                 print_help();
             }
@@ -319,7 +380,12 @@ fn print_help() {
         "  mode [always|demand]                      - Switch recording mode or show current mode"
     );
     println!("  devices | dev                             - List available audio devices");
+    println!("  monitors                                  - List system audio (monitor) sources");
+    println!(
+        "  source [mic|monitor|mix] [monitor_index]  - Record microphone, system audio or both"
+    );
     println!("  status                                    - Show current recorder status");
+    println!("  denoise <input.wav> [output.wav]          - Run noise suppression on a file");
     println!("  help | h                                  - Show this help message");
     println!("  quit | exit | q                           - Exit the program");
     println!();
@@ -329,6 +395,18 @@ fn print_help() {
     println!();
 }
 
+fn print_monitors(monitors: &[MonitorSource]) {
+    if monitors.is_empty() {
+        println!("No monitor sources found (PulseAudio or PipeWire required)");
+        return;
+    }
+    println!("Available monitor sources:");
+    for (index, monitor) in monitors.iter().enumerate() {
+        println!("  {}: {} ({})", index, monitor.description, monitor.name);
+    }
+    println!();
+}
+
 fn print_devices(devices: &[CpalDeviceInfo]) {
     println!("Available audio devices:");
     for (index, device) in devices.iter().enumerate() {
@@ -337,6 +415,50 @@ fn print_devices(devices: &[CpalDeviceInfo]) {
     println!();
 }
 
+/// Runs spectral subtraction over a 16 kHz mono WAV file, in the same frame
+/// size the recorder uses.
+fn denoise_file(input: &str, output: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = WavReader::open(input)?;
+    let spec = reader.spec();
+    if spec.channels != 1 || spec.sample_rate != 16000 {
+        return Err(format!(
+            "expected 16 kHz mono, got {} Hz with {} channel(s)",
+            spec.sample_rate, spec.channels
+        )
+        .into());
+    }
+
+    let mut samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    let rms_before = rms(&samples);
+
+    let mut suppressor = SpectralSubtraction::new(spec.sample_rate);
+    for frame in samples.chunks_mut(480) {
+        suppressor.process(frame);
+    }
+    // Drop the latency at the start and take the held-back end, so the output
+    // lines up with the input
+    samples.extend(suppressor.flush());
+    samples.drain(..suppressor.latency());
+
+    println!("RMS before: {:.5}, after: {:.5}", rms_before, rms(&samples));
+    save_audio(&samples, output)
+}
+
+/// `<stem>_denoised.wav` next to the input, whatever its extension.
+fn denoised_path(input: &Path) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    input.with_file_name(format!("{}_denoised.wav", stem))
+}
+
 fn save_audio(samples: &[f32], filename: &str) -> Result<(), Box<dyn std::error::Error>> {
     let spec = hound::WavSpec {
         channels: 1,
@@ -4,9 +4,7 @@ pub mod text;
 pub mod utils;
 pub mod vad;
 
-pub use audio::{
-    list_input_devices, list_output_devices, save_wav_file, AudioRecorder, CpalDeviceInfo,
-};
+pub use audio::{list_input_devices, list_output_devices, AudioRecorder, CpalDeviceInfo};
 pub use text::{apply_custom_words, filter_transcription_output};
 pub use utils::get_cpal_host;
 pub use vad::{SileroVad, VoiceActivityDetector};
@@ -1,90 +1,174 @@
+use crate::clipboard_snapshot::ClipboardSnapshot;
 use crate::input::{self, EnigoState};
-use crate::settings::{get_settings, ClipboardHandling, PasteMethod};
+use crate::managers::audio::AudioRecordingManager;
+use crate::settings::{get_settings, AppSettings, ClipboardHandling, PasteMethod};
+use crate::shortcut;
 use enigo::Enigo;
-use log::info;
+use log::{info, warn};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::ThreadId;
+use std::time::Duration;
 use tauri::{AppHandle, Manager};
 use tauri_plugin_clipboard_manager::ClipboardExt;
+use unicode_segmentation::UnicodeSegmentation;
 
 #[cfg(target_os = "linux")]
 use crate::utils::is_wayland;
 #[cfg(target_os = "linux")]
+use crate::wayland_input::{self, VirtualKeyboard};
+#[cfg(target_os = "linux")]
+use log::debug;
+#[cfg(target_os = "linux")]
 use std::process::Command;
 
-/// Pastes text using the clipboard: saves current content, writes text, sends paste keystroke, restores clipboard.
-fn paste_via_clipboard(
-    enigo: &mut Enigo,
-    text: &str,
-    app_handle: &AppHandle,
-    paste_method: &PasteMethod,
-) -> Result<(), String> {
-    let clipboard = app_handle.clipboard();
-    let clipboard_content = clipboard.read_text().unwrap_or_default();
+/// Set while a chunked type-out is running, so the cancel shortcut can reach it.
+static TYPING_ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Set by the cancel shortcut; checked between typing chunks.
+static TYPING_CANCELLED: AtomicBool = AtomicBool::new(false);
+/// Number of characters (grapheme clusters, what one Backspace removes) inserted
+/// by the last paste, for undoing it with backspaces.
+static LAST_INSERTED_CHARS: AtomicUsize = AtomicUsize::new(0);
+/// The thread `run_on_main_thread_blocking` dispatches to.
+static MAIN_THREAD: OnceLock<ThreadId> = OnceLock::new();
+
+/// Key combinations Handy sends to the focused application.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KeyCombo {
+    CtrlV,
+    CtrlShiftV,
+    ShiftInsert,
+    CtrlC,
+    Enter,
+    ShiftEnter,
+}
 
-    // Write text to clipboard first
-    clipboard
-        .write_text(text)
-        .map_err(|e| format!("Failed to write to clipboard: {}", e))?;
+impl KeyCombo {
+    fn for_paste_method(paste_method: &PasteMethod) -> Option<Self> {
+        match paste_method {
+            PasteMethod::CtrlV => Some(KeyCombo::CtrlV),
+            PasteMethod::CtrlShiftV => Some(KeyCombo::CtrlShiftV),
+            PasteMethod::ShiftInsert => Some(KeyCombo::ShiftInsert),
+            _ => None,
+        }
+    }
 
-    std::thread::sleep(std::time::Duration::from_millis(50));
+    /// Modifier mask and keysym for the in-process Wayland virtual keyboard.
+    #[cfg(target_os = "linux")]
+    fn wayland_shortcut(self) -> (u32, &'static str) {
+        match self {
+            KeyCombo::CtrlV => (wayland_input::MOD_CONTROL, "v"),
+            KeyCombo::CtrlShiftV => (wayland_input::MOD_CONTROL | wayland_input::MOD_SHIFT, "v"),
+            KeyCombo::ShiftInsert => (wayland_input::MOD_SHIFT, "Insert"),
+            KeyCombo::CtrlC => (wayland_input::MOD_CONTROL, "c"),
+            KeyCombo::Enter => (0, "Return"),
+            KeyCombo::ShiftEnter => (wayland_input::MOD_SHIFT, "Return"),
+        }
+    }
+}
 
-    // Send paste key combo
+/// Sends a key combination, preferring Linux-native tools and falling back to enigo.
+fn send_key_combo(enigo: &mut Enigo, combo: KeyCombo) -> Result<(), String> {
     #[cfg(target_os = "linux")]
-    let key_combo_sent = try_send_key_combo_linux(paste_method)?;
+    let key_combo_sent = try_send_key_combo_linux(combo)?;
 
     #[cfg(not(target_os = "linux"))]
     let key_combo_sent = false;
 
     // Fall back to enigo if no native tool handled it
     if !key_combo_sent {
-        match paste_method {
-            PasteMethod::CtrlV => input::send_paste_ctrl_v(enigo)?,
-            PasteMethod::CtrlShiftV => input::send_paste_ctrl_shift_v(enigo)?,
-            PasteMethod::ShiftInsert => input::send_paste_shift_insert(enigo)?,
-            _ => return Err("Invalid paste method for clipboard paste".into()),
+        match combo {
+            KeyCombo::CtrlV => input::send_paste_ctrl_v(enigo)?,
+            KeyCombo::CtrlShiftV => input::send_paste_ctrl_shift_v(enigo)?,
+            KeyCombo::ShiftInsert => input::send_paste_shift_insert(enigo)?,
+            KeyCombo::CtrlC => input::send_copy_ctrl_c(enigo)?,
+            KeyCombo::Enter => input::send_enter(enigo, false)?,
+            KeyCombo::ShiftEnter => input::send_enter(enigo, true)?,
         }
     }
 
-    std::thread::sleep(std::time::Duration::from_millis(50));
+    Ok(())
+}
 
-    // Restore original clipboard content
+/// Sends a key combination from the main thread and waits for it.
+fn send_key_combo_on_main_thread(app_handle: &AppHandle, combo: KeyCombo) -> Result<(), String> {
+    let app = app_handle.clone();
+    run_on_main_thread_blocking(app_handle, move || {
+        with_enigo(&app, |enigo| send_key_combo(enigo, combo))
+    })?
+}
+
+/// Pastes text using the clipboard: snapshots current content, writes text, sends paste
+/// keystroke, restores the snapshot and verifies it. Only the keystroke runs on the
+/// main thread; the clipboard work and the waits stay on the calling thread.
+fn paste_via_clipboard(
+    text: &str,
+    app_handle: &AppHandle,
+    settings: &AppSettings,
+) -> Result<(), String> {
+    let delay = Duration::from_millis(settings.clipboard_delay_ms);
+    let clipboard = app_handle.clipboard();
+    let snapshot = ClipboardSnapshot::capture(app_handle);
+
+    // Write text to clipboard first
     clipboard
-        .write_text(&clipboard_content)
-        .map_err(|e| format!("Failed to restore clipboard: {}", e))?;
+        .write_text(text)
+        .map_err(|e| format!("Failed to write to clipboard: {}", e))?;
+
+    std::thread::sleep(delay);
+
+    // Send paste key combo
+    let combo = KeyCombo::for_paste_method(&settings.paste_method)
+        .ok_or_else(|| "Invalid paste method for clipboard paste".to_string())?;
+    send_key_combo_on_main_thread(app_handle, combo)?;
 
+    std::thread::sleep(delay);
+
+    // Restore original clipboard content
+    snapshot.restore(app_handle, delay);
     Ok(())
 }
 
 /// Attempts to send a key combination using Linux-native tools.
 /// Returns `Ok(true)` if a native tool handled it, `Ok(false)` to fall back to enigo.
 #[cfg(target_os = "linux")]
-fn try_send_key_combo_linux(paste_method: &PasteMethod) -> Result<bool, String> {
+fn try_send_key_combo_linux(combo: KeyCombo) -> Result<bool, String> {
     if is_wayland() {
-        // Wayland: prefer wtype, then dotool, then ydotool
+        // Wayland: prefer the virtual keyboard protocol, then wtype, dotool, ydotool
+        match VirtualKeyboard::connect() {
+            Ok(mut keyboard) => {
+                info!("Using virtual keyboard protocol for key combo");
+                let (modifiers, keysym) = combo.wayland_shortcut();
+                keyboard.send_shortcut(modifiers, keysym)?;
+                return Ok(true);
+            }
+            Err(e) => debug!("Virtual keyboard unavailable: {}", e),
+        }
         if is_wtype_available() {
             info!("Using wtype for key combo");
-            send_key_combo_via_wtype(paste_method)?;
+            send_key_combo_via_wtype(combo)?;
             return Ok(true);
         }
         if is_dotool_available() {
             info!("Using dotool for key combo");
-            send_key_combo_via_dotool(paste_method)?;
+            send_key_combo_via_dotool(combo)?;
             return Ok(true);
         }
         if is_ydotool_available() {
             info!("Using ydotool for key combo");
-            send_key_combo_via_ydotool(paste_method)?;
+            send_key_combo_via_ydotool(combo)?;
             return Ok(true);
         }
     } else {
         // X11: prefer xdotool, then ydotool
         if is_xdotool_available() {
             info!("Using xdotool for key combo");
-            send_key_combo_via_xdotool(paste_method)?;
+            send_key_combo_via_xdotool(combo)?;
             return Ok(true);
         }
         if is_ydotool_available() {
             info!("Using ydotool for key combo");
-            send_key_combo_via_ydotool(paste_method)?;
+            send_key_combo_via_ydotool(combo)?;
             return Ok(true);
         }
     }
@@ -97,7 +181,15 @@ fn try_send_key_combo_linux(paste_method: &PasteMethod) -> Result<bool, String>
 #[cfg(target_os = "linux")]
 fn try_direct_typing_linux(text: &str) -> Result<bool, String> {
     if is_wayland() {
-        // Wayland: prefer wtype, then dotool, then ydotool
+        // Wayland: prefer the virtual keyboard protocol, then wtype, dotool, ydotool
+        match VirtualKeyboard::connect() {
+            Ok(mut keyboard) => {
+                info!("Using virtual keyboard protocol for direct text input");
+                keyboard.type_text(text)?;
+                return Ok(true);
+            }
+            Err(e) => debug!("Virtual keyboard unavailable: {}", e),
+        }
         if is_wtype_available() {
             info!("Using wtype for direct text input");
             type_text_via_wtype(text)?;
@@ -254,12 +346,14 @@ fn type_text_via_ydotool(text: &str) -> Result<(), String> {
 
 /// Send a key combination (e.g., Ctrl+V) via wtype on Wayland.
 #[cfg(target_os = "linux")]
-fn send_key_combo_via_wtype(paste_method: &PasteMethod) -> Result<(), String> {
-    let args: Vec<&str> = match paste_method {
-        PasteMethod::CtrlV => vec!["-M", "ctrl", "-k", "v"],
-        PasteMethod::ShiftInsert => vec!["-M", "shift", "-k", "Insert"],
-        PasteMethod::CtrlShiftV => vec!["-M", "ctrl", "-M", "shift", "-k", "v"],
-        _ => return Err("Unsupported paste method".into()),
+fn send_key_combo_via_wtype(combo: KeyCombo) -> Result<(), String> {
+    let args: Vec<&str> = match combo {
+        KeyCombo::CtrlV => vec!["-M", "ctrl", "-k", "v"],
+        KeyCombo::ShiftInsert => vec!["-M", "shift", "-k", "Insert"],
+        KeyCombo::CtrlShiftV => vec!["-M", "ctrl", "-M", "shift", "-k", "v"],
+        KeyCombo::CtrlC => vec!["-M", "ctrl", "-k", "c"],
+        KeyCombo::Enter => vec!["-k", "Return"],
+        KeyCombo::ShiftEnter => vec!["-M", "shift", "-k", "Return"],
     };
 
     let output = Command::new("wtype")
@@ -277,14 +371,15 @@ fn send_key_combo_via_wtype(paste_method: &PasteMethod) -> Result<(), String> {
 
 /// Send a key combination (e.g., Ctrl+V) via dotool.
 #[cfg(target_os = "linux")]
-fn send_key_combo_via_dotool(paste_method: &PasteMethod) -> Result<(), String> {
-    let command;
-    match paste_method {
-        PasteMethod::CtrlV => command = "echo key ctrl+v | dotool",
-        PasteMethod::ShiftInsert => command = "echo key shift+insert | dotool",
-        PasteMethod::CtrlShiftV => command = "echo key ctrl+shift+v | dotool",
-        _ => return Err("Unsupported paste method".into()),
-    }
+fn send_key_combo_via_dotool(combo: KeyCombo) -> Result<(), String> {
+    let command = match combo {
+        KeyCombo::CtrlV => "echo key ctrl+v | dotool",
+        KeyCombo::ShiftInsert => "echo key shift+insert | dotool",
+        KeyCombo::CtrlShiftV => "echo key ctrl+shift+v | dotool",
+        KeyCombo::CtrlC => "echo key ctrl+c | dotool",
+        KeyCombo::Enter => "echo key enter | dotool",
+        KeyCombo::ShiftEnter => "echo key shift+enter | dotool",
+    };
     let output = Command::new("sh")
         .arg("-c")
         .arg(command)
@@ -300,14 +395,16 @@ fn send_key_combo_via_dotool(paste_method: &PasteMethod) -> Result<(), String> {
 
 /// Send a key combination (e.g., Ctrl+V) via ydotool (requires ydotoold daemon).
 #[cfg(target_os = "linux")]
-fn send_key_combo_via_ydotool(paste_method: &PasteMethod) -> Result<(), String> {
+fn send_key_combo_via_ydotool(combo: KeyCombo) -> Result<(), String> {
     // ydotool uses Linux input event keycodes with format <keycode>:<pressed>
-    // where pressed is 1 for down, 0 for up. Keycodes: ctrl=29, shift=42, v=47, insert=110
-    let args: Vec<&str> = match paste_method {
-        PasteMethod::CtrlV => vec!["key", "29:1", "47:1", "47:0", "29:0"],
-        PasteMethod::ShiftInsert => vec!["key", "42:1", "110:1", "110:0", "42:0"],
-        PasteMethod::CtrlShiftV => vec!["key", "29:1", "42:1", "47:1", "47:0", "42:0", "29:0"],
-        _ => return Err("Unsupported paste method".into()),
+    // where pressed is 1 for down, 0 for up. Keycodes: enter=28, ctrl=29, shift=42, c=46, v=47, insert=110
+    let args: Vec<&str> = match combo {
+        KeyCombo::CtrlV => vec!["key", "29:1", "47:1", "47:0", "29:0"],
+        KeyCombo::ShiftInsert => vec!["key", "42:1", "110:1", "110:0", "42:0"],
+        KeyCombo::CtrlShiftV => vec!["key", "29:1", "42:1", "47:1", "47:0", "42:0", "29:0"],
+        KeyCombo::CtrlC => vec!["key", "29:1", "46:1", "46:0", "29:0"],
+        KeyCombo::Enter => vec!["key", "28:1", "28:0"],
+        KeyCombo::ShiftEnter => vec!["key", "42:1", "28:1", "28:0", "42:0"],
     };
 
     let output = Command::new("ydotool")
@@ -325,12 +422,14 @@ fn send_key_combo_via_ydotool(paste_method: &PasteMethod) -> Result<(), String>
 
 /// Send a key combination (e.g., Ctrl+V) via xdotool on X11.
 #[cfg(target_os = "linux")]
-fn send_key_combo_via_xdotool(paste_method: &PasteMethod) -> Result<(), String> {
-    let key_combo = match paste_method {
-        PasteMethod::CtrlV => "ctrl+v",
-        PasteMethod::CtrlShiftV => "ctrl+shift+v",
-        PasteMethod::ShiftInsert => "shift+Insert",
-        _ => return Err("Unsupported paste method".into()),
+fn send_key_combo_via_xdotool(combo: KeyCombo) -> Result<(), String> {
+    let key_combo = match combo {
+        KeyCombo::CtrlV => "ctrl+v",
+        KeyCombo::CtrlShiftV => "ctrl+shift+v",
+        KeyCombo::ShiftInsert => "shift+Insert",
+        KeyCombo::CtrlC => "ctrl+c",
+        KeyCombo::Enter => "Return",
+        KeyCombo::ShiftEnter => "shift+Return",
     };
 
     let output = Command::new("xdotool")
@@ -361,20 +460,109 @@ fn paste_direct(enigo: &mut Enigo, text: &str) -> Result<(), String> {
     input::paste_text_direct(enigo, text)
 }
 
-pub fn paste(text: String, app_handle: AppHandle) -> Result<(), String> {
-    let settings = get_settings(&app_handle);
-    let paste_method = settings.paste_method;
+/// Copies the current selection of the focused application and returns it.
+/// The clipboard is cleared before sending the copy keystroke so an empty result
+/// means nothing was selected, and the original text content is restored afterwards.
+///
+/// `shortcut` is the binding that triggered the capture. Its modifiers are
+/// released first, otherwise the application would see e.g. Ctrl+Shift+C.
+///
+/// Only the keystrokes are sent from the main thread; the waits and the
+/// clipboard reads stay on the calling thread.
+pub fn capture_selection(app_handle: &AppHandle, shortcut: &str) -> Result<String, String> {
+    let delay = Duration::from_millis(get_settings(app_handle).clipboard_delay_ms);
+    let clipboard = app_handle.clipboard();
+    let snapshot = ClipboardSnapshot::capture(app_handle);
 
-    // Append trailing space if setting is enabled
-    let text = if settings.append_trailing_space {
-        format!("{} ", text)
-    } else {
-        text
-    };
+    clipboard
+        .write_text("")
+        .map_err(|e| format!("Failed to clear clipboard: {}", e))?;
+
+    let app = app_handle.clone();
+    let shortcut = shortcut.to_string();
+    let released = run_on_main_thread_blocking(app_handle, move || {
+        with_enigo(&app, |enigo| {
+            input::release_shortcut_modifiers(enigo, &shortcut)
+        })
+    })?;
+    if let Err(e) = released {
+        warn!("Failed to release shortcut modifiers: {}", e);
+    }
 
-    info!("Using paste method: {:?}", paste_method);
+    std::thread::sleep(delay);
+
+    send_key_combo_on_main_thread(app_handle, KeyCombo::CtrlC)?;
+
+    // Give the focused application time to publish the selection
+    std::thread::sleep(delay * 3);
 
-    // Get the managed Enigo instance
+    let selection = clipboard.read_text().unwrap_or_default();
+
+    // Restore original clipboard content
+    snapshot.restore(app_handle, delay);
+
+    info!("Captured selection ({} chars)", selection.len());
+    Ok(selection)
+}
+
+/// A unit of chunked typing: a run of text or a line break sent as a key press.
+#[derive(Debug, PartialEq, Eq)]
+enum TypingChunk {
+    Text(String),
+    Newline,
+}
+
+/// Splits text into runs of at most `chunk_size` characters, with every line
+/// break as its own chunk. `\r\n` counts as a single line break. Characters are
+/// grapheme clusters, so emoji and combining marks are never split.
+fn split_typing_chunks(text: &str, chunk_size: usize) -> Vec<TypingChunk> {
+    let chunk_size = chunk_size.max(1);
+    let mut chunks = Vec::new();
+
+    for (i, line) in text.replace("\r\n", "\n").split('\n').enumerate() {
+        if i > 0 {
+            chunks.push(TypingChunk::Newline);
+        }
+        let graphemes: Vec<&str> = line.graphemes(true).collect();
+        for run in graphemes.chunks(chunk_size) {
+            chunks.push(TypingChunk::Text(run.concat()));
+        }
+    }
+
+    chunks
+}
+
+/// Records the calling thread as the main thread. Called once from app setup.
+pub fn register_main_thread() {
+    let _ = MAIN_THREAD.set(std::thread::current().id());
+}
+
+/// Runs `f` on the main thread and waits for its result.
+fn run_on_main_thread_blocking<T, F>(app_handle: &AppHandle, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    // Shortcut callbacks may already run there, and waiting on the main
+    // thread from itself never returns
+    if MAIN_THREAD.get() == Some(&std::thread::current().id()) {
+        return Ok(f());
+    }
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    app_handle
+        .run_on_main_thread(move || {
+            let _ = tx.send(f());
+        })
+        .map_err(|e| format!("Failed to run on main thread: {}", e))?;
+    rx.recv()
+        .map_err(|_| "Main thread dropped the paste task".to_string())
+}
+
+fn with_enigo<T>(
+    app_handle: &AppHandle,
+    f: impl FnOnce(&mut Enigo) -> Result<T, String>,
+) -> Result<T, String> {
     let enigo_state = app_handle
         .try_state::<EnigoState>()
         .ok_or("Enigo state not initialized")?;
@@ -382,19 +570,122 @@ pub fn paste(text: String, app_handle: AppHandle) -> Result<(), String> {
         .0
         .lock()
         .map_err(|e| format!("Failed to lock Enigo: {}", e))?;
+    f(&mut enigo)
+}
+
+/// Returns whether a chunked type-out is in progress.
+pub fn is_typing() -> bool {
+    TYPING_ACTIVE.load(Ordering::SeqCst)
+}
+
+/// Stops an in-progress chunked type-out before its next chunk.
+pub fn cancel_typing() {
+    if is_typing() {
+        info!("Cancelling chunked typing");
+        TYPING_CANCELLED.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Types text chunk by chunk with a pause in between. Each chunk is typed on the
+/// main thread while the waiting happens here, so the cancel shortcut stays live.
+/// Returns the number of characters typed before finishing or being cancelled.
+fn type_in_chunks(
+    app_handle: &AppHandle,
+    text: &str,
+    settings: &AppSettings,
+) -> Result<usize, String> {
+    let chunks = split_typing_chunks(text, settings.typing_chunk_size);
+    let delay = Duration::from_millis(settings.typing_chunk_delay_ms);
+    let newline = if settings.typing_shift_enter_newlines {
+        KeyCombo::ShiftEnter
+    } else {
+        KeyCombo::Enter
+    };
+
+    TYPING_CANCELLED.store(false, Ordering::SeqCst);
+    TYPING_ACTIVE.store(true, Ordering::SeqCst);
+    shortcut::register_cancel_shortcut(app_handle);
+
+    let mut typed = 0;
+    let mut result = Ok(());
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(delay);
+        }
+        if TYPING_CANCELLED.load(Ordering::SeqCst) {
+            info!("Chunked typing cancelled after {} chunks", i);
+            break;
+        }
+
+        let chunk_chars = match &chunk {
+            TypingChunk::Text(text) => text.graphemes(true).count(),
+            TypingChunk::Newline => 1,
+        };
+        let app = app_handle.clone();
+        result = run_on_main_thread_blocking(app_handle, move || {
+            with_enigo(&app, |enigo| match chunk {
+                TypingChunk::Text(text) => paste_direct(enigo, &text),
+                TypingChunk::Newline => send_key_combo(enigo, newline),
+            })
+        })
+        .and_then(|r| r);
+        if result.is_err() {
+            break;
+        }
+        typed += chunk_chars;
+    }
+
+    TYPING_ACTIVE.store(false, Ordering::SeqCst);
+    if !app_handle
+        .state::<Arc<AudioRecordingManager>>()
+        .is_recording()
+    {
+        shortcut::unregister_cancel_shortcut(app_handle);
+    }
+
+    result.map(|()| typed)
+}
+
+/// Pastes `text` into the focused application using the paste method and
+/// formatting from `settings`, which may carry per-application overrides.
+///
+/// Must be called off the main thread: keystrokes are dispatched to it and
+/// awaited, which lets chunked typing pause between chunks without freezing the UI.
+pub fn paste(text: String, app_handle: AppHandle, settings: &AppSettings) -> Result<(), String> {
+    let paste_method = settings.paste_method;
+
+    // Append trailing space if setting is enabled
+    let text = if settings.append_trailing_space {
+        format!("{} ", text)
+    } else {
+        text
+    };
+
+    info!("Using paste method: {:?}", paste_method);
 
     // Perform the paste operation
-    match paste_method {
+    let inserted_chars = match paste_method {
         PasteMethod::None => {
             info!("PasteMethod::None selected - skipping paste action");
+            0
+        }
+        PasteMethod::Direct if settings.typing_chunk_size > 0 => {
+            type_in_chunks(&app_handle, &text, settings)?
         }
         PasteMethod::Direct => {
-            paste_direct(&mut enigo, &text)?;
+            let app = app_handle.clone();
+            let text = text.clone();
+            run_on_main_thread_blocking(&app_handle, move || {
+                with_enigo(&app, |enigo| paste_direct(enigo, &text))
+            })??;
+            text.graphemes(true).count()
         }
         PasteMethod::CtrlV | PasteMethod::CtrlShiftV | PasteMethod::ShiftInsert => {
-            paste_via_clipboard(&mut enigo, &text, &app_handle, &paste_method)?
+            paste_via_clipboard(&text, &app_handle, settings)?;
+            text.graphemes(true).count()
         }
-    }
+    };
+    LAST_INSERTED_CHARS.store(inserted_chars, Ordering::SeqCst);
 
     // After pasting, optionally copy to clipboard based on settings
     if settings.clipboard_handling == ClipboardHandling::CopyToClipboard {
@@ -406,3 +697,140 @@ pub fn paste(text: String, app_handle: AppHandle) -> Result<(), String> {
 
     Ok(())
 }
+
+/// Removes the text inserted by the last paste by sending one Backspace per
+/// character. Returns the number of characters removed; a second call is a no-op.
+///
+/// Must be called off the main thread, like `paste`.
+pub fn undo_last_paste(app_handle: &AppHandle) -> Result<usize, String> {
+    let count = LAST_INSERTED_CHARS.swap(0, Ordering::SeqCst);
+    if count == 0 {
+        return Ok(0);
+    }
+
+    #[cfg(target_os = "linux")]
+    if try_send_backspaces_linux(count)? {
+        return Ok(count);
+    }
+
+    let app = app_handle.clone();
+    run_on_main_thread_blocking(app_handle, move || {
+        with_enigo(&app, |enigo| input::send_backspaces(enigo, count))
+    })??;
+    Ok(count)
+}
+
+/// Attempts to send `count` backspaces using Linux-native tools.
+/// Returns `Ok(true)` if a native tool handled it, `Ok(false)` to fall back to enigo.
+#[cfg(target_os = "linux")]
+fn try_send_backspaces_linux(count: usize) -> Result<bool, String> {
+    let count_arg = count.to_string();
+    let (program, args): (&str, Vec<String>) = if is_wayland() {
+        match VirtualKeyboard::connect() {
+            Ok(mut keyboard) => {
+                info!("Using virtual keyboard protocol for backspaces");
+                keyboard.repeat_key("BackSpace", count)?;
+                return Ok(true);
+            }
+            Err(e) => debug!("Virtual keyboard unavailable: {}", e),
+        }
+        if is_wtype_available() {
+            let args = std::iter::repeat(["-k".to_string(), "BackSpace".to_string()])
+                .take(count)
+                .flatten()
+                .collect();
+            ("wtype", args)
+        } else if is_ydotool_available() {
+            // Linux input event keycode for Backspace is 14
+            let args = std::iter::once("key".to_string())
+                .chain(
+                    std::iter::repeat(["14:1".to_string(), "14:0".to_string()])
+                        .take(count)
+                        .flatten(),
+                )
+                .collect();
+            ("ydotool", args)
+        } else {
+            return Ok(false);
+        }
+    } else if is_xdotool_available() {
+        let args = [
+            "key",
+            "--clearmodifiers",
+            "--repeat",
+            &count_arg,
+            "BackSpace",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+        ("xdotool", args)
+    } else {
+        return Ok(false);
+    };
+
+    info!("Using {} for backspaces", program);
+    let output = Command::new(program)
+        .args(&args)
+        .output()
+        .map_err(|e| format!("Failed to execute {}: {}", program, e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} failed: {}", program, stderr));
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> TypingChunk {
+        TypingChunk::Text(s.to_string())
+    }
+
+    #[test]
+    fn splits_text_into_fixed_size_chunks() {
+        assert_eq!(
+            split_typing_chunks("abcdefg", 3),
+            vec![text("abc"), text("def"), text("g")]
+        );
+    }
+
+    #[test]
+    fn turns_line_breaks_into_newline_chunks() {
+        assert_eq!(
+            split_typing_chunks("ab\r\n\ncd", 10),
+            vec![
+                text("ab"),
+                TypingChunk::Newline,
+                TypingChunk::Newline,
+                text("cd")
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        assert_eq!(
+            split_typing_chunks("héllo", 2),
+            vec![text("hé"), text("ll"), text("o")]
+        );
+    }
+
+    #[test]
+    fn keeps_combining_marks_and_emoji_together() {
+        // "e" + combining acute, and a family emoji joined by zero-width joiners
+        let text_with_clusters = "e\u{301}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}!";
+        assert_eq!(
+            split_typing_chunks(text_with_clusters, 1),
+            vec![
+                text("e\u{301}"),
+                text("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}"),
+                text("!")
+            ]
+        );
+    }
+}
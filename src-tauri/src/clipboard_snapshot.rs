@@ -0,0 +1,352 @@
+//! Saving and restoring the user's clipboard around a clipboard paste.
+//!
+//! Text and images go through the clipboard plugin. HTML is read with
+//! `osascript` on macOS, and on Linux the richest MIME target (image, file list
+//! or HTML) is copied byte-for-byte through wl-clipboard or xclip.
+//!
+//! Only one rich format survives: wl-copy and xclip serve a single MIME type
+//! per process, and the plugin writes text, HTML with its text alternative, or
+//! an image. A clipboard offering several formats at once (e.g. an image with
+//! its file list, or RTF next to HTML) comes back as the richest of them. The
+//! formats left behind are logged when the snapshot is taken.
+//!
+//! Restoring is best effort. The paste has already happened by then, so a
+//! restore that fails or can't be verified is only logged.
+
+use log::{debug, warn};
+use std::time::Duration;
+use tauri::image::Image;
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+#[cfg(target_os = "linux")]
+use crate::utils::is_wayland;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+
+const RESTORE_ATTEMPTS: u32 = 3;
+
+/// MIME targets worth preserving byte-for-byte, most specific first.
+#[cfg(target_os = "linux")]
+const PREFERRED_TARGETS: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/",
+    "x-special/gnome-copied-files",
+    "text/uri-list",
+    "text/html",
+];
+
+/// Clipboard contents captured before Handy overwrites the clipboard.
+pub struct ClipboardSnapshot {
+    text: Option<String>,
+    html: Option<String>,
+    image: Option<Image<'static>>,
+    /// The richest MIME target and its raw bytes
+    #[cfg(target_os = "linux")]
+    native: Option<(String, Vec<u8>)>,
+}
+
+impl ClipboardSnapshot {
+    pub fn capture(app_handle: &AppHandle) -> Self {
+        let clipboard = app_handle.clipboard();
+        let text = clipboard.read_text().ok().filter(|t| !t.is_empty());
+        let image = if text.is_none() {
+            clipboard.read_image().ok().map(|image| image.to_owned())
+        } else {
+            None
+        };
+
+        #[cfg(target_os = "linux")]
+        let (html, native) = match read_native_target() {
+            // HTML round-trips through the plugin together with its text fallback
+            Some((target, bytes)) if target.starts_with("text/html") => {
+                (String::from_utf8(bytes).ok(), None)
+            }
+            native => (None, native),
+        };
+        #[cfg(not(target_os = "linux"))]
+        let html = read_html();
+
+        let snapshot = ClipboardSnapshot {
+            text,
+            html,
+            image,
+            #[cfg(target_os = "linux")]
+            native,
+        };
+        debug!("Captured clipboard snapshot: {}", snapshot.describe());
+        snapshot
+    }
+
+    /// Writes the snapshot back and checks that the clipboard holds it again,
+    /// retrying up to `RESTORE_ATTEMPTS` times. Failures are logged only.
+    pub fn restore(&self, app_handle: &AppHandle, delay: Duration) {
+        for attempt in 1..=RESTORE_ATTEMPTS {
+            if let Err(e) = self.write(app_handle) {
+                warn!("{}", e);
+                return;
+            }
+            std::thread::sleep(delay);
+
+            if self.is_current(app_handle) {
+                return;
+            }
+            debug!(
+                "Clipboard restore not visible yet (attempt {}/{})",
+                attempt, RESTORE_ATTEMPTS
+            );
+        }
+
+        warn!(
+            "Clipboard restore could not be verified ({})",
+            self.describe()
+        );
+    }
+
+    fn write(&self, app_handle: &AppHandle) -> Result<(), String> {
+        #[cfg(target_os = "linux")]
+        if let Some((target, bytes)) = &self.native {
+            match write_native_target(target, bytes) {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!("Native clipboard restore failed, using plugin: {}", e),
+            }
+        }
+
+        let clipboard = app_handle.clipboard();
+        let result = match (&self.html, &self.image, &self.text) {
+            (Some(html), _, text) => clipboard.write_html(html.as_str(), text.as_deref()),
+            (None, Some(image), _) => clipboard.write_image(image),
+            (None, None, Some(text)) => clipboard.write_text(text.as_str()),
+            (None, None, None) => clipboard.clear(),
+        };
+        result.map_err(|e| format!("Failed to restore clipboard: {}", e))
+    }
+
+    fn is_current(&self, app_handle: &AppHandle) -> bool {
+        #[cfg(target_os = "linux")]
+        if let Some((target, _)) = &self.native {
+            return list_native_targets().iter().any(|t| t == target);
+        }
+
+        let clipboard = app_handle.clipboard();
+        match (&self.text, &self.image) {
+            (Some(text), _) => clipboard.read_text().ok().as_ref() == Some(text),
+            (None, Some(_)) => clipboard.read_image().is_ok(),
+            (None, None) => clipboard.read_text().map(|t| t.is_empty()).unwrap_or(true),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(text) = &self.text {
+            parts.push(format!("text {} chars", text.len()));
+        }
+        if self.html.is_some() {
+            parts.push("html".to_string());
+        }
+        if let Some(image) = &self.image {
+            parts.push(format!("image {}x{}", image.width(), image.height()));
+        }
+        #[cfg(target_os = "linux")]
+        if let Some((target, bytes)) = &self.native {
+            parts.push(format!("{} {} bytes", target, bytes.len()));
+        }
+        if parts.is_empty() {
+            "empty".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Reads HTML through AppleScript, which returns it as `«data HTML<hex>»`.
+#[cfg(target_os = "macos")]
+fn read_html() -> Option<String> {
+    let output = Command::new("osascript")
+        .args(["-e", "the clipboard as «class HTML»"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let hex = stdout
+        .trim()
+        .strip_prefix("«data HTML")?
+        .strip_suffix('»')?;
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// HTML is not readable through the clipboard plugin on Windows.
+#[cfg(target_os = "windows")]
+fn read_html() -> Option<String> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn list_native_targets() -> Vec<String> {
+    let output = if is_wayland() {
+        Command::new("wl-paste").arg("--list-types").output()
+    } else {
+        Command::new("xclip")
+            .args(["-selection", "clipboard", "-t", "TARGETS", "-o"])
+            .output()
+    };
+
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pick_native_target(targets: &[String]) -> Option<&String> {
+    PREFERRED_TARGETS
+        .iter()
+        .find_map(|preferred| targets.iter().find(|t| t.starts_with(preferred)))
+}
+
+/// MIME targets offered next to `kept` that a snapshot keeping `kept` can't
+/// bring back. Plain text is restored through the plugin unless an image or
+/// file list took its place. X11 aliases like `UTF8_STRING` are left out.
+#[cfg(target_os = "linux")]
+fn lost_targets<'a>(targets: &'a [String], kept: Option<&str>) -> Vec<&'a str> {
+    let text_restored = kept.is_none_or(|kept| kept.starts_with("text/html"));
+    targets
+        .iter()
+        .map(String::as_str)
+        .filter(|t| t.contains('/') && Some(*t) != kept)
+        .filter(|t| !(text_restored && t.starts_with("text/plain")))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn read_native_target() -> Option<(String, Vec<u8>)> {
+    let targets = list_native_targets();
+    let target = pick_native_target(&targets).cloned();
+    let lost = lost_targets(&targets, target.as_deref());
+    if !lost.is_empty() {
+        debug!(
+            "Clipboard formats that won't be restored: {}",
+            lost.join(", ")
+        );
+    }
+    let target = target?;
+
+    let output = if is_wayland() {
+        Command::new("wl-paste")
+            .args(["--no-newline", "--type", &target])
+            .output()
+    } else {
+        Command::new("xclip")
+            .args(["-selection", "clipboard", "-t", &target, "-o"])
+            .output()
+    }
+    .ok()?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return None;
+    }
+    Some((target, output.stdout))
+}
+
+/// Offers `bytes` as `target` through wl-copy or xclip, which keep serving the
+/// selection from a background process.
+#[cfg(target_os = "linux")]
+fn write_native_target(target: &str, bytes: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let (program, mut command) = if is_wayland() {
+        let mut command = Command::new("wl-copy");
+        command.args(["--type", target]);
+        ("wl-copy", command)
+    } else {
+        let mut command = Command::new("xclip");
+        command.args(["-selection", "clipboard", "-t", target, "-i"]);
+        ("xclip", command)
+    };
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn {}: {}", program, e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(bytes)
+            .map_err(|e| format!("Failed to write to {}: {}", program, e))?;
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| format!("Failed to wait for {}: {}", program, e))?;
+    if !status.success() {
+        return Err(format!("{} exited with {}", program, status));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_images_over_html_and_text() {
+        let targets: Vec<String> = ["TARGETS", "text/plain", "text/html", "image/png"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(
+            pick_native_target(&targets).map(String::as_str),
+            Some("image/png")
+        );
+    }
+
+    #[test]
+    fn only_the_richest_format_is_kept() {
+        let targets: Vec<String> = [
+            "TARGETS",
+            "UTF8_STRING",
+            "text/plain;charset=utf-8",
+            "text/html",
+            "text/rtf",
+            "image/png",
+        ]
+        .iter()
+        .map(|t| t.to_string())
+        .collect();
+
+        // An image replaces everything else, plain text included
+        assert_eq!(
+            lost_targets(&targets, Some("image/png")),
+            vec!["text/plain;charset=utf-8", "text/html", "text/rtf"]
+        );
+        // HTML keeps its plain text alternative
+        assert_eq!(
+            lost_targets(&targets, Some("text/html")),
+            vec!["text/rtf", "image/png"]
+        );
+    }
+
+    #[test]
+    fn ignores_plain_text_only_clipboards() {
+        let targets: Vec<String> = ["UTF8_STRING", "text/plain;charset=utf-8"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(pick_native_target(&targets), None);
+    }
+}
@@ -1,10 +1,16 @@
 use crate::audio_feedback;
-use crate::audio_toolkit::audio::{list_input_devices, list_output_devices};
-use crate::managers::audio::{AudioRecordingManager, MicrophoneMode};
-use crate::settings::{get_settings, write_settings};
+use crate::audio_toolkit::audio::{list_input_devices, list_monitor_sources, list_output_devices};
+use crate::audio_toolkit::{get_cpal_host, AudioRecorder};
+use crate::managers::audio::AudioRecordingManager;
+use crate::settings::{
+    get_settings, update_settings, AudioSource, MicrophoneChannel, MicrophoneCondition,
+    MicrophoneRule,
+};
+use cpal::traits::HostTrait;
 use log::warn;
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tauri::{AppHandle, Manager};
 
@@ -37,26 +43,32 @@ pub struct AudioDevice {
     pub index: String,
     pub name: String,
     pub is_default: bool,
+    /// Input channels the recorder captures; only set for microphones
+    pub channels: Option<u16>,
+}
+
+/// A PulseAudio/PipeWire monitor source that can be recorded.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct SystemAudioSource {
+    pub name: String,
+    pub description: String,
+}
+
+/// Channel count of an input device, or `None` if it can't be queried.
+fn input_channels(device: &cpal::Device) -> Option<u16> {
+    AudioRecorder::input_channels(device)
+        .map_err(|e| warn!("Failed to query input channels: {}", e))
+        .ok()
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn update_microphone_mode(app: AppHandle, always_on: bool) -> Result<(), String> {
-    // Update settings
-    let mut settings = get_settings(&app);
-    settings.always_on_microphone = always_on;
-    write_settings(&app, settings);
-
-    // Update the audio manager mode
-    let rm = app.state::<Arc<AudioRecordingManager>>();
-    let new_mode = if always_on {
-        MicrophoneMode::AlwaysOn
-    } else {
-        MicrophoneMode::OnDemand
-    };
-
-    rm.update_mode(new_mode)
-        .map_err(|e| format!("Failed to update microphone mode: {}", e))
+    // The audio manager switches modes when it sees the change
+    update_settings(&app, |settings| {
+        settings.always_on_microphone = always_on;
+    });
+    Ok(())
 }
 
 #[tauri::command]
@@ -76,9 +88,13 @@ pub fn get_available_microphones() -> Result<Vec<AudioDevice>, String> {
         index: "default".to_string(),
         name: "Default".to_string(),
         is_default: true,
+        channels: get_cpal_host()
+            .default_input_device()
+            .and_then(|d| input_channels(&d)),
     }];
 
     result.extend(devices.into_iter().map(|d| AudioDevice {
+        channels: input_channels(&d.device),
         index: d.index,
         name: d.name,
         is_default: false, // The explicit default is handled separately
@@ -87,22 +103,103 @@ pub fn get_available_microphones() -> Result<Vec<AudioDevice>, String> {
     Ok(result)
 }
 
+#[tauri::command]
+#[specta::specta]
+pub fn get_microphone_channels(app: AppHandle) -> HashMap<String, MicrophoneChannel> {
+    get_settings(&app).microphone_channels
+}
+
+/// Sets how `device_name` is mixed down to mono.
+#[tauri::command]
+#[specta::specta]
+pub fn set_microphone_channel(
+    app: AppHandle,
+    device_name: String,
+    channel: MicrophoneChannel,
+) -> Result<(), String> {
+    update_settings(&app, |settings| {
+        if channel == MicrophoneChannel::Average {
+            settings.microphone_channels.remove(&device_name);
+        } else {
+            settings
+                .microphone_channels
+                .insert(device_name.clone(), channel);
+        }
+    });
+    Ok(())
+}
+
 #[tauri::command]
 #[specta::specta]
 pub fn set_selected_microphone(app: AppHandle, device_name: String) -> Result<(), String> {
-    let mut settings = get_settings(&app);
-    settings.selected_microphone = if device_name == "default" {
-        None
-    } else {
-        Some(device_name)
-    };
-    write_settings(&app, settings);
+    update_settings(&app, |settings| {
+        let device_name = (device_name != "default").then_some(device_name);
+        settings.set_top_microphone(MicrophoneCondition::Always, device_name);
+    });
+    Ok(())
+}
 
-    // Update the audio manager to use the new device
-    let rm = app.state::<Arc<AudioRecordingManager>>();
-    rm.update_selected_device()
-        .map_err(|e| format!("Failed to update selected device: {}", e))?;
+#[tauri::command]
+#[specta::specta]
+pub fn get_microphone_priority(app: AppHandle) -> Vec<MicrophoneRule> {
+    get_settings(&app).microphone_priority
+}
 
+/// Replaces the ordered microphone rules.
+#[tauri::command]
+#[specta::specta]
+pub fn set_microphone_priority(app: AppHandle, rules: Vec<MicrophoneRule>) -> Result<(), String> {
+    if rules.iter().any(|r| r.pattern.trim().is_empty()) {
+        return Err("Microphone rules need a device name or pattern".to_string());
+    }
+
+    update_settings(&app, |settings| {
+        settings.microphone_priority = rules;
+        settings.sync_microphone_selectors();
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_system_audio_sources() -> Vec<SystemAudioSource> {
+    list_monitor_sources()
+        .into_iter()
+        .map(|source| SystemAudioSource {
+            name: source.name,
+            description: source.description,
+        })
+        .collect()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_audio_source(app: AppHandle) -> AudioSource {
+    get_settings(&app).audio_source
+}
+
+/// Switches between microphone, system audio or both.
+#[tauri::command]
+#[specta::specta]
+pub fn set_audio_source(app: AppHandle, source: AudioSource) -> Result<(), String> {
+    if source != AudioSource::Microphone && list_monitor_sources().is_empty() {
+        return Err("No system audio source found, PulseAudio or PipeWire is required".to_string());
+    }
+
+    update_settings(&app, |settings| {
+        settings.audio_source = source;
+    });
+    Ok(())
+}
+
+/// Picks the monitor source for system audio; `"default"` follows the
+/// default output device.
+#[tauri::command]
+#[specta::specta]
+pub fn set_system_audio_source(app: AppHandle, source_name: String) -> Result<(), String> {
+    update_settings(&app, |settings| {
+        settings.system_audio_source = (source_name != "default").then_some(source_name);
+    });
     Ok(())
 }
 
@@ -125,12 +222,14 @@ pub fn get_available_output_devices() -> Result<Vec<AudioDevice>, String> {
         index: "default".to_string(),
         name: "Default".to_string(),
         is_default: true,
+        channels: None,
     }];
 
     result.extend(devices.into_iter().map(|d| AudioDevice {
         index: d.index,
         name: d.name,
         is_default: false, // The explicit default is handled separately
+        channels: None,
     }));
 
     Ok(result)
@@ -139,13 +238,13 @@ pub fn get_available_output_devices() -> Result<Vec<AudioDevice>, String> {
 #[tauri::command]
 #[specta::specta]
 pub fn set_selected_output_device(app: AppHandle, device_name: String) -> Result<(), String> {
-    let mut settings = get_settings(&app);
-    settings.selected_output_device = if device_name == "default" {
-        None
-    } else {
-        Some(device_name)
-    };
-    write_settings(&app, settings);
+    update_settings(&app, |settings| {
+        settings.selected_output_device = if device_name == "default" {
+            None
+        } else {
+            Some(device_name)
+        };
+    });
     Ok(())
 }
 
@@ -175,13 +274,10 @@ pub async fn play_test_sound(app: AppHandle, sound_type: String) {
 #[tauri::command]
 #[specta::specta]
 pub fn set_clamshell_microphone(app: AppHandle, device_name: String) -> Result<(), String> {
-    let mut settings = get_settings(&app);
-    settings.clamshell_microphone = if device_name == "default" {
-        None
-    } else {
-        Some(device_name)
-    };
-    write_settings(&app, settings);
+    update_settings(&app, |settings| {
+        let device_name = (device_name != "default").then_some(device_name);
+        settings.set_top_microphone(MicrophoneCondition::Clamshell, device_name);
+    });
     Ok(())
 }
 
@@ -1,5 +1,6 @@
 use crate::cloud_stt::{self, CloudSTTProvider};
-use crate::settings::{get_settings, write_settings};
+use crate::secrets;
+use crate::settings::{get_settings, update_settings};
 use tauri::AppHandle;
 
 #[tauri::command]
@@ -10,43 +11,63 @@ pub fn get_cloud_stt_providers() -> Vec<CloudSTTProvider> {
 
 #[tauri::command]
 #[specta::specta]
-pub async fn test_cloud_stt_connection(provider_id: String, api_key: String) -> Result<bool, String> {
+pub async fn test_cloud_stt_connection(
+    app: AppHandle,
+    provider_id: String,
+    api_key: String,
+) -> Result<bool, String> {
+    // A masked key means "test the key that is already stored"
+    let settings = get_settings(&app);
+    let api_key =
+        if secrets::is_unchanged_mask(&settings.cloud_stt_api_keys, &provider_id, &api_key) {
+            secrets::resolve_api_key(&settings.cloud_stt_api_keys, &provider_id)
+        } else {
+            api_key
+        };
     cloud_stt::test_connection(&provider_id, &api_key).await
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn set_cloud_stt_enabled(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = get_settings(&app);
-    settings.cloud_stt_enabled = enabled;
-    write_settings(&app, settings);
+    update_settings(&app, |settings| {
+        settings.cloud_stt_enabled = enabled;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn set_cloud_stt_provider(app: AppHandle, provider_id: String) -> Result<(), String> {
-    let mut settings = get_settings(&app);
-    settings.cloud_stt_provider = Some(provider_id);
-    write_settings(&app, settings);
+    update_settings(&app, |settings| {
+        settings.cloud_stt_provider = Some(provider_id);
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn set_cloud_stt_api_key(app: AppHandle, provider_id: String, api_key: String) -> Result<(), String> {
-    let mut settings = get_settings(&app);
-    settings.cloud_stt_api_keys.insert(provider_id, api_key);
-    write_settings(&app, settings);
+    let settings = get_settings(&app);
+
+    // The frontend only ever sees the masked key; saving it back changes nothing
+    if secrets::is_unchanged_mask(&settings.cloud_stt_api_keys, &provider_id, &api_key) {
+        return Ok(());
+    }
+
+    let reference = secrets::store_api_key("cloud_stt", &provider_id, &api_key)?;
+    update_settings(&app, |settings| {
+        settings.cloud_stt_api_keys.insert(provider_id, reference);
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn set_cloud_stt_model(app: AppHandle, provider_id: String, model_id: String) -> Result<(), String> {
-    let mut settings = get_settings(&app);
-    settings.cloud_stt_models.insert(provider_id, model_id);
-    write_settings(&app, settings);
+    update_settings(&app, |settings| {
+        settings.cloud_stt_models.insert(provider_id, model_id);
+    });
     Ok(())
 }
 
@@ -59,7 +80,7 @@ pub fn get_cloud_stt_config(
     Ok(cloud_stt::CloudSTTConfig {
         enabled: settings.cloud_stt_enabled,
         active_provider: settings.cloud_stt_provider.clone(),
-        api_keys: settings.cloud_stt_api_keys.clone(),
+        api_keys: secrets::mask_api_keys(&settings.cloud_stt_api_keys),
         selected_models: settings.cloud_stt_models.clone(),
     })
 }
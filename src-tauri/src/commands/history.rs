@@ -1,6 +1,24 @@
-use crate::managers::history::{HistoryEntry, HistoryManager};
+use crate::corrections::{suggest_corrections, CorrectionSuggestion};
+use crate::managers::history::{
+    recording_format, HistoryEntry, HistoryManager, HistoryStorageStats, HistoryTag,
+};
+use crate::settings::{RecordingFormat, RetentionPolicy};
+use crate::usage_stats::{UsageRange, UsageStats};
+use serde::Serialize;
+use specta::Type;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Emitter, State};
+
+/// Set while `convert_recordings` is running in the background
+static CONVERTING_RECORDINGS: AtomicBool = AtomicBool::new(false);
+
+/// Payload of the `recording-conversion-progress` event
+#[derive(Clone, Debug, Serialize, Type)]
+pub struct RecordingConversionProgress {
+    pub done: usize,
+    pub total: usize,
+}
 
 #[tauri::command]
 #[specta::specta]
@@ -40,6 +58,119 @@ pub async fn get_audio_file_path(
         .map(|s| s.to_string())
 }
 
+/// Stores a hand-corrected transcript next to the raw and post-processed
+/// text. An empty `text` removes the correction.
+#[tauri::command]
+#[specta::specta]
+pub fn update_history_entry_text(
+    history_manager: State<'_, Arc<HistoryManager>>,
+    id: i64,
+    text: String,
+) -> Result<(), String> {
+    let text = text.trim();
+    let corrected_text = (!text.is_empty()).then(|| text.to_string());
+    history_manager
+        .update_entry_text(id, corrected_text)
+        .map_err(|e| e.to_string())
+}
+
+/// Words, speaking time, time saved, latency per model and the most frequent
+/// corrections over `range`.
+#[tauri::command]
+#[specta::specta]
+pub fn get_usage_stats(
+    history_manager: State<'_, Arc<HistoryManager>>,
+    range: UsageRange,
+) -> Result<UsageStats, String> {
+    history_manager
+        .get_usage_stats(range)
+        .map_err(|e| e.to_string())
+}
+
+/// Renames an entry. An empty `title` goes back to the date and time.
+#[tauri::command]
+#[specta::specta]
+pub fn update_history_entry_title(
+    history_manager: State<'_, Arc<HistoryManager>>,
+    id: i64,
+    title: String,
+) -> Result<(), String> {
+    let title = title.trim();
+    history_manager
+        .update_entry_title(id, (!title.is_empty()).then(|| title.to_string()))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn update_history_entry_notes(
+    history_manager: State<'_, Arc<HistoryManager>>,
+    id: i64,
+    notes: String,
+) -> Result<(), String> {
+    let notes = (!notes.trim().is_empty()).then_some(notes);
+    history_manager
+        .update_entry_notes(id, notes)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn add_history_entry_tag(
+    history_manager: State<'_, Arc<HistoryManager>>,
+    id: i64,
+    tag: String,
+) -> Result<(), String> {
+    history_manager.add_tag(id, &tag).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_history_entry_tag(
+    history_manager: State<'_, Arc<HistoryManager>>,
+    id: i64,
+    tag: String,
+) -> Result<(), String> {
+    history_manager
+        .remove_tag(id, &tag)
+        .map_err(|e| e.to_string())
+}
+
+/// Every tag in use, with the number of entries carrying it.
+#[tauri::command]
+#[specta::specta]
+pub fn get_history_tags(
+    history_manager: State<'_, Arc<HistoryManager>>,
+) -> Result<Vec<HistoryTag>, String> {
+    history_manager.get_tags().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_history_entries_by_tag(
+    history_manager: State<'_, Arc<HistoryManager>>,
+    tag: String,
+) -> Result<Vec<HistoryEntry>, String> {
+    history_manager
+        .get_entries_by_tag(&tag)
+        .map_err(|e| e.to_string())
+}
+
+/// Suggests `custom_words` entries and replacement rules from words that were
+/// corrected the same way repeatedly.
+#[tauri::command]
+#[specta::specta]
+pub fn analyze_history_corrections(
+    app: AppHandle,
+    history_manager: State<'_, Arc<HistoryManager>>,
+) -> Result<Vec<CorrectionSuggestion>, String> {
+    let corrections = history_manager
+        .get_corrections()
+        .map_err(|e| e.to_string())?;
+    let settings = crate::settings::get_settings(&app);
+    Ok(suggest_corrections(&corrections, &settings.custom_words))
+}
+
 #[tauri::command]
 #[specta::specta]
 pub async fn delete_history_entry(
@@ -60,9 +191,9 @@ pub async fn update_history_limit(
     history_manager: State<'_, Arc<HistoryManager>>,
     limit: usize,
 ) -> Result<(), String> {
-    let mut settings = crate::settings::get_settings(&app);
-    settings.history_limit = limit;
-    crate::settings::write_settings(&app, settings);
+    crate::settings::update_settings(&app, |settings| {
+        settings.set_legacy_retention(settings.recording_retention_period, limit);
+    });
 
     history_manager
         .cleanup_old_entries()
@@ -89,9 +220,62 @@ pub async fn update_recording_retention_period(
         _ => return Err(format!("Invalid retention period: {}", period)),
     };
 
-    let mut settings = crate::settings::get_settings(&app);
-    settings.recording_retention_period = retention_period;
-    crate::settings::write_settings(&app, settings);
+    crate::settings::update_settings(&app, |settings| {
+        settings.set_legacy_retention(retention_period, settings.history_limit);
+    });
+
+    history_manager
+        .cleanup_old_entries()
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Sets how long recordings (`audio`) and history entries (`text`) are kept.
+#[tauri::command]
+#[specta::specta]
+pub async fn update_history_retention(
+    app: AppHandle,
+    history_manager: State<'_, Arc<HistoryManager>>,
+    audio: RetentionPolicy,
+    text: RetentionPolicy,
+) -> Result<(), String> {
+    for policy in [audio, text] {
+        if matches!(
+            policy,
+            RetentionPolicy::Days(0) | RetentionPolicy::Months(0)
+        ) {
+            return Err("Retention must be at least one day".to_string());
+        }
+    }
+
+    crate::settings::update_settings(&app, |settings| {
+        settings.audio_retention = audio;
+        settings.text_retention = text;
+    });
+
+    history_manager
+        .cleanup_old_entries()
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Sets the total size recordings may take up; `None` removes the quota.
+#[tauri::command]
+#[specta::specta]
+pub async fn update_recordings_quota(
+    app: AppHandle,
+    history_manager: State<'_, Arc<HistoryManager>>,
+    quota_mb: Option<u64>,
+) -> Result<(), String> {
+    if quota_mb == Some(0) {
+        return Err("Recordings quota must be at least 1 MB".to_string());
+    }
+
+    crate::settings::update_settings(&app, |settings| {
+        settings.recordings_quota_mb = quota_mb;
+    });
 
     history_manager
         .cleanup_old_entries()
@@ -99,3 +283,67 @@ pub async fn update_recording_retention_period(
 
     Ok(())
 }
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_history_storage_stats(
+    history_manager: State<'_, Arc<HistoryManager>>,
+) -> Result<HistoryStorageStats, String> {
+    history_manager
+        .get_storage_stats()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn update_recording_format(
+    app: AppHandle,
+    format: RecordingFormat,
+    opus_bitrate_kbps: Option<u32>,
+) -> Result<(), String> {
+    if let Some(bitrate) = opus_bitrate_kbps {
+        if !(6..=128).contains(&bitrate) {
+            return Err(format!("Opus bitrate must be 6-128 kbps, got {}", bitrate));
+        }
+    }
+
+    crate::settings::update_settings(&app, |settings| {
+        settings.recording_format = format;
+        if let Some(bitrate) = opus_bitrate_kbps {
+            settings.recording_opus_bitrate_kbps = bitrate;
+        }
+    });
+
+    Ok(())
+}
+
+/// Converts the existing WAV recordings to the configured recording format in
+/// the background, reporting through `recording-conversion-progress` events.
+#[tauri::command]
+#[specta::specta]
+pub fn convert_recordings(
+    app: AppHandle,
+    history_manager: State<'_, Arc<HistoryManager>>,
+) -> Result<(), String> {
+    if CONVERTING_RECORDINGS.swap(true, Ordering::SeqCst) {
+        return Err("Recordings are already being converted".to_string());
+    }
+
+    let format = recording_format(&crate::settings::get_settings(&app));
+    let history_manager = Arc::clone(&history_manager);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = history_manager.convert_recordings(format, |done, total| {
+            let _ = app.emit(
+                "recording-conversion-progress",
+                RecordingConversionProgress { done, total },
+            );
+        });
+        if let Err(e) = result {
+            log::error!("Failed to convert recordings: {}", e);
+        }
+        CONVERTING_RECORDINGS.store(false, Ordering::SeqCst);
+    });
+
+    Ok(())
+}
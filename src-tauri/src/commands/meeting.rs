@@ -0,0 +1,26 @@
+use crate::managers::meeting::{MeetingManager, MeetingStatus};
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+#[specta::specta]
+pub fn start_meeting(
+    meeting_manager: State<'_, Arc<MeetingManager>>,
+) -> Result<MeetingStatus, String> {
+    meeting_manager.start()
+}
+
+/// Stops the running meeting. Returns `None` if there was none.
+#[tauri::command]
+#[specta::specta]
+pub fn stop_meeting(meeting_manager: State<'_, Arc<MeetingManager>>) -> Option<MeetingStatus> {
+    meeting_manager.stop()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_meeting_status(
+    meeting_manager: State<'_, Arc<MeetingManager>>,
+) -> Option<MeetingStatus> {
+    meeting_manager.status()
+}
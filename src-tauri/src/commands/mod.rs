@@ -1,10 +1,12 @@
 pub mod audio;
 pub mod cloud_stt;
 pub mod history;
+pub mod meeting;
 pub mod models;
+pub mod profiles;
 pub mod transcription;
 
-use crate::settings::{get_settings, write_settings, AppSettings, LogLevel};
+use crate::settings::{get_settings, update_settings, AppSettings, LogLevel};
 use crate::utils::cancel_current_operation;
 use tauri::{AppHandle, Manager};
 use tauri_plugin_opener::OpenerExt;
@@ -29,7 +31,11 @@ pub fn get_app_dir_path(app: AppHandle) -> Result<String, String> {
 #[tauri::command]
 #[specta::specta]
 pub fn get_app_settings(app: AppHandle) -> Result<AppSettings, String> {
-    Ok(get_settings(&app))
+    let mut settings = get_settings(&app);
+    // API keys never leave the backend in clear text
+    settings.post_process_api_keys = crate::secrets::mask_api_keys(&settings.post_process_api_keys);
+    settings.cloud_stt_api_keys = crate::secrets::mask_api_keys(&settings.cloud_stt_api_keys);
+    Ok(settings)
 }
 
 #[tauri::command]
@@ -60,9 +66,9 @@ pub fn set_log_level(app: AppHandle, level: LogLevel) -> Result<(), String> {
         std::sync::atomic::Ordering::Relaxed,
     );
 
-    let mut settings = get_settings(&app);
-    settings.log_level = level;
-    write_settings(&app, settings);
+    update_settings(&app, |settings| {
+        settings.log_level = level;
+    });
 
     Ok(())
 }
@@ -1,6 +1,6 @@
 use crate::managers::model::{ModelInfo, ModelManager};
 use crate::managers::transcription::TranscriptionManager;
-use crate::settings::{get_settings, write_settings};
+use crate::settings::{get_settings, update_settings};
 use std::sync::Arc;
 use tauri::{AppHandle, State};
 
@@ -67,9 +67,9 @@ pub async fn set_active_model(
         .map_err(|e| e.to_string())?;
 
     // Update settings
-    let mut settings = get_settings(&app_handle);
-    settings.selected_model = model_id.clone();
-    write_settings(&app_handle, settings);
+    update_settings(&app_handle, |settings| {
+        settings.selected_model = model_id.clone();
+    });
 
     Ok(())
 }
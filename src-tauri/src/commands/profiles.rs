@@ -0,0 +1,276 @@
+use crate::managers::model::ModelManager;
+use crate::managers::transcription::TranscriptionManager;
+use crate::post_process;
+use crate::settings::{
+    get_settings, update_settings, AppSettings, ProfileSettings, SettingsProfile,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+/// On-disk format for exported profiles.
+#[derive(Serialize, Deserialize)]
+struct ProfileFile {
+    name: String,
+    settings: ProfileSettings,
+}
+
+/// Applies the profile `id` to the current settings and makes it active.
+/// Used by the settings UI, the tray menu and the profile shortcut.
+pub fn activate_profile(app: &AppHandle, id: &str) -> Result<SettingsProfile, String> {
+    let profile = get_settings(app)
+        .profiles
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Profile with id '{}' not found", id))?;
+
+    // Looked up before taking the settings lock, which the model manager
+    // takes itself while holding its own
+    let model_downloaded = app
+        .state::<Arc<ModelManager>>()
+        .get_model_info(&profile.settings.selected_model)
+        .is_some_and(|info| info.is_downloaded);
+
+    let (model_id, reload_model) = update_settings(app, |settings| {
+        validate_profile(settings, &profile.settings)?;
+        let previous_model = settings.selected_model.clone();
+        profile.settings.apply_to(settings);
+
+        // Only switch to models that are actually available on this machine
+        if settings.selected_model != previous_model && !model_downloaded {
+            warn!(
+                "Profile model '{}' is not downloaded, keeping '{}'",
+                settings.selected_model, previous_model
+            );
+            settings.selected_model = previous_model.clone();
+        }
+
+        let model_id = settings.selected_model.clone();
+        let reload_model =
+            model_id != previous_model && !settings.cloud_stt_enabled && !model_id.is_empty();
+        settings.active_profile_id = Some(profile.id.clone());
+        Ok::<_, String>((model_id, reload_model))
+    })?;
+
+    if reload_model {
+        let tm = Arc::clone(&app.state::<Arc<TranscriptionManager>>());
+        std::thread::spawn(move || {
+            if let Err(e) = tm.load_model(&model_id) {
+                warn!("Failed to load profile model '{}': {}", model_id, e);
+            }
+        });
+    }
+
+    info!("Activated profile '{}'", profile.name);
+
+    Ok(profile)
+}
+
+/// Saves the current settings as a new profile.
+#[tauri::command]
+#[specta::specta]
+pub fn create_profile(app: AppHandle, name: String) -> Result<SettingsProfile, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Profile name cannot be empty".to_string());
+    }
+
+    let profile = update_settings(&app, |settings| {
+        let profile = SettingsProfile {
+            id: format!("profile_{}", chrono::Utc::now().timestamp_millis()),
+            name,
+            settings: ProfileSettings::capture(settings),
+        };
+
+        settings.profiles.push(profile.clone());
+        settings.active_profile_id = Some(profile.id.clone());
+        profile
+    });
+
+    Ok(profile)
+}
+
+/// Overwrites a profile with the current settings, optionally renaming it.
+#[tauri::command]
+#[specta::specta]
+pub fn update_profile(
+    app: AppHandle,
+    id: String,
+    name: Option<String>,
+) -> Result<SettingsProfile, String> {
+    let name = name.map(|n| n.trim().to_string());
+    if name.as_deref().is_some_and(str::is_empty) {
+        return Err("Profile name cannot be empty".to_string());
+    }
+
+    update_settings(&app, |settings| {
+        let captured = ProfileSettings::capture(settings);
+
+        let profile = settings
+            .profiles
+            .iter_mut()
+            .find(|p| p.id == id)
+            .ok_or_else(|| format!("Profile with id '{}' not found", id))?;
+        if let Some(name) = name {
+            profile.name = name;
+        }
+        profile.settings = captured;
+        Ok(profile.clone())
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn delete_profile(app: AppHandle, id: String) -> Result<(), String> {
+    update_settings(&app, |settings| {
+        let original_len = settings.profiles.len();
+        settings.profiles.retain(|p| p.id != id);
+        if settings.profiles.len() == original_len {
+            return Err(format!("Profile with id '{}' not found", id));
+        }
+
+        if settings.active_profile_id.as_deref() == Some(id.as_str()) {
+            settings.active_profile_id = None;
+        }
+        Ok(())
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn apply_profile(app: AppHandle, id: String) -> Result<SettingsProfile, String> {
+    activate_profile(&app, &id)
+}
+
+/// Writes a profile to `path` as JSON. Profiles never contain API keys.
+#[tauri::command]
+#[specta::specta]
+pub fn export_profile(app: AppHandle, id: String, path: String) -> Result<(), String> {
+    let settings = get_settings(&app);
+    let profile = settings
+        .profiles
+        .iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Profile with id '{}' not found", id))?;
+
+    let file = ProfileFile {
+        name: profile.name.clone(),
+        settings: profile.settings.clone(),
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize profile: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Reads a profile exported by `export_profile` and adds it as a new profile.
+#[tauri::command]
+#[specta::specta]
+pub fn import_profile(app: AppHandle, path: String) -> Result<SettingsProfile, String> {
+    let json =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let file = parse_profile_file(&json)?;
+
+    update_settings(&app, |settings| {
+        validate_profile(settings, &file.settings)?;
+        let profile = SettingsProfile {
+            id: format!("profile_{}", chrono::Utc::now().timestamp_millis()),
+            name: file.name,
+            settings: file.settings,
+        };
+
+        settings.profiles.push(profile.clone());
+        Ok(profile)
+    })
+}
+
+/// Checks the settings `profile` would leave behind when applied on top of
+/// `settings`, as the pipeline and prompt commands check their input.
+fn validate_profile(settings: &AppSettings, profile: &ProfileSettings) -> Result<(), String> {
+    let mut applied = settings.clone();
+    profile.apply_to(&mut applied);
+
+    let mut seen_ids = HashSet::new();
+    for stage in &applied.post_process_pipeline {
+        if !seen_ids.insert(stage.id.as_str()) {
+            return Err(format!("Duplicate stage id '{}'", stage.id));
+        }
+        post_process::validate_stage(&applied, stage)?;
+    }
+
+    let prompt_exists = |id: &str| applied.post_process_prompts.iter().any(|p| p.id == id);
+    if let Some(prompt_id) = &applied.post_process_selected_prompt_id {
+        if !prompt_exists(prompt_id) {
+            return Err(format!("Prompt with id '{}' not found", prompt_id));
+        }
+    }
+    if let Some(rule) = applied.app_rules.iter().find(|rule| {
+        rule.prompt_id
+            .as_deref()
+            .is_some_and(|id| !prompt_exists(id))
+    }) {
+        return Err(format!(
+            "App rule '{}' uses a prompt the profile doesn't have",
+            rule.name
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_profile_file(json: &str) -> Result<ProfileFile, String> {
+    let file: ProfileFile =
+        serde_json::from_str(json).map_err(|e| format!("Invalid profile file: {}", e))?;
+    if file.name.trim().is_empty() {
+        return Err("Invalid profile file: name is empty".to_string());
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::get_default_settings;
+
+    #[test]
+    fn exported_profile_round_trips_without_api_keys() {
+        let mut settings = get_default_settings();
+        settings
+            .post_process_api_keys
+            .insert("openai".to_string(), "sk-secret".to_string());
+        settings
+            .cloud_stt_api_keys
+            .insert("openai".to_string(), "sk-cloud".to_string());
+        settings.selected_language = "de".to_string();
+
+        let file = ProfileFile {
+            name: "Meeting notes".to_string(),
+            settings: ProfileSettings::capture(&settings),
+        };
+        let json = serde_json::to_string(&file).unwrap();
+        assert!(!json.contains("sk-secret"));
+        assert!(!json.contains("sk-cloud"));
+
+        let parsed = parse_profile_file(&json).unwrap();
+        assert_eq!(parsed.name, "Meeting notes");
+        assert_eq!(parsed.settings.selected_language, "de");
+    }
+
+    #[test]
+    fn rejects_profile_with_unknown_prompt() {
+        let settings = get_default_settings();
+        let mut profile = ProfileSettings::capture(&settings);
+        assert!(validate_profile(&settings, &profile).is_ok());
+
+        profile.post_process_selected_prompt_id = Some("missing".to_string());
+        assert!(validate_profile(&settings, &profile).is_err());
+    }
+
+    #[test]
+    fn rejects_profile_without_name() {
+        let json =
+            r#"{"name": " ", "settings": {"selected_model": "", "selected_language": "en"}}"#;
+        assert!(parse_profile_file(json).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! Learning from transcripts corrected by hand
+//!
+//! Every corrected history entry is diffed word by word against its raw
+//! transcription. Words and short phrases that were replaced the same way
+//! several times become suggestions: a `custom_words` entry when the fix is a
+//! spelling of something that sounds alike, a `RegexReplace` stage otherwise.
+
+use serde::Serialize;
+use specta::Type;
+use std::collections::HashMap;
+use strsim::normalized_levenshtein;
+
+/// A fix has to show up this often before it is suggested
+const MIN_OCCURRENCES: usize = 2;
+/// Longest phrase, in words, considered a single replacement
+const MAX_PHRASE_WORDS: usize = 3;
+/// Pairs of transcripts with more word pairs than this aren't diffed
+const MAX_DIFF_CELLS: usize = 4_000_000;
+/// Spelling similarity above which a fix counts as a custom word
+const CUSTOM_WORD_SIMILARITY: f64 = 0.5;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CorrectionSuggestion {
+    /// Add `word` to `custom_words`
+    CustomWord {
+        word: String,
+        /// What the transcription produced instead
+        heard_as: Vec<String>,
+        occurrences: usize,
+    },
+    /// Add a `RegexReplace` stage with `pattern` and `replacement`
+    Replacement {
+        original: String,
+        corrected: String,
+        pattern: String,
+        replacement: String,
+        occurrences: usize,
+    },
+}
+
+/// One replaced run of words: `original` in the transcription, `corrected` by hand.
+#[derive(Debug, PartialEq)]
+struct Substitution {
+    original: String,
+    corrected: String,
+}
+
+/// How often a word or phrase was replaced by the same correction.
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+pub struct CorrectionCount {
+    pub original: String,
+    pub corrected: String,
+    pub occurrences: usize,
+}
+
+/// Every correction in `(raw, corrected)` transcript pairs, most frequent first.
+pub fn count_corrections(pairs: &[(String, String)]) -> Vec<CorrectionCount> {
+    let mut counts: HashMap<(String, String), usize> = HashMap::new();
+    for (raw, corrected) in pairs {
+        for substitution in substitutions(raw, corrected) {
+            *counts
+                .entry((substitution.original, substitution.corrected))
+                .or_default() += 1;
+        }
+    }
+
+    let mut counts: Vec<CorrectionCount> = counts
+        .into_iter()
+        .map(|((original, corrected), occurrences)| CorrectionCount {
+            original,
+            corrected,
+            occurrences,
+        })
+        .collect();
+    counts.sort_by(|a, b| {
+        b.occurrences
+            .cmp(&a.occurrences)
+            .then_with(|| a.original.cmp(&b.original))
+            .then_with(|| a.corrected.cmp(&b.corrected))
+    });
+    counts
+}
+
+/// Suggestions from `(raw, corrected)` transcript pairs, most frequent first.
+/// Words already in `custom_words` aren't suggested again.
+pub fn suggest_corrections(
+    pairs: &[(String, String)],
+    custom_words: &[String],
+) -> Vec<CorrectionSuggestion> {
+    let known: Vec<String> = custom_words.iter().map(|w| w.to_lowercase()).collect();
+    let mut custom_words: HashMap<String, (Vec<String>, usize)> = HashMap::new();
+    let mut suggestions = Vec::new();
+
+    for CorrectionCount {
+        original,
+        corrected,
+        occurrences,
+    } in count_corrections(pairs)
+    {
+        if occurrences < MIN_OCCURRENCES {
+            continue;
+        }
+
+        if is_custom_word(&original, &corrected) {
+            if known.contains(&corrected.to_lowercase()) {
+                continue;
+            }
+            let (heard_as, count) = custom_words.entry(corrected).or_default();
+            heard_as.push(original);
+            *count += occurrences;
+        } else {
+            suggestions.push(CorrectionSuggestion::Replacement {
+                pattern: format!(r"(?i)\b{}\b", regex::escape(&original)),
+                replacement: corrected.replace('$', "$$"),
+                original,
+                corrected,
+                occurrences,
+            });
+        }
+    }
+
+    suggestions.extend(
+        custom_words
+            .into_iter()
+            .map(|(word, (mut heard_as, occurrences))| {
+                heard_as.sort();
+                CorrectionSuggestion::CustomWord {
+                    word,
+                    heard_as,
+                    occurrences,
+                }
+            }),
+    );
+    suggestions.sort_by(|a, b| {
+        occurrences(b)
+            .cmp(&occurrences(a))
+            .then_with(|| label(a).cmp(label(b)))
+    });
+    suggestions
+}
+
+fn occurrences(suggestion: &CorrectionSuggestion) -> usize {
+    match suggestion {
+        CorrectionSuggestion::CustomWord { occurrences, .. }
+        | CorrectionSuggestion::Replacement { occurrences, .. } => *occurrences,
+    }
+}
+
+fn label(suggestion: &CorrectionSuggestion) -> &str {
+    match suggestion {
+        CorrectionSuggestion::CustomWord { word, .. } => word,
+        CorrectionSuggestion::Replacement { original, .. } => original,
+    }
+}
+
+/// A single corrected word that is spelled like what was heard, e.g. a name
+/// or product the model doesn't know.
+fn is_custom_word(original: &str, corrected: &str) -> bool {
+    if corrected.contains(' ') || original.split(' ').count() > 2 {
+        return false;
+    }
+    let original: String = original.split(' ').collect();
+    normalized_levenshtein(&original.to_lowercase(), &corrected.to_lowercase())
+        >= CUSTOM_WORD_SIMILARITY
+}
+
+/// Word for comparing: lowercase without surrounding punctuation, so changes
+/// to capitalisation or punctuation alone don't count as corrections.
+fn normalize(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// The runs of words replaced between `raw` and `corrected`.
+fn substitutions(raw: &str, corrected: &str) -> Vec<Substitution> {
+    let raw_words: Vec<String> = raw
+        .split_whitespace()
+        .map(normalize)
+        .filter(|w| !w.is_empty())
+        .collect();
+    let corrected_words: Vec<&str> = corrected
+        .split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| !w.is_empty())
+        .collect();
+    let corrected_normalized: Vec<String> =
+        corrected_words.iter().map(|w| w.to_lowercase()).collect();
+
+    let (n, m) = (raw_words.len(), corrected_words.len());
+    if n * m > MAX_DIFF_CELLS {
+        log::debug!("Skipping correction diff of {} by {} words", n, m);
+        return Vec::new();
+    }
+
+    // Longest common subsequence table, lcs[i][j] for the suffixes at i and j
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if raw_words[i] == corrected_normalized[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut substitutions = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    let mut flush = |removed: &mut Vec<String>, added: &mut Vec<&str>| {
+        let is_phrase = |len: usize| (1..=MAX_PHRASE_WORDS).contains(&len);
+        if is_phrase(removed.len()) && is_phrase(added.len()) {
+            substitutions.push(Substitution {
+                original: removed.join(" "),
+                corrected: added.join(" "),
+            });
+        }
+        removed.clear();
+        added.clear();
+    };
+
+    while i < n || j < m {
+        if i < n && j < m && raw_words[i] == corrected_normalized[j] {
+            flush(&mut removed, &mut added);
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(corrected_words[j]);
+            j += 1;
+        } else {
+            removed.push(raw_words[i].clone());
+            i += 1;
+        }
+    }
+    flush(&mut removed, &mut added);
+
+    substitutions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(raw: &str, corrected: &str) -> (String, String) {
+        (raw.to_string(), corrected.to_string())
+    }
+
+    #[test]
+    fn finds_replaced_words_and_ignores_case_and_punctuation() {
+        assert_eq!(
+            substitutions(
+                "ask handy to open the get hub page",
+                "Ask Handy to open the GitHub page."
+            ),
+            vec![Substitution {
+                original: "get hub".to_string(),
+                corrected: "GitHub".to_string(),
+            }]
+        );
+        // Pure insertions and deletions aren't substitutions
+        assert!(substitutions("so um we ship it", "we ship it today").is_empty());
+    }
+
+    #[test]
+    fn suggests_repeated_corrections_only() {
+        let pairs = vec![
+            pair("call get hub", "call GitHub"),
+            pair("the get hub repo", "the GitHub repo"),
+            pair("new get hub issue", "new GitHub issue"),
+            pair("see you tomorrow", "see you Monday"),
+            pair(
+                "book the meeting for tomorrow",
+                "book the meeting for Monday",
+            ),
+            pair("one typo here", "one type here"),
+        ];
+
+        let suggestions = suggest_corrections(&pairs, &[]);
+        assert_eq!(
+            suggestions,
+            vec![
+                CorrectionSuggestion::CustomWord {
+                    word: "GitHub".to_string(),
+                    heard_as: vec!["get hub".to_string()],
+                    occurrences: 3,
+                },
+                CorrectionSuggestion::Replacement {
+                    original: "tomorrow".to_string(),
+                    corrected: "Monday".to_string(),
+                    pattern: r"(?i)\btomorrow\b".to_string(),
+                    replacement: "Monday".to_string(),
+                    occurrences: 2,
+                },
+            ]
+        );
+
+        // Known custom words aren't suggested again
+        let known = suggest_corrections(&pairs, &["github".to_string()]);
+        assert_eq!(known.len(), 1);
+    }
+}
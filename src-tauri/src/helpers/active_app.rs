@@ -0,0 +1,162 @@
+use log::debug;
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+
+/// Returns an identifier for the application that currently has keyboard focus.
+///
+/// - Linux: the X11 `WM_CLASS` class name, queried via xdotool or xprop
+///   (Wayland-native windows are not visible to either tool)
+/// - macOS: the bundle identifier of the frontmost process
+/// - Windows: the executable name of the foreground window's process
+///
+/// Returns `None` when the application cannot be determined.
+pub fn focused_app_id() -> Option<String> {
+    let app_id = detect_focused_app();
+    debug!("Focused application: {:?}", app_id);
+    app_id
+}
+
+#[cfg(target_os = "linux")]
+fn detect_focused_app() -> Option<String> {
+    focused_class_via_xdotool().or_else(focused_class_via_xprop)
+}
+
+#[cfg(target_os = "linux")]
+fn focused_class_via_xdotool() -> Option<String> {
+    let output = Command::new("xdotool")
+        .args(["getactivewindow", "getwindowclassname"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let class = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!class.is_empty()).then_some(class)
+}
+
+#[cfg(target_os = "linux")]
+fn focused_class_via_xprop() -> Option<String> {
+    let root = Command::new("xprop")
+        .args(["-root", "_NET_ACTIVE_WINDOW"])
+        .output()
+        .ok()?;
+    let window_id = parse_active_window_id(&String::from_utf8_lossy(&root.stdout))?;
+
+    let output = Command::new("xprop")
+        .args(["-id", &window_id, "WM_CLASS"])
+        .output()
+        .ok()?;
+    parse_wm_class(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extracts the window id from `_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_active_window_id(output: &str) -> Option<String> {
+    let id = output.rsplit('#').next()?.trim();
+    let id = id.split(',').next()?.trim();
+    (id.starts_with("0x") && id != "0x0").then(|| id.to_string())
+}
+
+/// Extracts the class (second value) from `WM_CLASS(STRING) = "instance", "Class"`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_wm_class(output: &str) -> Option<String> {
+    let (_, values) = output.split_once('=')?;
+    let values: Vec<&str> = values
+        .split(',')
+        .map(|v| v.trim().trim_matches('"'))
+        .filter(|v| !v.is_empty())
+        .collect();
+    values.last().map(|v| v.to_string())
+}
+
+#[cfg(target_os = "macos")]
+fn detect_focused_app() -> Option<String> {
+    let output = Command::new("osascript")
+        .args([
+            "-e",
+            "tell application \"System Events\" to get bundle identifier of first application process whose frontmost is true",
+        ])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let bundle_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!bundle_id.is_empty()).then_some(bundle_id)
+}
+
+#[cfg(target_os = "windows")]
+fn detect_focused_app() -> Option<String> {
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_invalid() {
+            return None;
+        }
+
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+        if pid == 0 {
+            return None;
+        }
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+        let mut buffer = [0u16; 260];
+        let mut len = buffer.len() as u32;
+        let result = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(buffer.as_mut_ptr()),
+            &mut len,
+        );
+        let _ = CloseHandle(process);
+        result.ok()?;
+
+        let path = String::from_utf16_lossy(&buffer[..len as usize]);
+        std::path::Path::new(&path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+fn detect_focused_app() -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_active_window_id() {
+        assert_eq!(
+            parse_active_window_id("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007\n"),
+            Some("0x3a00007".to_string())
+        );
+        assert_eq!(
+            parse_active_window_id("_NET_ACTIVE_WINDOW(WINDOW): window id # 0x0\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_wm_class() {
+        assert_eq!(
+            parse_wm_class("WM_CLASS(STRING) = \"gnome-terminal-server\", \"Gnome-terminal\"\n"),
+            Some("Gnome-terminal".to_string())
+        );
+        assert_eq!(parse_wm_class("WM_CLASS:  not found.\n"), None);
+    }
+}
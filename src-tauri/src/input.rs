@@ -86,6 +86,34 @@ pub fn send_paste_ctrl_shift_v(enigo: &mut Enigo) -> Result<(), String> {
     Ok(())
 }
 
+/// Sends a Ctrl+C or Cmd+C copy command using platform-specific virtual key codes.
+/// Note: On Wayland, this may not work - callers should check for Wayland and use alternative methods.
+pub fn send_copy_ctrl_c(enigo: &mut Enigo) -> Result<(), String> {
+    // Platform-specific key definitions
+    #[cfg(target_os = "macos")]
+    let (modifier_key, c_key_code) = (Key::Meta, Key::Other(8));
+    #[cfg(target_os = "windows")]
+    let (modifier_key, c_key_code) = (Key::Control, Key::Other(0x43)); // VK_C
+    #[cfg(target_os = "linux")]
+    let (modifier_key, c_key_code) = (Key::Control, Key::Unicode('c'));
+
+    // Press modifier + C
+    enigo
+        .key(modifier_key, enigo::Direction::Press)
+        .map_err(|e| format!("Failed to press modifier key: {}", e))?;
+    enigo
+        .key(c_key_code, enigo::Direction::Click)
+        .map_err(|e| format!("Failed to click C key: {}", e))?;
+
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    enigo
+        .key(modifier_key, enigo::Direction::Release)
+        .map_err(|e| format!("Failed to release modifier key: {}", e))?;
+
+    Ok(())
+}
+
 /// Sends a Shift+Insert paste command (Windows and Linux only).
 /// This is more universal for terminal applications and legacy software.
 /// Note: On Wayland, this may not work - callers should check for Wayland and use alternative methods.
@@ -112,6 +140,55 @@ pub fn send_paste_shift_insert(enigo: &mut Enigo) -> Result<(), String> {
     Ok(())
 }
 
+/// Presses Enter, optionally with Shift held (a soft line break in most chat apps).
+pub fn send_enter(enigo: &mut Enigo, with_shift: bool) -> Result<(), String> {
+    if with_shift {
+        enigo
+            .key(Key::Shift, enigo::Direction::Press)
+            .map_err(|e| format!("Failed to press Shift key: {}", e))?;
+    }
+
+    let result = enigo
+        .key(Key::Return, enigo::Direction::Click)
+        .map_err(|e| format!("Failed to click Enter key: {}", e));
+
+    if with_shift {
+        enigo
+            .key(Key::Shift, enigo::Direction::Release)
+            .map_err(|e| format!("Failed to release Shift key: {}", e))?;
+    }
+
+    result
+}
+
+/// Releases the modifier keys in `shortcut` (e.g. "ctrl+shift+space") that
+/// the user may still be holding, so keys sent next aren't combined with them.
+pub fn release_shortcut_modifiers(enigo: &mut Enigo, shortcut: &str) -> Result<(), String> {
+    for part in shortcut.split('+').map(|part| part.trim().to_lowercase()) {
+        let key = match part.as_str() {
+            "ctrl" | "control" => Key::Control,
+            "shift" => Key::Shift,
+            "alt" | "option" => Key::Alt,
+            "meta" | "command" | "cmd" | "super" | "win" | "windows" => Key::Meta,
+            _ => continue,
+        };
+        enigo
+            .key(key, enigo::Direction::Release)
+            .map_err(|e| format!("Failed to release {} key: {}", part, e))?;
+    }
+    Ok(())
+}
+
+/// Presses Backspace `count` times.
+pub fn send_backspaces(enigo: &mut Enigo, count: usize) -> Result<(), String> {
+    for _ in 0..count {
+        enigo
+            .key(Key::Backspace, enigo::Direction::Click)
+            .map_err(|e| format!("Failed to click Backspace key: {}", e))?;
+    }
+    Ok(())
+}
+
 /// Pastes text directly using the enigo text method.
 /// This tries to use system input methods if possible, otherwise simulates keystrokes one by one.
 pub fn paste_text_direct(enigo: &mut Enigo, text: &str) -> Result<(), String> {
@@ -4,26 +4,37 @@ mod apple_intelligence;
 mod audio_feedback;
 pub mod audio_toolkit;
 mod clipboard;
+mod clipboard_snapshot;
 pub mod cloud_stt;
 mod commands;
+mod corrections;
 mod helpers;
 mod input;
 mod llm_client;
 mod managers;
+mod output_sinks;
 mod overlay;
+mod post_process;
+mod secrets;
 mod settings;
+mod settings_migration;
 mod shortcut;
 mod signal_handle;
 mod tray;
 mod tray_i18n;
+mod usage_stats;
 mod utils;
+#[cfg(target_os = "linux")]
+mod wayland_input;
 use specta_typescript::{BigIntExportBehavior, Typescript};
 use tauri_specta::{collect_commands, Builder};
 
 use env_filter::Builder as EnvFilterBuilder;
 use managers::audio::AudioRecordingManager;
 use managers::history::HistoryManager;
+use managers::meeting::MeetingManager;
 use managers::model::ModelManager;
+use managers::settings::{SettingsChangedEvent, SettingsManager, SETTINGS_CHANGED_EVENT};
 use managers::transcription::TranscriptionManager;
 #[cfg(unix)]
 use signal_hook::consts::SIGUSR2;
@@ -36,7 +47,7 @@ use tauri::image::Image;
 
 use tauri::tray::TrayIconBuilder;
 use tauri::Emitter;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Listener, Manager};
 use tauri_plugin_autostart::{MacosLauncher, ManagerExt};
 use tauri_plugin_log::{Builder as LogBuilder, RotationStrategy, Target, TargetKind};
 
@@ -84,6 +95,8 @@ fn build_console_filter() -> env_filter::Filter {
 struct ShortcutToggleStates {
     // Map: shortcut_binding_id -> is_active
     active_toggles: HashMap<String, bool>,
+    // Map: shortcut_binding_id -> gesture state, used in gesture mode
+    gestures: HashMap<String, shortcut::gesture::GestureRecognizer>,
 }
 
 type ManagedToggleState = Mutex<ShortcutToggleStates>;
@@ -110,7 +123,38 @@ fn show_main_window(app: &AppHandle) {
     }
 }
 
+/// Lets backend subsystems react to settings written from anywhere.
+fn listen_for_settings_changes(app_handle: &AppHandle) {
+    let app = app_handle.clone();
+    app_handle.listen(SETTINGS_CHANGED_EVENT, move |event| {
+        let Ok(event) = serde_json::from_str::<SettingsChangedEvent>(event.payload()) else {
+            return;
+        };
+        if event.contains("profiles") || event.contains("active_profile_id") {
+            tray::refresh_tray_menu(&app);
+        }
+        if let Some(rm) = app.try_state::<Arc<AudioRecordingManager>>() {
+            rm.apply_settings_change(&event);
+        }
+        if event.contains("bindings") {
+            shortcut::apply_binding_changes(&app);
+        }
+    });
+}
+
 fn initialize_core_logic(app_handle: &AppHandle) {
+    // Setup runs on the main thread, which keystrokes are sent from
+    clipboard::register_main_thread();
+
+    // Move API keys out of the settings file before anything reads them
+    secrets::init(app_handle);
+
+    // Cache settings in memory; everything after this reads from the cache
+    let settings_manager =
+        Arc::new(SettingsManager::new(app_handle).expect("Failed to initialize settings manager"));
+    app_handle.manage(settings_manager);
+    listen_for_settings_changes(app_handle);
+
     // Note: Enigo (keyboard/mouse simulation) is NOT initialized here.
     // The frontend is responsible for calling the `initialize_enigo` command
     // after onboarding completes. This avoids triggering permission dialogs
@@ -134,6 +178,7 @@ fn initialize_core_logic(app_handle: &AppHandle) {
     app_handle.manage(model_manager.clone());
     app_handle.manage(transcription_manager.clone());
     app_handle.manage(history_manager.clone());
+    app_handle.manage(Arc::new(MeetingManager::new(app_handle)));
 
     // Initialize the shortcuts
     shortcut::init_shortcuts(app_handle);
@@ -193,7 +238,13 @@ fn initialize_core_logic(app_handle: &AppHandle) {
             "quit" => {
                 app.exit(0);
             }
-            _ => {}
+            other => {
+                if let Some(profile_id) = other.strip_prefix(tray::PROFILE_MENU_PREFIX) {
+                    if let Err(e) = commands::profiles::activate_profile(app, profile_id) {
+                        log::error!("Failed to activate profile from tray: {}", e);
+                    }
+                }
+            }
         })
         .build(app_handle)
         .unwrap();
@@ -240,6 +291,7 @@ pub fn run() {
         shortcut::change_binding,
         shortcut::reset_binding,
         shortcut::change_ptt_setting,
+        shortcut::change_gesture_settings,
         shortcut::change_audio_feedback_setting,
         shortcut::change_audio_feedback_volume_setting,
         shortcut::change_sound_theme_setting,
@@ -247,6 +299,7 @@ pub fn run() {
         shortcut::change_autostart_setting,
         shortcut::change_translate_to_english_setting,
         shortcut::change_selected_language_setting,
+        shortcut::set_language_cycle,
         shortcut::change_overlay_position_setting,
         shortcut::change_debug_mode_setting,
         shortcut::change_word_correction_threshold_setting,
@@ -263,11 +316,24 @@ pub fn run() {
         shortcut::update_post_process_prompt,
         shortcut::delete_post_process_prompt,
         shortcut::set_post_process_selected_prompt,
+        shortcut::set_post_process_pipeline,
+        shortcut::change_edit_selection_prompt_setting,
+        shortcut::set_app_rules,
+        shortcut::set_output_sinks,
         shortcut::update_custom_words,
         shortcut::suspend_binding,
         shortcut::resume_binding,
         shortcut::change_mute_while_recording_setting,
+        shortcut::change_audio_high_pass_filter_setting,
+        shortcut::change_audio_gain_control_setting,
+        shortcut::change_audio_normalize_loudness_setting,
+        shortcut::change_audio_noise_suppression_setting,
+        shortcut::change_meeting_notes_dir_setting,
+        shortcut::change_history_auto_title_setting,
         shortcut::change_append_trailing_space_setting,
+        shortcut::change_clipboard_delay_setting,
+        shortcut::change_typing_chunk_setting,
+        shortcut::change_typing_shift_enter_setting,
         shortcut::change_app_language_setting,
         shortcut::change_update_checks_setting,
         shortcut::change_keyboard_implementation_setting,
@@ -303,6 +369,14 @@ pub fn run() {
         commands::audio::get_available_microphones,
         commands::audio::set_selected_microphone,
         commands::audio::get_selected_microphone,
+        commands::audio::get_microphone_priority,
+        commands::audio::get_microphone_channels,
+        commands::audio::set_microphone_channel,
+        commands::audio::get_system_audio_sources,
+        commands::audio::get_audio_source,
+        commands::audio::set_audio_source,
+        commands::audio::set_system_audio_source,
+        commands::audio::set_microphone_priority,
         commands::audio::get_available_output_devices,
         commands::audio::set_selected_output_device,
         commands::audio::get_selected_output_device,
@@ -311,15 +385,38 @@ pub fn run() {
         commands::audio::set_clamshell_microphone,
         commands::audio::get_clamshell_microphone,
         commands::audio::is_recording,
+        commands::profiles::create_profile,
+        commands::profiles::update_profile,
+        commands::profiles::delete_profile,
+        commands::profiles::apply_profile,
+        commands::profiles::export_profile,
+        commands::profiles::import_profile,
         commands::transcription::set_model_unload_timeout,
         commands::transcription::get_model_load_status,
         commands::transcription::unload_model_manually,
         commands::history::get_history_entries,
+        commands::meeting::start_meeting,
+        commands::meeting::stop_meeting,
+        commands::meeting::get_meeting_status,
         commands::history::toggle_history_entry_saved,
         commands::history::get_audio_file_path,
+        commands::history::update_history_entry_text,
+        commands::history::analyze_history_corrections,
+        commands::history::update_history_entry_title,
+        commands::history::update_history_entry_notes,
+        commands::history::add_history_entry_tag,
+        commands::history::remove_history_entry_tag,
+        commands::history::get_history_tags,
+        commands::history::get_history_entries_by_tag,
+        commands::history::get_usage_stats,
         commands::history::delete_history_entry,
         commands::history::update_history_limit,
         commands::history::update_recording_retention_period,
+        commands::history::update_history_retention,
+        commands::history::update_recordings_quota,
+        commands::history::get_history_storage_stats,
+        commands::history::update_recording_format,
+        commands::history::convert_recordings,
         commands::cloud_stt::get_cloud_stt_providers,
         commands::cloud_stt::test_cloud_stt_connection,
         commands::cloud_stt::set_cloud_stt_enabled,
@@ -1,11 +1,26 @@
-use crate::audio_toolkit::{list_input_devices, vad::SmoothedVad, AudioRecorder, SileroVad};
+use crate::audio_toolkit::audio::{
+    AudioSegment, CaptureSource, ChannelSelection, DspConfig, NoiseSuppressionMethod,
+    DEFAULT_MONITOR,
+};
+use crate::audio_toolkit::{
+    get_cpal_host, list_input_devices, vad::SmoothedVad, AudioRecorder, SileroVad,
+};
 use crate::helpers::clamshell;
-use crate::settings::{get_settings, AppSettings};
+use crate::managers::settings::SettingsChangedEvent;
+use crate::settings::{
+    get_settings, AppSettings, AudioSource, MicrophoneChannel, MicrophoneCondition, MicrophoneRule,
+    NoiseSuppression,
+};
 use crate::utils;
-use log::{debug, error, info};
-use std::sync::{Arc, Mutex};
-use std::time::Instant;
-use tauri::Manager;
+use cpal::traits::{DeviceTrait, HostTrait};
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use specta::Type;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
 
 fn set_mute(mute: bool) {
     // Expected behavior:
@@ -98,6 +113,14 @@ fn set_mute(mute: bool) {
 
 const WHISPER_SAMPLE_RATE: usize = 16000;
 
+/// How often the open stream is checked for errors, and the device list while
+/// recording, so a lost microphone costs little audio
+const DEVICE_WATCH_INTERVAL: Duration = Duration::from_secs(3);
+/// How often the device list is read while idle with the stream open, to
+/// notice unplugged devices and the preferred one coming back. Listing devices
+/// is slow with some hosts (ALSA probes every card).
+const IDLE_DEVICE_SCAN_INTERVAL: Duration = Duration::from_secs(30);
+
 /* ──────────────────────────────────────────────────────────────── */
 
 #[derive(Clone, Debug)]
@@ -112,11 +135,143 @@ pub enum MicrophoneMode {
     OnDemand,
 }
 
+/// Payload of the `microphone-lost` event.
+#[derive(Clone, Debug, Serialize, Type)]
+pub struct MicrophoneLostEvent {
+    pub device: String,
+}
+
+/// Payload of the `microphone-changed` event.
+#[derive(Clone, Debug, Serialize, Type)]
+pub struct MicrophoneChangedEvent {
+    pub device: String,
+    /// The preferred microphone isn't available and this one stands in for it
+    pub is_fallback: bool,
+}
+
 /* ──────────────────────────────────────────────────────────────── */
 
+/// The microphone rules that currently apply, in priority order.
+fn active_microphone_rules(settings: &AppSettings) -> Vec<&MicrophoneRule> {
+    let has_clamshell_rules = settings
+        .microphone_priority
+        .iter()
+        .any(|r| r.condition == MicrophoneCondition::Clamshell);
+    // Only ask the OS about the lid when a rule depends on it
+    let is_clamshell = has_clamshell_rules && clamshell::is_clamshell().unwrap_or(false);
+
+    settings
+        .microphone_priority
+        .iter()
+        .filter(|r| match r.condition {
+            MicrophoneCondition::Always => true,
+            MicrophoneCondition::Clamshell => is_clamshell,
+        })
+        .collect()
+}
+
+/// Index into `names` of the device picked by the first rule that matches one.
+fn pick_microphone(rules: &[&MicrophoneRule], names: &[&str]) -> Option<usize> {
+    rules
+        .iter()
+        .find_map(|rule| names.iter().position(|name| rule.matches(name)))
+}
+
+/// Settings the microphone stream is opened with. `system_audio_source`
+/// only matters while system audio is captured.
+const DEVICE_FIELDS: &[&str] = &[
+    "audio_source",
+    "selected_microphone",
+    "clamshell_microphone",
+    "microphone_priority",
+    "microphone_channels",
+];
+
+/// Settings read into the recorder's `DspConfig`.
+const DSP_FIELDS: &[&str] = &[
+    "audio_high_pass_filter",
+    "audio_noise_suppression",
+    "audio_gain_control",
+    "audio_normalize_loudness",
+];
+
+fn dsp_config(settings: &AppSettings) -> DspConfig {
+    DspConfig {
+        high_pass: settings.audio_high_pass_filter,
+        noise_suppression: match settings.audio_noise_suppression {
+            NoiseSuppression::Off => NoiseSuppressionMethod::Off,
+            NoiseSuppression::Spectral => NoiseSuppressionMethod::SpectralSubtraction,
+            NoiseSuppression::Rnnoise => NoiseSuppressionMethod::Rnnoise,
+        },
+        agc: settings.audio_gain_control,
+        normalize: settings.audio_normalize_loudness,
+    }
+}
+
+/// The channel selection configured for `device_name`.
+fn channel_selection(settings: &AppSettings, device_name: &str) -> ChannelSelection {
+    match settings.microphone_channels.get(device_name) {
+        None | Some(MicrophoneChannel::Average) => ChannelSelection::Average,
+        Some(MicrophoneChannel::Channel(channel)) => ChannelSelection::Channel(*channel),
+        Some(MicrophoneChannel::Loudest) => ChannelSelection::Loudest,
+    }
+}
+
+fn capture_source(settings: &AppSettings) -> CaptureSource {
+    let monitor = || {
+        settings
+            .system_audio_source
+            .clone()
+            .unwrap_or_else(|| DEFAULT_MONITOR.to_string())
+    };
+    match settings.audio_source {
+        AudioSource::Microphone => CaptureSource::Microphone,
+        AudioSource::SystemAudio => CaptureSource::Monitor(monitor()),
+        AudioSource::MicrophoneAndSystemAudio => CaptureSource::Mixed(monitor()),
+    }
+}
+
+/// Opens `device`, or the system default when `None`, with its configured
+/// channel selection and the configured capture source.
+fn open_device(
+    rec: &mut AudioRecorder,
+    device: Option<cpal::Device>,
+    settings: &AppSettings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let device = device.or_else(|| get_cpal_host().default_input_device());
+    let name = device.as_ref().and_then(|d| d.name().ok());
+    rec.set_channel_selection(
+        name.map(|name| channel_selection(settings, &name))
+            .unwrap_or_default(),
+    );
+    rec.set_capture_source(capture_source(settings));
+    rec.open(device)
+}
+
+/// Opens `device`, falling back to the system default if that fails.
+fn open_recorder(
+    rec: &mut AudioRecorder,
+    device: Option<cpal::Device>,
+    settings: &AppSettings,
+) -> Result<(), String> {
+    let has_device = device.is_some();
+    match open_device(rec, device, settings) {
+        Ok(()) => Ok(()),
+        Err(e) if has_device => {
+            warn!(
+                "Failed to open microphone ({}), trying the default device",
+                e
+            );
+            open_device(rec, None, settings).map_err(|e| e.to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 fn create_audio_recorder(
     vad_path: &str,
     app_handle: &tauri::AppHandle,
+    stream_failed: Arc<AtomicBool>,
 ) -> Result<AudioRecorder, anyhow::Error> {
     let silero = SileroVad::new(vad_path, 0.3)
         .map_err(|e| anyhow::anyhow!("Failed to create SileroVad: {}", e))?;
@@ -132,7 +287,8 @@ fn create_audio_recorder(
             move |levels| {
                 utils::emit_levels(&app_handle, &levels);
             }
-        });
+        })
+        .with_error_callback(move |_| stream_failed.store(true, Ordering::SeqCst));
 
     Ok(recorder)
 }
@@ -149,6 +305,15 @@ pub struct AudioRecordingManager {
     is_open: Arc<Mutex<bool>>,
     is_recording: Arc<Mutex<bool>>,
     did_mute: Arc<Mutex<bool>>,
+
+    /// Device the stream was last opened on
+    active_device: Arc<Mutex<Option<String>>>,
+    /// Set by the audio thread when the stream errors out
+    stream_failed: Arc<AtomicBool>,
+    /// Audio recorded on a device that was lost mid-recording
+    carried_samples: Arc<Mutex<Vec<f32>>>,
+    /// Receives the audio of a segmented recording, see `try_start_segmented_recording`
+    segment_tx: Arc<Mutex<Option<mpsc::Sender<AudioSegment>>>>,
 }
 
 impl AudioRecordingManager {
@@ -171,6 +336,11 @@ impl AudioRecordingManager {
             is_open: Arc::new(Mutex::new(false)),
             is_recording: Arc::new(Mutex::new(false)),
             did_mute: Arc::new(Mutex::new(false)),
+
+            active_device: Arc::new(Mutex::new(None)),
+            stream_failed: Arc::new(AtomicBool::new(false)),
+            carried_samples: Arc::new(Mutex::new(Vec::new())),
+            segment_tx: Arc::new(Mutex::new(None)),
         };
 
         // Always-on?  Open immediately.
@@ -178,38 +348,198 @@ impl AudioRecordingManager {
             manager.start_microphone_stream()?;
         }
 
+        manager.spawn_device_watcher();
+
         Ok(manager)
     }
 
     /* ---------- helper methods --------------------------------------------- */
 
-    fn get_effective_microphone_device(&self, settings: &AppSettings) -> Option<cpal::Device> {
-        // Check if we're in clamshell mode and have a clamshell microphone configured
-        let use_clamshell_mic = if let Ok(is_clamshell) = clamshell::is_clamshell() {
-            is_clamshell && settings.clamshell_microphone.is_some()
-        } else {
-            false
+    /// Picks the device to open: the first device matched by the priority
+    /// rules, otherwise the system default, otherwise the first input found.
+    /// The flag is set when rules apply but none of their devices is connected.
+    fn get_effective_microphone_device(
+        &self,
+        settings: &AppSettings,
+    ) -> (Option<cpal::Device>, bool) {
+        let rules = active_microphone_rules(settings);
+
+        let devices = match list_input_devices() {
+            Ok(devices) => devices,
+            Err(e) => {
+                debug!("Failed to list devices, using default: {}", e);
+                return (None, false);
+            }
         };
 
-        let device_name = if use_clamshell_mic {
-            settings.clamshell_microphone.as_ref().unwrap()
-        } else {
-            settings.selected_microphone.as_ref()?
-        };
+        let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+        if let Some(index) = pick_microphone(&rules, &names) {
+            return (Some(devices[index].device.clone()), false);
+        }
+        if !rules.is_empty() {
+            warn!("No preferred microphone connected, using a fallback");
+        }
+
+        let fallback = devices
+            .iter()
+            .find(|d| d.is_default)
+            .or_else(|| devices.first())
+            .map(|d| d.device.clone());
+        (fallback, !rules.is_empty())
+    }
+
+    /// Name of the device the stream was last opened on.
+    pub fn active_microphone(&self) -> Option<String> {
+        self.active_device.lock().unwrap().clone()
+    }
+
+    /// Records which device the stream is on and tells the frontend when it
+    /// differs from the one used before.
+    fn set_active_device(&self, name: Option<String>, is_fallback: bool) {
+        let previous = std::mem::replace(&mut *self.active_device.lock().unwrap(), name.clone());
+
+        if let (Some(previous), Some(device)) = (previous, name) {
+            if previous != device {
+                info!("Microphone changed from '{}' to '{}'", previous, device);
+                let _ = self.app_handle.emit(
+                    "microphone-changed",
+                    MicrophoneChangedEvent {
+                        device,
+                        is_fallback,
+                    },
+                );
+            }
+        }
+    }
+
+    /* ---------- hot-plug handling ------------------------------------------ */
+
+    fn spawn_device_watcher(&self) {
+        let manager = self.clone();
+        thread::spawn(move || {
+            let mut last_scan = Instant::now();
+            loop {
+                thread::sleep(DEVICE_WATCH_INTERVAL);
+                if !*manager.is_open.lock().unwrap() {
+                    continue;
+                }
+
+                let due = manager.stream_failed.load(Ordering::SeqCst)
+                    || manager.is_recording()
+                    || last_scan.elapsed() >= IDLE_DEVICE_SCAN_INTERVAL;
+                if due {
+                    manager.check_microphone();
+                    last_scan = Instant::now();
+                }
+            }
+        });
+    }
+
+    /// Moves the stream to another device if the current one failed or was
+    /// unplugged, and back to the preferred device once it reappears.
+    fn check_microphone(&self) {
+        if !*self.is_open.lock().unwrap() {
+            return;
+        }
+
+        let failed = self.stream_failed.load(Ordering::SeqCst);
+        // No microphone to watch while only system audio is recorded
+        if !failed && get_settings(&self.app_handle).audio_source == AudioSource::SystemAudio {
+            return;
+        }
 
-        // Find the device by name
-        match list_input_devices() {
-            Ok(devices) => devices
-                .into_iter()
-                .find(|d| d.name == *device_name)
-                .map(|d| d.device),
+        let devices = match list_input_devices() {
+            Ok(devices) => devices,
             Err(e) => {
-                debug!("Failed to list devices, using default: {}", e);
-                None
+                debug!("Failed to list devices: {}", e);
+                return;
+            }
+        };
+        let is_connected = |name: &str| devices.iter().any(|d| d.name == name);
+
+        let active = self.active_device.lock().unwrap().clone();
+        if failed || !active.as_deref().is_some_and(is_connected) {
+            if let Some(device) = active {
+                warn!("Microphone '{}' was lost", device);
+                let _ = self
+                    .app_handle
+                    .emit("microphone-lost", MicrophoneLostEvent { device });
             }
+            self.reopen_microphone_stream();
+            return;
+        }
+
+        // Switching while recording would cut the audio, so wait until idle
+        if self.is_recording() {
+            return;
+        }
+
+        let settings = get_settings(&self.app_handle);
+        let names: Vec<&str> = devices.iter().map(|d| d.name.as_str()).collect();
+        let wanted = pick_microphone(&active_microphone_rules(&settings), &names)
+            .map(|index| devices[index].name.clone())
+            .or_else(|| {
+                devices
+                    .iter()
+                    .find(|d| d.is_default)
+                    .map(|d| d.name.clone())
+            });
+        if wanted.is_some() && wanted != active {
+            info!("Preferred microphone {:?} is available again", wanted);
+            self.reopen_microphone_stream();
         }
     }
 
+    /// Reopens the stream on the currently preferred device. A recording in
+    /// progress keeps the audio captured so far and continues on the new device.
+    fn reopen_microphone_stream(&self) {
+        let open_flag = self.is_open.lock().unwrap();
+        if !*open_flag {
+            return;
+        }
+
+        let settings = get_settings(&self.app_handle);
+        let (device, is_fallback) = self.get_effective_microphone_device(&settings);
+
+        let mut recorder_opt = self.recorder.lock().unwrap();
+        let Some(rec) = recorder_opt.as_mut() else {
+            return;
+        };
+
+        let recording = *self.is_recording.lock().unwrap();
+        let segment_tx = self.segment_tx.lock().unwrap().clone();
+        if recording {
+            match rec.stop() {
+                // A segmented recording has sent its audio on already
+                Ok(samples) => self.carried_samples.lock().unwrap().extend(samples),
+                Err(e) => warn!("Failed to collect samples from lost microphone: {}", e),
+            }
+        }
+        let _ = rec.close();
+
+        self.stream_failed.store(false, Ordering::SeqCst);
+        if let Err(e) = open_recorder(rec, device, &settings) {
+            error!("Failed to reopen microphone stream: {}", e);
+            // Retried on the next check
+            *self.active_device.lock().unwrap() = None;
+            return;
+        }
+        if recording {
+            let resumed = match segment_tx {
+                Some(tx) => rec.start_segmented(tx),
+                None => rec.start(),
+            };
+            if let Err(e) = resumed {
+                error!("Failed to resume recording on new microphone: {}", e);
+            }
+        }
+
+        let name = rec.device_name();
+        drop(recorder_opt);
+        drop(open_flag);
+        self.set_active_device(name, is_fallback);
+    }
+
     /* ---------- microphone life-cycle -------------------------------------- */
 
     /// Applies mute if mute_while_recording is enabled and stream is open
@@ -217,7 +547,9 @@ impl AudioRecordingManager {
         let settings = get_settings(&self.app_handle);
         let mut did_mute_guard = self.did_mute.lock().unwrap();
 
-        if settings.mute_while_recording && *self.is_open.lock().unwrap() {
+        // Muting the output would also silence system audio we're recording
+        let records_system_audio = settings.audio_source != AudioSource::Microphone;
+        if settings.mute_while_recording && !records_system_audio && *self.is_open.lock().unwrap() {
             set_mute(true);
             *did_mute_guard = true;
             debug!("Mute applied");
@@ -261,17 +593,23 @@ impl AudioRecordingManager {
             *recorder_opt = Some(create_audio_recorder(
                 vad_path.to_str().unwrap(),
                 &self.app_handle,
+                self.stream_failed.clone(),
             )?);
         }
 
         // Get the selected device from settings, considering clamshell mode
         let settings = get_settings(&self.app_handle);
-        let selected_device = self.get_effective_microphone_device(&settings);
+        let (selected_device, is_fallback) = self.get_effective_microphone_device(&settings);
 
+        let mut device_name = None;
         if let Some(rec) = recorder_opt.as_mut() {
-            rec.open(selected_device)
+            self.stream_failed.store(false, Ordering::SeqCst);
+            open_recorder(rec, selected_device, &settings)
                 .map_err(|e| anyhow::anyhow!("Failed to open recorder: {}", e))?;
+            device_name = rec.device_name();
         }
+        drop(recorder_opt);
+        self.set_active_device(device_name, is_fallback);
 
         *open_flag = true;
         info!(
@@ -333,6 +671,25 @@ impl AudioRecordingManager {
     /* ---------- recording --------------------------------------------------- */
 
     pub fn try_start_recording(&self, binding_id: &str) -> bool {
+        self.start_recording_with(binding_id, None)
+    }
+
+    /// Starts a recording whose audio goes to `segment_tx` in pieces cut at
+    /// pauses while it runs, the last one when it stops. `stop_recording`
+    /// then returns no audio.
+    pub fn try_start_segmented_recording(
+        &self,
+        binding_id: &str,
+        segment_tx: mpsc::Sender<AudioSegment>,
+    ) -> bool {
+        self.start_recording_with(binding_id, Some(segment_tx))
+    }
+
+    fn start_recording_with(
+        &self,
+        binding_id: &str,
+        segment_tx: Option<mpsc::Sender<AudioSegment>>,
+    ) -> bool {
         let mut state = self.state.lock().unwrap();
 
         if let RecordingState::Idle = *state {
@@ -345,7 +702,14 @@ impl AudioRecordingManager {
             }
 
             if let Some(rec) = self.recorder.lock().unwrap().as_ref() {
-                if rec.start().is_ok() {
+                self.carried_samples.lock().unwrap().clear();
+                rec.set_dsp_config(dsp_config(&get_settings(&self.app_handle)));
+                let started = match &segment_tx {
+                    Some(tx) => rec.start_segmented(tx.clone()),
+                    None => rec.start(),
+                };
+                if started.is_ok() {
+                    *self.segment_tx.lock().unwrap() = segment_tx;
                     *self.is_recording.lock().unwrap() = true;
                     *state = RecordingState::Recording {
                         binding_id: binding_id.to_string(),
@@ -370,6 +734,38 @@ impl AudioRecordingManager {
         Ok(())
     }
 
+    /// Applies a settings write to the stream, whichever command or profile
+    /// switch made it.
+    pub fn apply_settings_change(&self, event: &SettingsChangedEvent) {
+        let settings = get_settings(&self.app_handle);
+
+        if event.contains("always_on_microphone") {
+            let mode = if settings.always_on_microphone {
+                MicrophoneMode::AlwaysOn
+            } else {
+                MicrophoneMode::OnDemand
+            };
+            if let Err(e) = self.update_mode(mode) {
+                error!("Failed to update microphone mode: {}", e);
+            }
+        }
+
+        let reopen = DEVICE_FIELDS.iter().any(|field| event.contains(field))
+            || (event.contains("system_audio_source")
+                && settings.audio_source != AudioSource::Microphone);
+        if reopen {
+            if let Err(e) = self.update_selected_device() {
+                error!("Failed to reopen the microphone: {}", e);
+            }
+        }
+
+        if DSP_FIELDS.iter().any(|field| event.contains(field)) {
+            if let Some(rec) = self.recorder.lock().unwrap().as_ref() {
+                rec.set_dsp_config(dsp_config(&settings));
+            }
+        }
+    }
+
     pub fn stop_recording(&self, binding_id: &str) -> Option<Vec<f32>> {
         let mut state = self.state.lock().unwrap();
 
@@ -379,15 +775,16 @@ impl AudioRecordingManager {
             } if active == binding_id => {
                 *state = RecordingState::Idle;
                 drop(state);
+                self.segment_tx.lock().unwrap().take();
 
                 let samples = if let Some(rec) = self.recorder.lock().unwrap().as_ref() {
+                    // Audio from a microphone that was lost during this recording
+                    let mut samples = std::mem::take(&mut *self.carried_samples.lock().unwrap());
                     match rec.stop() {
-                        Ok(buf) => buf,
-                        Err(e) => {
-                            error!("stop() failed: {e}");
-                            Vec::new()
-                        }
+                        Ok(buf) => samples.extend(buf),
+                        Err(e) => error!("stop() failed: {e}"),
                     }
+                    samples
                 } else {
                     error!("Recorder not available");
                     Vec::new()
@@ -428,9 +825,11 @@ impl AudioRecordingManager {
         if let RecordingState::Recording { .. } = *state {
             *state = RecordingState::Idle;
             drop(state);
+            self.segment_tx.lock().unwrap().take();
 
             if let Some(rec) = self.recorder.lock().unwrap().as_ref() {
                 let _ = rec.stop(); // Discard the result
+                self.carried_samples.lock().unwrap().clear();
             }
 
             *self.is_recording.lock().unwrap() = false;
@@ -442,3 +841,30 @@ impl AudioRecordingManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, condition: MicrophoneCondition) -> MicrophoneRule {
+        MicrophoneRule {
+            pattern: pattern.to_string(),
+            condition,
+        }
+    }
+
+    #[test]
+    fn first_connected_rule_wins() {
+        let headset = rule("Jabra*", MicrophoneCondition::Always);
+        let dock = rule("*dock*", MicrophoneCondition::Always);
+        let names = ["MacBook Pro Microphone", "CalDigit Dock Audio"];
+
+        assert_eq!(pick_microphone(&[&headset, &dock], &names), Some(1));
+        let names = ["Jabra Evolve2 65", "CalDigit Dock Audio"];
+        assert_eq!(pick_microphone(&[&headset, &dock], &names), Some(0));
+        assert_eq!(
+            pick_microphone(&[&headset], &["MacBook Pro Microphone"]),
+            None
+        );
+    }
+}
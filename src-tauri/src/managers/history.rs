@@ -1,15 +1,21 @@
 use anyhow::Result;
-use chrono::{DateTime, Local, Utc};
+use chrono::{DateTime, Duration, Local, Months, Utc};
 use log::{debug, error, info};
 use rusqlite::{params, Connection, OptionalExtension};
 use rusqlite_migration::{Migrations, M};
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use tauri::{AppHandle, Emitter, Manager};
 
-use crate::audio_toolkit::save_wav_file;
+use crate::audio_toolkit::audio::{read_wav_file, save_audio_file, AudioFileFormat};
+use crate::audio_toolkit::constants::WHISPER_SAMPLE_RATE;
+use crate::post_process::PostProcessStageResult;
+use crate::settings::{AppSettings, RecordingFormat, RetentionPolicy};
+use crate::usage_stats::{self, count_words, UsageRange, UsageRow, UsageStats};
 
 /// Database migrations for transcription history.
 /// Each migration is applied in order. The library tracks which migrations
@@ -31,8 +37,47 @@ static MIGRATIONS: &[M] = &[
     ),
     M::up("ALTER TABLE transcription_history ADD COLUMN post_processed_text TEXT;"),
     M::up("ALTER TABLE transcription_history ADD COLUMN post_process_prompt TEXT;"),
+    M::up("ALTER TABLE transcription_history ADD COLUMN post_process_stages TEXT;"),
+    M::up("ALTER TABLE transcription_history ADD COLUMN microphone TEXT;"),
+    M::up("ALTER TABLE transcription_history ADD COLUMN segments TEXT;"),
+    M::up("ALTER TABLE transcription_history ADD COLUMN audio_deleted BOOLEAN NOT NULL DEFAULT 0;"),
+    M::up("ALTER TABLE transcription_history ADD COLUMN corrected_text TEXT;"),
+    M::up("ALTER TABLE transcription_history ADD COLUMN notes TEXT;"),
+    M::up(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE
+        );
+        CREATE TABLE IF NOT EXISTS history_tags (
+            history_id INTEGER NOT NULL REFERENCES transcription_history(id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (history_id, tag_id)
+        );",
+    ),
+    M::up(
+        "ALTER TABLE transcription_history ADD COLUMN duration_ms INTEGER;
+        ALTER TABLE transcription_history ADD COLUMN word_count INTEGER;
+        ALTER TABLE transcription_history ADD COLUMN model TEXT;
+        ALTER TABLE transcription_history ADD COLUMN transcription_ms INTEGER;
+        ALTER TABLE transcription_history ADD COLUMN post_process_ms INTEGER;
+        ALTER TABLE transcription_history ADD COLUMN language TEXT;",
+    ),
 ];
 
+/// Columns selected for every `HistoryEntry` query, in the order expected by `HistoryEntry::from_row`.
+const HISTORY_COLUMNS: &str = "id, file_name, timestamp, saved, title, transcription_text, post_processed_text, post_process_prompt, post_process_stages, microphone, segments, audio_deleted, corrected_text, notes, \
+    duration_ms, word_count, model, transcription_ms, post_process_ms, language, \
+    (SELECT json_group_array(tags.name) FROM history_tags JOIN tags ON tags.id = history_tags.tag_id WHERE history_tags.history_id = transcription_history.id) AS tags";
+
+/// One timestamped piece of a long-form (meeting) transcription.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Type)]
+pub struct TranscriptSegment {
+    /// Offset from the start of the session
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, Type)]
 pub struct HistoryEntry {
     pub id: i64,
@@ -43,12 +88,144 @@ pub struct HistoryEntry {
     pub transcription_text: String,
     pub post_processed_text: Option<String>,
     pub post_process_prompt: Option<String>,
+    /// Output of every post-processing stage, in the order they ran
+    pub post_process_stages: Vec<PostProcessStageResult>,
+    /// Input device the recording was made with
+    pub microphone: Option<String>,
+    /// Pieces of a meeting session, empty for regular recordings
+    pub segments: Vec<TranscriptSegment>,
+    /// The recording was removed by the audio retention policy or quota
+    pub audio_deleted: bool,
+    /// The transcript as fixed by hand
+    pub corrected_text: Option<String>,
+    pub notes: Option<String>,
+    /// Sorted by name
+    pub tags: Vec<String>,
+    /// Length of the recording
+    pub duration_ms: Option<u64>,
+    /// Words in the final text
+    pub word_count: Option<u64>,
+    /// Local model id or `provider/model` for cloud transcription
+    pub model: Option<String>,
+    pub transcription_ms: Option<u64>,
+    /// Time spent in the post-processing pipeline
+    pub post_process_ms: Option<u64>,
+    /// Language setting the recording was transcribed with
+    pub language: Option<String>,
+}
+
+/// How a transcription was made, stored with its history entry.
+#[derive(Clone, Debug, Default)]
+pub struct TranscriptionStats {
+    pub model: Option<String>,
+    pub language: Option<String>,
+    pub transcription_ms: u64,
+    pub post_process_ms: Option<u64>,
+}
+
+impl HistoryEntry {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let post_process_stages = row
+            .get::<_, Option<String>>("post_process_stages")?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let segments = row
+            .get::<_, Option<String>>("segments")?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        let mut tags: Vec<String> = row
+            .get::<_, Option<String>>("tags")?
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        tags.sort_by_key(|tag| tag.to_lowercase());
+
+        Ok(HistoryEntry {
+            id: row.get("id")?,
+            file_name: row.get("file_name")?,
+            timestamp: row.get("timestamp")?,
+            saved: row.get("saved")?,
+            title: row.get("title")?,
+            transcription_text: row.get("transcription_text")?,
+            post_processed_text: row.get("post_processed_text")?,
+            post_process_prompt: row.get("post_process_prompt")?,
+            post_process_stages,
+            microphone: row.get("microphone")?,
+            segments,
+            audio_deleted: row.get("audio_deleted")?,
+            corrected_text: row.get("corrected_text")?,
+            notes: row.get("notes")?,
+            tags,
+            duration_ms: row.get("duration_ms")?,
+            word_count: row.get("word_count")?,
+            model: row.get("model")?,
+            transcription_ms: row.get("transcription_ms")?,
+            post_process_ms: row.get("post_process_ms")?,
+            language: row.get("language")?,
+        })
+    }
+}
+
+/// A tag and how many entries carry it, as listed by `get_history_tags`.
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+pub struct HistoryTag {
+    pub name: String,
+    pub entries: u64,
+}
+
+/// Disk usage of the history, as reported by `get_history_storage_stats`.
+#[derive(Clone, Debug, Serialize, Type)]
+pub struct HistoryStorageStats {
+    pub entries: u64,
+    pub saved_entries: u64,
+    /// Entries whose recording is still on disk
+    pub recordings: u64,
+    pub recordings_bytes: u64,
+    pub database_bytes: u64,
+}
+
+/// A recording on disk, for enforcing the size quota.
+struct RecordingFile {
+    id: i64,
+    file_name: String,
+    saved: bool,
+    bytes: u64,
+}
+
+/// The oldest unsaved recordings that have to go for all of `recordings`
+/// (oldest first) to fit in `quota_bytes`.
+fn recordings_over_quota(recordings: &[RecordingFile], quota_bytes: u64) -> Vec<(i64, String)> {
+    let mut total: u64 = recordings.iter().map(|r| r.bytes).sum();
+    let mut over_quota = Vec::new();
+
+    for recording in recordings.iter().filter(|r| !r.saved) {
+        if total <= quota_bytes {
+            break;
+        }
+        total -= recording.bytes;
+        over_quota.push((recording.id, recording.file_name.clone()));
+    }
+
+    over_quota
+}
+
+/// The file format new recordings are saved in
+pub fn recording_format(settings: &AppSettings) -> AudioFileFormat {
+    match settings.recording_format {
+        RecordingFormat::Wav => AudioFileFormat::Wav,
+        RecordingFormat::Flac => AudioFileFormat::Flac,
+        RecordingFormat::Opus => AudioFileFormat::Opus {
+            bitrate_kbps: settings.recording_opus_bitrate_kbps,
+        },
+    }
 }
 
 pub struct HistoryManager {
     app_handle: AppHandle,
     recordings_dir: PathBuf,
     db_path: PathBuf,
+    /// Entries whose recording is still being written or converted by its
+    /// writer, which bulk conversion leaves alone
+    recordings_in_use: Mutex<HashSet<i64>>,
 }
 
 impl HistoryManager {
@@ -68,6 +245,7 @@ impl HistoryManager {
             app_handle: app_handle.clone(),
             recordings_dir,
             db_path,
+            recordings_in_use: Mutex::new(HashSet::new()),
         };
 
         // Initialize database and run migrations synchronously
@@ -173,34 +351,55 @@ impl HistoryManager {
     }
 
     fn get_connection(&self) -> Result<Connection> {
-        Ok(Connection::open(&self.db_path)?)
+        let conn = Connection::open(&self.db_path)?;
+        // Tags of deleted entries go with them
+        conn.pragma_update(None, "foreign_keys", true)?;
+        Ok(conn)
     }
 
-    /// Save a transcription to history (both database and WAV file)
+    /// Save a transcription to history (both database and audio file)
+    /// `settings` are the ones the transcription ran with, per-app rules
+    /// applied, so an app that disables post-processing gets no LLM title.
     pub async fn save_transcription(
         &self,
+        settings: &AppSettings,
         audio_samples: Vec<f32>,
         transcription_text: String,
         post_processed_text: Option<String>,
         post_process_prompt: Option<String>,
+        post_process_stages: Vec<PostProcessStageResult>,
+        microphone: Option<String>,
+        stats: TranscriptionStats,
     ) -> Result<()> {
         let timestamp = Utc::now().timestamp();
-        let file_name = format!("handy-{}.wav", timestamp);
+        let duration_ms = audio_samples.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+        let format = recording_format(settings);
+        let file_name = format!("handy-{}.{}", timestamp, format.extension());
         let title = self.format_timestamp_title(timestamp);
 
-        // Save WAV file
+        // Save audio file; FLAC and Opus encoding takes a while for long recordings
         let file_path = self.recordings_dir.join(&file_name);
-        save_wav_file(file_path, &audio_samples).await?;
+        tauri::async_runtime::spawn_blocking(move || {
+            save_audio_file(file_path, &audio_samples, format)
+        })
+        .await??;
+
+        let final_text = post_processed_text
+            .clone()
+            .unwrap_or_else(|| transcription_text.clone());
 
         // Save to database
-        self.save_to_database(
+        let id = self.save_to_database(
             file_name,
             timestamp,
             title,
             transcription_text,
             post_processed_text,
             post_process_prompt,
+            post_process_stages,
+            microphone,
         )?;
+        self.save_stats(id, duration_ms, count_words(&final_text), stats)?;
 
         // Clean up old entries
         self.cleanup_old_entries()?;
@@ -210,6 +409,18 @@ impl HistoryManager {
             error!("Failed to emit history-updated event: {}", e);
         }
 
+        if settings.history_auto_title {
+            match crate::post_process::generate_title(settings, &final_text).await {
+                // The entry is saved already, a missing title doesn't undo that
+                Ok(title) => {
+                    if let Err(e) = self.update_entry_title(id, Some(title)) {
+                        error!("Failed to set title of entry {}: {}", id, e);
+                    }
+                }
+                Err(e) => debug!("No automatic title for entry {}: {}", id, e),
+            }
+        }
+
         Ok(())
     }
 
@@ -221,155 +432,283 @@ impl HistoryManager {
         transcription_text: String,
         post_processed_text: Option<String>,
         post_process_prompt: Option<String>,
-    ) -> Result<()> {
+        post_process_stages: Vec<PostProcessStageResult>,
+        microphone: Option<String>,
+    ) -> Result<i64> {
+        let stages_json = if post_process_stages.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&post_process_stages)?)
+        };
+
         let conn = self.get_connection()?;
         conn.execute(
-            "INSERT INTO transcription_history (file_name, timestamp, saved, title, transcription_text, post_processed_text, post_process_prompt) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![file_name, timestamp, false, title, transcription_text, post_processed_text, post_process_prompt],
+            "INSERT INTO transcription_history (file_name, timestamp, saved, title, transcription_text, post_processed_text, post_process_prompt, post_process_stages, microphone) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![file_name, timestamp, false, title, transcription_text, post_processed_text, post_process_prompt, stages_json, microphone],
         )?;
 
         debug!("Saved transcription to database");
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn save_stats(
+        &self,
+        id: i64,
+        duration_ms: u64,
+        word_count: u64,
+        stats: TranscriptionStats,
+    ) -> Result<()> {
+        let conn = self.get_connection()?;
+        conn.execute(
+            "UPDATE transcription_history SET duration_ms = ?1, word_count = ?2, model = ?3, transcription_ms = ?4, post_process_ms = ?5, language = ?6 WHERE id = ?7",
+            params![duration_ms as i64, word_count as i64, stats.model, stats.transcription_ms as i64, stats.post_process_ms.map(|ms| ms as i64), stats.language, id],
+        )?;
         Ok(())
     }
 
-    pub fn cleanup_old_entries(&self) -> Result<()> {
-        let retention_period = crate::settings::get_recording_retention_period(&self.app_handle);
+    /// Creates the history entry for a meeting session, which segments are
+    /// appended to as they are transcribed. Returns its id.
+    pub fn create_session_entry(
+        &self,
+        file_name: String,
+        timestamp: i64,
+        microphone: Option<String>,
+    ) -> Result<i64> {
+        let title = self.format_timestamp_title(timestamp);
 
-        match retention_period {
-            crate::settings::RecordingRetentionPeriod::Never => {
-                // Don't delete anything
-                return Ok(());
-            }
-            crate::settings::RecordingRetentionPeriod::PreserveLimit => {
-                // Use the old count-based logic with history_limit
-                let limit = crate::settings::get_history_limit(&self.app_handle);
-                return self.cleanup_by_count(limit);
-            }
-            _ => {
-                // Use time-based logic
-                return self.cleanup_by_time(retention_period);
-            }
+        let conn = self.get_connection()?;
+        conn.execute(
+            "INSERT INTO transcription_history (file_name, timestamp, saved, title, transcription_text, microphone, segments) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![file_name, timestamp, false, title, "", microphone, "[]"],
+        )?;
+        let id = conn.last_insert_rowid();
+
+        self.cleanup_old_entries()?;
+        if let Err(e) = self.app_handle.emit("history-updated", ()) {
+            error!("Failed to emit history-updated event: {}", e);
         }
+
+        Ok(id)
     }
 
-    fn delete_entries_and_files(&self, entries: &[(i64, String)]) -> Result<usize> {
-        if entries.is_empty() {
-            return Ok(0);
+    /// Adds a transcribed segment to a meeting session entry.
+    pub fn append_segment(&self, id: i64, segment: TranscriptSegment) -> Result<()> {
+        let conn = self.get_connection()?;
+        Self::append_segment_with_conn(&conn, id, segment)?;
+
+        if let Err(e) = self.app_handle.emit("history-updated", ()) {
+            error!("Failed to emit history-updated event: {}", e);
         }
+        Ok(())
+    }
 
+    fn append_segment_with_conn(
+        conn: &Connection,
+        id: i64,
+        segment: TranscriptSegment,
+    ) -> Result<()> {
+        let (text, segments_json): (String, Option<String>) = conn.query_row(
+            "SELECT transcription_text, segments FROM transcription_history WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut segments: Vec<TranscriptSegment> = segments_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        // The full text stays searchable and pasteable like any other entry
+        let text = if text.is_empty() {
+            segment.text.clone()
+        } else {
+            format!("{}\n{}", text, segment.text)
+        };
+        segments.push(segment);
+
+        // The session lasts until the end of its latest segment
+        let duration_ms = segments.last().map_or(0, |s| s.end_ms);
+
+        conn.execute(
+            "UPDATE transcription_history SET transcription_text = ?1, segments = ?2, duration_ms = ?3, word_count = ?4 WHERE id = ?5",
+            params![text, serde_json::to_string(&segments)?, duration_ms as i64, count_words(&text) as i64, id],
+        )?;
+        Ok(())
+    }
+
+    pub fn cleanup_old_entries(&self) -> Result<()> {
+        let settings = crate::settings::get_settings(&self.app_handle);
         let conn = self.get_connection()?;
-        let mut deleted_count = 0;
+        let now = Utc::now();
 
-        for (id, file_name) in entries {
-            // Delete database entry
-            conn.execute(
-                "DELETE FROM transcription_history WHERE id = ?1",
-                params![id],
-            )?;
+        // Entries first, their recordings go with them
+        let expired = Self::expired_entries_with_conn(&conn, settings.text_retention, now, false)?;
+        let deleted_count = self.delete_entries_and_files(&expired)?;
+        if deleted_count > 0 {
+            debug!("Cleaned up {} old history entries", deleted_count);
+        }
+
+        let expired = Self::expired_entries_with_conn(&conn, settings.audio_retention, now, true)?;
+        let mut removed_count = self.delete_recordings(&conn, &expired)?;
+
+        if let Some(quota_mb) = settings.recordings_quota_mb {
+            let recordings = self.recording_files_with_conn(&conn)?;
+            let over_quota = recordings_over_quota(&recordings, quota_mb * 1024 * 1024);
+            removed_count += self.delete_recordings(&conn, &over_quota)?;
+        }
+        if removed_count > 0 {
+            debug!("Removed {} old recordings", removed_count);
+        }
+
+        Ok(())
+    }
 
-            // Delete WAV file
+    /// Unsaved entries `policy` no longer keeps. With `audio_only`, only
+    /// entries whose recording is still there are considered.
+    fn expired_entries_with_conn(
+        conn: &Connection,
+        policy: RetentionPolicy,
+        now: DateTime<Utc>,
+        audio_only: bool,
+    ) -> Result<Vec<(i64, String)>> {
+        let filter = if audio_only {
+            "saved = 0 AND audio_deleted = 0"
+        } else {
+            "saved = 0"
+        };
+        let cutoff = match policy {
+            RetentionPolicy::Forever => return Ok(Vec::new()),
+            RetentionPolicy::Latest(limit) => {
+                let mut stmt = conn.prepare(&format!(
+                    "SELECT id, file_name FROM transcription_history WHERE {} ORDER BY timestamp DESC LIMIT -1 OFFSET ?1",
+                    filter
+                ))?;
+                let rows = stmt.query_map(params![limit as i64], |row| {
+                    Ok((row.get::<_, i64>("id")?, row.get::<_, String>("file_name")?))
+                })?;
+                return Ok(rows.collect::<rusqlite::Result<_>>()?);
+            }
+            RetentionPolicy::Days(days) => now - Duration::days(days as i64),
+            RetentionPolicy::Months(months) => now
+                .checked_sub_months(Months::new(months))
+                .unwrap_or(DateTime::<Utc>::MIN_UTC),
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, file_name FROM transcription_history WHERE {} AND timestamp < ?1",
+            filter
+        ))?;
+        let rows = stmt.query_map(params![cutoff.timestamp()], |row| {
+            Ok((row.get::<_, i64>("id")?, row.get::<_, String>("file_name")?))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Deletes the audio files of `entries` but keeps the entries.
+    fn delete_recordings(&self, conn: &Connection, entries: &[(i64, String)]) -> Result<usize> {
+        let mut removed_count = 0;
+
+        for (id, file_name) in entries {
             let file_path = self.recordings_dir.join(file_name);
             if file_path.exists() {
                 if let Err(e) = fs::remove_file(&file_path) {
-                    error!("Failed to delete WAV file {}: {}", file_name, e);
-                } else {
-                    debug!("Deleted old WAV file: {}", file_name);
-                    deleted_count += 1;
+                    error!("Failed to delete audio file {}: {}", file_name, e);
+                    continue;
                 }
+                removed_count += 1;
             }
+            conn.execute(
+                "UPDATE transcription_history SET audio_deleted = 1 WHERE id = ?1",
+                params![id],
+            )?;
         }
 
-        Ok(deleted_count)
+        Ok(removed_count)
     }
 
-    fn cleanup_by_count(&self, limit: usize) -> Result<()> {
-        let conn = self.get_connection()?;
-
-        // Get all entries that are not saved, ordered by timestamp desc
+    /// Every recording still on disk, oldest first.
+    fn recording_files_with_conn(&self, conn: &Connection) -> Result<Vec<RecordingFile>> {
         let mut stmt = conn.prepare(
-            "SELECT id, file_name FROM transcription_history WHERE saved = 0 ORDER BY timestamp DESC"
+            "SELECT id, file_name, saved FROM transcription_history WHERE audio_deleted = 0 ORDER BY timestamp ASC",
         )?;
-
         let rows = stmt.query_map([], |row| {
-            Ok((row.get::<_, i64>("id")?, row.get::<_, String>("file_name")?))
+            Ok((
+                row.get::<_, i64>("id")?,
+                row.get::<_, String>("file_name")?,
+                row.get::<_, bool>("saved")?,
+            ))
         })?;
 
-        let mut entries: Vec<(i64, String)> = Vec::new();
+        let mut files = Vec::new();
         for row in rows {
-            entries.push(row?);
+            let (id, file_name, saved) = row?;
+            let Ok(metadata) = fs::metadata(self.recordings_dir.join(&file_name)) else {
+                continue;
+            };
+            files.push(RecordingFile {
+                id,
+                file_name,
+                saved,
+                bytes: metadata.len(),
+            });
         }
-
-        if entries.len() > limit {
-            let entries_to_delete = &entries[limit..];
-            let deleted_count = self.delete_entries_and_files(entries_to_delete)?;
-
-            if deleted_count > 0 {
-                debug!("Cleaned up {} old history entries by count", deleted_count);
-            }
-        }
-
-        Ok(())
+        Ok(files)
     }
 
-    fn cleanup_by_time(
-        &self,
-        retention_period: crate::settings::RecordingRetentionPeriod,
-    ) -> Result<()> {
+    pub fn get_storage_stats(&self) -> Result<HistoryStorageStats> {
         let conn = self.get_connection()?;
-
-        // Calculate cutoff timestamp (current time minus retention period)
-        let now = Utc::now().timestamp();
-        let cutoff_timestamp = match retention_period {
-            crate::settings::RecordingRetentionPeriod::Days3 => now - (3 * 24 * 60 * 60), // 3 days in seconds
-            crate::settings::RecordingRetentionPeriod::Weeks2 => now - (2 * 7 * 24 * 60 * 60), // 2 weeks in seconds
-            crate::settings::RecordingRetentionPeriod::Months3 => now - (3 * 30 * 24 * 60 * 60), // 3 months in seconds (approximate)
-            _ => unreachable!("Should not reach here"),
-        };
-
-        // Get all unsaved entries older than the cutoff timestamp
-        let mut stmt = conn.prepare(
-            "SELECT id, file_name FROM transcription_history WHERE saved = 0 AND timestamp < ?1",
+        let (entries, saved_entries): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(saved), 0) FROM transcription_history",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
         )?;
+        let recordings = self.recording_files_with_conn(&conn)?;
+
+        Ok(HistoryStorageStats {
+            entries: entries as u64,
+            saved_entries: saved_entries as u64,
+            recordings: recordings.len() as u64,
+            recordings_bytes: recordings.iter().map(|r| r.bytes).sum(),
+            database_bytes: fs::metadata(&self.db_path).map_or(0, |m| m.len()),
+        })
+    }
 
-        let rows = stmt.query_map(params![cutoff_timestamp], |row| {
-            Ok((row.get::<_, i64>("id")?, row.get::<_, String>("file_name")?))
-        })?;
-
-        let mut entries_to_delete: Vec<(i64, String)> = Vec::new();
-        for row in rows {
-            entries_to_delete.push(row?);
+    fn delete_entries_and_files(&self, entries: &[(i64, String)]) -> Result<usize> {
+        if entries.is_empty() {
+            return Ok(0);
         }
 
-        let deleted_count = self.delete_entries_and_files(&entries_to_delete)?;
+        let conn = self.get_connection()?;
+        let mut deleted_count = 0;
 
-        if deleted_count > 0 {
-            debug!(
-                "Cleaned up {} old history entries based on retention period",
-                deleted_count
-            );
+        for (id, file_name) in entries {
+            // Delete database entry
+            conn.execute(
+                "DELETE FROM transcription_history WHERE id = ?1",
+                params![id],
+            )?;
+            deleted_count += 1;
+
+            // Delete audio file, unless the audio retention already did
+            let file_path = self.recordings_dir.join(file_name);
+            if file_path.exists() {
+                if let Err(e) = fs::remove_file(&file_path) {
+                    error!("Failed to delete audio file {}: {}", file_name, e);
+                } else {
+                    debug!("Deleted old audio file: {}", file_name);
+                }
+            }
         }
 
-        Ok(())
+        Ok(deleted_count)
     }
 
     pub async fn get_history_entries(&self) -> Result<Vec<HistoryEntry>> {
         let conn = self.get_connection()?;
-        let mut stmt = conn.prepare(
-            "SELECT id, file_name, timestamp, saved, title, transcription_text, post_processed_text, post_process_prompt FROM transcription_history ORDER BY timestamp DESC"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM transcription_history ORDER BY timestamp DESC",
+            HISTORY_COLUMNS
+        ))?;
 
-        let rows = stmt.query_map([], |row| {
-            Ok(HistoryEntry {
-                id: row.get("id")?,
-                file_name: row.get("file_name")?,
-                timestamp: row.get("timestamp")?,
-                saved: row.get("saved")?,
-                title: row.get("title")?,
-                transcription_text: row.get("transcription_text")?,
-                post_processed_text: row.get("post_processed_text")?,
-                post_process_prompt: row.get("post_process_prompt")?,
-            })
-        })?;
+        let rows = stmt.query_map([], HistoryEntry::from_row)?;
 
         let mut entries = Vec::new();
         for row in rows {
@@ -385,27 +724,15 @@ impl HistoryManager {
     }
 
     fn get_latest_entry_with_conn(conn: &Connection) -> Result<Option<HistoryEntry>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, file_name, timestamp, saved, title, transcription_text, post_processed_text, post_process_prompt
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}
              FROM transcription_history
              ORDER BY timestamp DESC
              LIMIT 1",
-        )?;
+            HISTORY_COLUMNS
+        ))?;
 
-        let entry = stmt
-            .query_row([], |row| {
-                Ok(HistoryEntry {
-                    id: row.get("id")?,
-                    file_name: row.get("file_name")?,
-                    timestamp: row.get("timestamp")?,
-                    saved: row.get("saved")?,
-                    title: row.get("title")?,
-                    transcription_text: row.get("transcription_text")?,
-                    post_processed_text: row.get("post_processed_text")?,
-                    post_process_prompt: row.get("post_process_prompt")?,
-                })
-            })
-            .optional()?;
+        let entry = stmt.query_row([], HistoryEntry::from_row).optional()?;
 
         Ok(entry)
     }
@@ -437,31 +764,335 @@ impl HistoryManager {
         Ok(())
     }
 
-    pub fn get_audio_file_path(&self, file_name: &str) -> PathBuf {
-        self.recordings_dir.join(file_name)
+    /// Renames entry `id`; `None` goes back to the timestamp title.
+    pub fn update_entry_title(&self, id: i64, title: Option<String>) -> Result<()> {
+        let conn = self.get_connection()?;
+        let title = match title {
+            Some(title) => title,
+            None => {
+                let timestamp: i64 = conn
+                    .query_row(
+                        "SELECT timestamp FROM transcription_history WHERE id = ?1",
+                        params![id],
+                        |row| row.get(0),
+                    )
+                    .optional()?
+                    .ok_or_else(|| anyhow::anyhow!("History entry {} not found", id))?;
+                self.format_timestamp_title(timestamp)
+            }
+        };
+
+        let updated = conn.execute(
+            "UPDATE transcription_history SET title = ?1 WHERE id = ?2",
+            params![title, id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow::anyhow!("History entry {} not found", id));
+        }
+
+        debug!("Renamed entry {} to '{}'", id, title);
+        if let Err(e) = self.app_handle.emit("history-updated", ()) {
+            error!("Failed to emit history-updated event: {}", e);
+        }
+
+        Ok(())
     }
 
-    pub async fn get_entry_by_id(&self, id: i64) -> Result<Option<HistoryEntry>> {
+    pub fn update_entry_notes(&self, id: i64, notes: Option<String>) -> Result<()> {
+        let conn = self.get_connection()?;
+        let updated = conn.execute(
+            "UPDATE transcription_history SET notes = ?1 WHERE id = ?2",
+            params![notes, id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow::anyhow!("History entry {} not found", id));
+        }
+
+        if let Err(e) = self.app_handle.emit("history-updated", ()) {
+            error!("Failed to emit history-updated event: {}", e);
+        }
+
+        Ok(())
+    }
+
+    pub fn add_tag(&self, id: i64, tag: &str) -> Result<()> {
+        let conn = self.get_connection()?;
+        Self::add_tag_with_conn(&conn, id, tag)?;
+
+        if let Err(e) = self.app_handle.emit("history-updated", ()) {
+            error!("Failed to emit history-updated event: {}", e);
+        }
+
+        Ok(())
+    }
+
+    fn add_tag_with_conn(conn: &Connection, id: i64, tag: &str) -> Result<()> {
+        let tag = tag.trim();
+        if tag.is_empty() {
+            return Err(anyhow::anyhow!("Tag name is empty"));
+        }
+
+        conn.execute(
+            "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
+            params![tag],
+        )?;
+        conn.execute(
+            "INSERT OR IGNORE INTO history_tags (history_id, tag_id)
+             SELECT ?1, id FROM tags WHERE name = ?2",
+            params![id, tag],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_tag(&self, id: i64, tag: &str) -> Result<()> {
+        let conn = self.get_connection()?;
+        Self::remove_tag_with_conn(&conn, id, tag)?;
+
+        if let Err(e) = self.app_handle.emit("history-updated", ()) {
+            error!("Failed to emit history-updated event: {}", e);
+        }
+
+        Ok(())
+    }
+
+    fn remove_tag_with_conn(conn: &Connection, id: i64, tag: &str) -> Result<()> {
+        conn.execute(
+            "DELETE FROM history_tags
+             WHERE history_id = ?1 AND tag_id = (SELECT id FROM tags WHERE name = ?2)",
+            params![id, tag.trim()],
+        )?;
+        // Drop tags nothing carries anymore
+        conn.execute(
+            "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM history_tags)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_tags(&self) -> Result<Vec<HistoryTag>> {
         let conn = self.get_connection()?;
+        Self::get_tags_with_conn(&conn)
+    }
+
+    fn get_tags_with_conn(conn: &Connection) -> Result<Vec<HistoryTag>> {
         let mut stmt = conn.prepare(
-            "SELECT id, file_name, timestamp, saved, title, transcription_text, post_processed_text, post_process_prompt
-             FROM transcription_history WHERE id = ?1",
+            "SELECT tags.name, COUNT(*) AS entries FROM tags
+             JOIN history_tags ON history_tags.tag_id = tags.id
+             GROUP BY tags.id ORDER BY tags.name COLLATE NOCASE",
         )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(HistoryTag {
+                name: row.get("name")?,
+                entries: row.get::<_, i64>("entries")? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn get_entries_by_tag(&self, tag: &str) -> Result<Vec<HistoryEntry>> {
+        let conn = self.get_connection()?;
+        Self::get_entries_by_tag_with_conn(&conn, tag)
+    }
 
-        let entry = stmt
-            .query_row([id], |row| {
-                Ok(HistoryEntry {
-                    id: row.get("id")?,
-                    file_name: row.get("file_name")?,
-                    timestamp: row.get("timestamp")?,
-                    saved: row.get("saved")?,
-                    title: row.get("title")?,
-                    transcription_text: row.get("transcription_text")?,
-                    post_processed_text: row.get("post_processed_text")?,
-                    post_process_prompt: row.get("post_process_prompt")?,
-                })
+    fn get_entries_by_tag_with_conn(conn: &Connection, tag: &str) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM transcription_history
+             WHERE id IN (
+                 SELECT history_tags.history_id FROM history_tags
+                 JOIN tags ON tags.id = history_tags.tag_id
+                 WHERE tags.name = ?1
+             )
+             ORDER BY timestamp DESC",
+            HISTORY_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![tag.trim()], HistoryEntry::from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Stores a hand-corrected transcript for entry `id`; `None` removes it.
+    pub fn update_entry_text(&self, id: i64, corrected_text: Option<String>) -> Result<()> {
+        let conn = self.get_connection()?;
+        let updated = conn.execute(
+            "UPDATE transcription_history SET corrected_text = ?1 WHERE id = ?2",
+            params![corrected_text, id],
+        )?;
+        if updated == 0 {
+            return Err(anyhow::anyhow!("History entry {} not found", id));
+        }
+
+        debug!("Updated corrected text of entry {}", id);
+        if let Err(e) = self.app_handle.emit("history-updated", ()) {
+            error!("Failed to emit history-updated event: {}", e);
+        }
+
+        Ok(())
+    }
+
+    /// Raw and corrected transcript of every entry that was corrected by hand.
+    pub fn get_corrections(&self) -> Result<Vec<(String, String)>> {
+        let conn = self.get_connection()?;
+        Self::get_corrections_with_conn(&conn, None)
+    }
+
+    /// Like `get_corrections`, limited to entries from `since` on.
+    fn get_corrections_with_conn(
+        conn: &Connection,
+        since: Option<i64>,
+    ) -> Result<Vec<(String, String)>> {
+        let mut stmt = conn.prepare(
+            "SELECT transcription_text, corrected_text FROM transcription_history WHERE corrected_text IS NOT NULL AND timestamp >= ?1",
+        )?;
+        let rows = stmt.query_map(params![since.unwrap_or(i64::MIN)], |row| {
+            Ok((
+                row.get::<_, String>("transcription_text")?,
+                row.get::<_, String>("corrected_text")?,
+            ))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn get_usage_stats(&self, range: UsageRange) -> Result<UsageStats> {
+        let conn = self.get_connection()?;
+        let since = range.since(Local::now());
+        let rows = Self::get_usage_rows_with_conn(&conn, since)?;
+        let corrections = Self::get_corrections_with_conn(&conn, since)?;
+        Ok(usage_stats::summarize(&rows, &corrections))
+    }
+
+    fn get_usage_rows_with_conn(conn: &Connection, since: Option<i64>) -> Result<Vec<UsageRow>> {
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, COALESCE(corrected_text, post_processed_text, transcription_text) AS text,
+                    word_count, duration_ms, model, transcription_ms, post_process_ms
+             FROM transcription_history WHERE timestamp >= ?1",
+        )?;
+        let rows = stmt.query_map(params![since.unwrap_or(i64::MIN)], |row| {
+            Ok(UsageRow {
+                timestamp: row.get("timestamp")?,
+                text: row.get("text")?,
+                word_count: row.get("word_count")?,
+                duration_ms: row.get("duration_ms")?,
+                model: row.get("model")?,
+                transcription_ms: row.get("transcription_ms")?,
+                post_process_ms: row.get("post_process_ms")?,
             })
-            .optional()?;
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn get_audio_file_path(&self, file_name: &str) -> PathBuf {
+        self.recordings_dir.join(file_name)
+    }
+
+    /// Keeps bulk conversion away from the recording of entry `id` until
+    /// `release_recording`, while it is still being written.
+    pub fn hold_recording(&self, id: i64) {
+        self.recordings_in_use.lock().unwrap().insert(id);
+    }
+
+    pub fn release_recording(&self, id: i64) {
+        self.recordings_in_use.lock().unwrap().remove(&id);
+    }
+
+    /// Re-encodes the WAV recording of entry `id` in `format`, points the
+    /// entry at the new file and removes the WAV. Returns the new file name.
+    pub fn convert_recording(
+        &self,
+        id: i64,
+        file_name: &str,
+        format: AudioFileFormat,
+    ) -> Result<String> {
+        let new_name = Path::new(file_name)
+            .with_extension(format.extension())
+            .to_string_lossy()
+            .to_string();
+        if new_name == file_name {
+            return Ok(new_name);
+        }
+
+        let source = self.recordings_dir.join(file_name);
+        let target = self.recordings_dir.join(&new_name);
+        let samples = read_wav_file(&source)?;
+        save_audio_file(&target, &samples, format)?;
+
+        let conn = self.get_connection()?;
+        let updated = conn.execute(
+            "UPDATE transcription_history SET file_name = ?1 WHERE id = ?2",
+            params![new_name, id],
+        )?;
+        if updated == 0 {
+            // The entry was deleted while we were encoding
+            let _ = fs::remove_file(&target);
+            return Ok(new_name);
+        }
+
+        if let Err(e) = fs::remove_file(&source) {
+            error!("Failed to delete converted WAV file {}: {}", file_name, e);
+        }
+        debug!("Converted {} to {}", file_name, new_name);
+        Ok(new_name)
+    }
+
+    /// Converts every WAV recording in history to `format`, except ones held
+    /// by `hold_recording`. `on_progress` gets the number of recordings handled
+    /// so far and the total. Returns how many were converted.
+    pub fn convert_recordings(
+        &self,
+        format: AudioFileFormat,
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<usize> {
+        if format == AudioFileFormat::Wav {
+            return Ok(0);
+        }
+
+        let entries: Vec<(i64, String)> = {
+            let conn = self.get_connection()?;
+            let mut stmt = conn.prepare(
+                "SELECT id, file_name FROM transcription_history WHERE file_name LIKE '%.wav'",
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get::<_, i64>("id")?, row.get::<_, String>("file_name")?))
+            })?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let mut converted = 0;
+        for (index, (id, file_name)) in entries.iter().enumerate() {
+            // Checked per entry and held while converting, so a meeting can't
+            // start or finish writing this recording meanwhile
+            let in_use = self.recordings_in_use.lock().unwrap();
+            if !in_use.contains(id) && self.recordings_dir.join(file_name).exists() {
+                match self.convert_recording(*id, file_name, format) {
+                    Ok(_) => converted += 1,
+                    Err(e) => error!("Failed to convert recording {}: {}", file_name, e),
+                }
+            }
+            drop(in_use);
+            on_progress(index + 1, entries.len());
+        }
+
+        info!(
+            "Converted {} of {} WAV recordings to {}",
+            converted,
+            entries.len(),
+            format.extension()
+        );
+        if converted > 0 {
+            if let Err(e) = self.app_handle.emit("history-updated", ()) {
+                error!("Failed to emit history-updated event: {}", e);
+            }
+        }
+
+        Ok(converted)
+    }
+
+    pub async fn get_entry_by_id(&self, id: i64) -> Result<Option<HistoryEntry>> {
+        let conn = self.get_connection()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM transcription_history WHERE id = ?1",
+            HISTORY_COLUMNS
+        ))?;
+
+        let entry = stmt.query_row([id], HistoryEntry::from_row).optional()?;
 
         Ok(entry)
     }
@@ -497,7 +1128,7 @@ impl HistoryManager {
         Ok(())
     }
 
-    fn format_timestamp_title(&self, timestamp: i64) -> String {
+    pub fn format_timestamp_title(&self, timestamp: i64) -> String {
         if let Some(utc_datetime) = DateTime::from_timestamp(timestamp, 0) {
             // Convert UTC to local timezone
             let local_datetime = utc_datetime.with_timezone(&Local);
@@ -514,20 +1145,10 @@ mod tests {
     use rusqlite::{params, Connection};
 
     fn setup_conn() -> Connection {
-        let conn = Connection::open_in_memory().expect("open in-memory db");
-        conn.execute_batch(
-            "CREATE TABLE transcription_history (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                file_name TEXT NOT NULL,
-                timestamp INTEGER NOT NULL,
-                saved BOOLEAN NOT NULL DEFAULT 0,
-                title TEXT NOT NULL,
-                transcription_text TEXT NOT NULL,
-                post_processed_text TEXT,
-                post_process_prompt TEXT
-            );",
-        )
-        .expect("create transcription_history table");
+        let mut conn = Connection::open_in_memory().expect("open in-memory db");
+        Migrations::new(MIGRATIONS.to_vec())
+            .to_latest(&mut conn)
+            .expect("apply history migrations");
         conn
     }
 
@@ -569,4 +1190,211 @@ mod tests {
         assert_eq!(entry.transcription_text, "second");
         assert_eq!(entry.post_processed_text.as_deref(), Some("processed"));
     }
+
+    #[test]
+    fn post_process_stages_round_trip_through_json_column() {
+        let conn = setup_conn();
+        insert_entry(&conn, 100, "teh cat", Some("the cat"));
+        let stages = vec![PostProcessStageResult {
+            stage_id: "regex".to_string(),
+            name: "Fix typos".to_string(),
+            applied: true,
+            output: "the cat".to_string(),
+            error: None,
+        }];
+        conn.execute(
+            "UPDATE transcription_history SET post_process_stages = ?1",
+            params![serde_json::to_string(&stages).unwrap()],
+        )
+        .expect("store stages");
+
+        let entry = HistoryManager::get_latest_entry_with_conn(&conn)
+            .expect("fetch latest entry")
+            .expect("entry exists");
+
+        assert_eq!(entry.post_process_stages.len(), 1);
+        assert_eq!(entry.post_process_stages[0].stage_id, "regex");
+        assert_eq!(entry.post_process_stages[0].output, "the cat");
+    }
+
+    #[test]
+    fn segments_are_appended_to_session_entry() {
+        let conn = setup_conn();
+        insert_entry(&conn, 100, "", None);
+        let id = conn.last_insert_rowid();
+
+        for (start_ms, text) in [(0, "Welcome everyone."), (4200, "Let's start.")] {
+            let segment = TranscriptSegment {
+                start_ms,
+                end_ms: start_ms + 3000,
+                text: text.to_string(),
+            };
+            HistoryManager::append_segment_with_conn(&conn, id, segment).expect("append");
+        }
+
+        let entry = HistoryManager::get_latest_entry_with_conn(&conn)
+            .expect("fetch latest entry")
+            .expect("entry exists");
+        assert_eq!(entry.transcription_text, "Welcome everyone.\nLet's start.");
+        assert_eq!(entry.segments.len(), 2);
+        assert_eq!(entry.segments[1].start_ms, 4200);
+    }
+
+    #[test]
+    fn retention_policies_select_old_unsaved_entries() {
+        let conn = setup_conn();
+        let now = DateTime::from_timestamp(100 * 86_400, 0).unwrap();
+        for day in [10, 80, 95, 99] {
+            insert_entry(&conn, day * 86_400, "text", None);
+        }
+        conn.execute(
+            "UPDATE transcription_history SET saved = 1 WHERE timestamp = ?1",
+            params![10 * 86_400],
+        )
+        .unwrap();
+        conn.execute(
+            "UPDATE transcription_history SET audio_deleted = 1 WHERE timestamp = ?1",
+            params![80 * 86_400],
+        )
+        .unwrap();
+
+        let names = |policy, audio_only| -> Vec<String> {
+            HistoryManager::expired_entries_with_conn(&conn, policy, now, audio_only)
+                .unwrap()
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect()
+        };
+        let day = |d: i64| format!("handy-{}.wav", d * 86_400);
+
+        assert!(names(RetentionPolicy::Forever, false).is_empty());
+        assert_eq!(names(RetentionPolicy::Days(14), false), vec![day(80)]);
+        assert_eq!(
+            names(RetentionPolicy::Latest(1), false),
+            vec![day(95), day(80)]
+        );
+        // The recording of day 80 is already gone
+        assert_eq!(names(RetentionPolicy::Latest(1), true), vec![day(95)]);
+    }
+
+    #[test]
+    fn quota_removes_oldest_unsaved_recordings() {
+        let recording = |id, saved, bytes| RecordingFile {
+            id,
+            file_name: format!("handy-{}.wav", id),
+            saved,
+            bytes,
+        };
+        let recordings = vec![
+            recording(1, true, 400),
+            recording(2, false, 300),
+            recording(3, false, 200),
+            recording(4, false, 100),
+        ];
+
+        let ids = |quota| -> Vec<i64> {
+            recordings_over_quota(&recordings, quota)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect()
+        };
+        assert!(ids(1000).is_empty());
+        assert_eq!(ids(700), vec![2]);
+        // Saved recordings stay even if the quota can't be met
+        assert_eq!(ids(100), vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn corrections_are_read_back_with_the_raw_text() {
+        let conn = setup_conn();
+        insert_entry(&conn, 100, "call get hub", None);
+        insert_entry(&conn, 200, "untouched", None);
+        conn.execute(
+            "UPDATE transcription_history SET corrected_text = ?1 WHERE timestamp = 100",
+            params!["call GitHub"],
+        )
+        .unwrap();
+
+        let corrections = HistoryManager::get_corrections_with_conn(&conn, None).unwrap();
+        assert_eq!(
+            corrections,
+            vec![("call get hub".to_string(), "call GitHub".to_string())]
+        );
+
+        let entry = HistoryManager::get_latest_entry_with_conn(&conn)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.corrected_text, None);
+    }
+
+    #[test]
+    fn tags_are_shared_between_entries() {
+        let conn = setup_conn();
+        insert_entry(&conn, 100, "standup notes", None);
+        let first = conn.last_insert_rowid();
+        insert_entry(&conn, 200, "email draft", None);
+        let second = conn.last_insert_rowid();
+
+        HistoryManager::add_tag_with_conn(&conn, first, "Work").unwrap();
+        HistoryManager::add_tag_with_conn(&conn, second, "work").unwrap();
+        HistoryManager::add_tag_with_conn(&conn, second, "email").unwrap();
+        assert!(HistoryManager::add_tag_with_conn(&conn, second, "  ").is_err());
+
+        let tags = HistoryManager::get_tags_with_conn(&conn).unwrap();
+        assert_eq!(
+            tags,
+            vec![
+                HistoryTag {
+                    name: "email".to_string(),
+                    entries: 1
+                },
+                HistoryTag {
+                    name: "Work".to_string(),
+                    entries: 2
+                },
+            ]
+        );
+
+        let tagged = HistoryManager::get_entries_by_tag_with_conn(&conn, "WORK").unwrap();
+        assert_eq!(
+            tagged.iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![second, first]
+        );
+        assert_eq!(tagged[0].tags, vec!["email", "Work"]);
+
+        HistoryManager::remove_tag_with_conn(&conn, second, "email").unwrap();
+        let tags = HistoryManager::get_tags_with_conn(&conn).unwrap();
+        assert_eq!(tags.len(), 1);
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM tags", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn usage_rows_use_the_final_text_and_range() {
+        let conn = setup_conn();
+        insert_entry(&conn, 100, "too old", None);
+        insert_entry(&conn, 200, "raw words", Some("processed words here"));
+        let id = conn.last_insert_rowid();
+        conn.execute(
+            "UPDATE transcription_history SET duration_ms = 1500, model = 'parakeet', transcription_ms = 300 WHERE id = ?1",
+            params![id],
+        )
+        .unwrap();
+
+        let rows = HistoryManager::get_usage_rows_with_conn(&conn, Some(150)).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].text, "processed words here");
+        assert_eq!(rows[0].word_count, None);
+        assert_eq!(rows[0].duration_ms, Some(1500));
+        assert_eq!(rows[0].model.as_deref(), Some("parakeet"));
+
+        assert_eq!(
+            HistoryManager::get_usage_rows_with_conn(&conn, None)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
 }
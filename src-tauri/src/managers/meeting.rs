@@ -0,0 +1,362 @@
+//! Long-form meeting sessions.
+//!
+//! A session records in segments cut at pauses (see
+//! `AudioRecordingManager::try_start_segmented_recording`). Segments are
+//! appended to the session's WAV file as soon as they arrive and transcribed
+//! in the background while recording continues. The WAV is converted to the
+//! configured recording format once the session ends. The text goes to a Markdown
+//! document, the session's history entry and a `meeting-segment` event for the
+//! live view.
+
+use crate::actions::perform_transcription;
+use crate::audio_toolkit::audio::AudioSegment;
+use crate::audio_toolkit::constants::WHISPER_SAMPLE_RATE;
+use crate::managers::audio::AudioRecordingManager;
+use crate::managers::history::{recording_format, HistoryManager, TranscriptSegment};
+use crate::managers::transcription::TranscriptionManager;
+use crate::settings::{get_settings, AppSettings};
+use crate::tray::{change_tray_icon, TrayIconState};
+use crate::utils;
+use chrono::{DateTime, Local, Utc};
+use hound::{WavSpec, WavWriter};
+use log::{debug, error, info, warn};
+use serde::Serialize;
+use specta::Type;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Binding id the meeting recording runs under
+pub const MEETING_BINDING_ID: &str = "meeting";
+
+#[derive(Clone, Debug, Serialize, Type)]
+pub struct MeetingStatus {
+    /// History entry the segments are added to
+    pub history_id: i64,
+    /// Markdown document the transcript is written to
+    pub notes_path: String,
+    /// Unix timestamp in seconds
+    pub started_at: i64,
+}
+
+/// Payload of the `meeting-segment` event, emitted for every transcribed segment.
+#[derive(Clone, Debug, Serialize, Type)]
+pub struct MeetingSegmentEvent {
+    pub history_id: i64,
+    pub segment: TranscriptSegment,
+}
+
+struct ActiveSession {
+    status: MeetingStatus,
+    segment_tx: mpsc::Sender<AudioSegment>,
+}
+
+/// A segment with its position in the session, waiting for transcription.
+struct PendingSegment {
+    samples: Vec<f32>,
+    start_ms: u64,
+    end_ms: u64,
+}
+
+pub struct MeetingManager {
+    app_handle: AppHandle,
+    session: Mutex<Option<ActiveSession>>,
+    /// Mirrors `session` without its lock, for the tray icon
+    running: AtomicBool,
+}
+
+impl MeetingManager {
+    pub fn new(app_handle: &AppHandle) -> Self {
+        Self {
+            app_handle: app_handle.clone(),
+            session: Mutex::new(None),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    pub fn status(&self) -> Option<MeetingStatus> {
+        self.session
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|s| s.status.clone())
+    }
+
+    pub fn start(&self) -> Result<MeetingStatus, String> {
+        let mut session = self.session.lock().unwrap();
+        if session.is_some() {
+            return Err("A meeting is already running".to_string());
+        }
+
+        let app = &self.app_handle;
+        let settings = get_settings(app);
+        let rm = app.state::<Arc<AudioRecordingManager>>();
+        let hm = Arc::clone(&app.state::<Arc<HistoryManager>>());
+
+        if !settings.cloud_stt_enabled {
+            app.state::<Arc<TranscriptionManager>>()
+                .initiate_model_load();
+        }
+
+        let started_at = Utc::now();
+        let notes_path = notes_path(app, &settings, started_at)?;
+        let (segment_tx, segment_rx) = mpsc::channel::<AudioSegment>();
+        // Segment offsets are measured from here
+        let clock = Instant::now();
+        if !rm.try_start_segmented_recording(MEETING_BINDING_ID, segment_tx.clone()) {
+            return Err("Failed to start recording".to_string());
+        }
+
+        let file_name = format!("handy-meeting-{}.wav", started_at.timestamp());
+        let opened = hm
+            .create_session_entry(
+                file_name.clone(),
+                started_at.timestamp(),
+                rm.active_microphone(),
+            )
+            .map_err(|e| e.to_string())
+            .and_then(|history_id| {
+                // Bulk conversion stays away from the WAV until it is final
+                hm.hold_recording(history_id);
+                let files = create_wav(&hm.get_audio_file_path(&file_name))
+                    .and_then(|audio| Ok((audio, create_notes(&notes_path, started_at)?)));
+                if files.is_err() {
+                    hm.release_recording(history_id);
+                }
+                files.map(|(audio, notes)| (history_id, audio, notes))
+            });
+        let (history_id, audio, notes) = match opened {
+            Ok(opened) => opened,
+            Err(e) => {
+                rm.cancel_recording();
+                return Err(format!("Failed to create meeting files: {}", e));
+            }
+        };
+
+        let (pending_tx, pending_rx) = mpsc::channel::<PendingSegment>();
+        let format = recording_format(&settings);
+        std::thread::spawn(move || {
+            store_segments(segment_rx, pending_tx, audio, clock);
+            if let Err(e) = hm.convert_recording(history_id, &file_name, format) {
+                error!("Failed to convert meeting audio: {}", e);
+            }
+            hm.release_recording(history_id);
+        });
+        let ah = app.clone();
+        std::thread::spawn(move || transcribe_segments(ah, history_id, pending_rx, notes));
+
+        let status = MeetingStatus {
+            history_id,
+            notes_path: notes_path.to_string_lossy().to_string(),
+            started_at: started_at.timestamp(),
+        };
+        *session = Some(ActiveSession {
+            status: status.clone(),
+            segment_tx,
+        });
+        self.running.store(true, Ordering::SeqCst);
+
+        info!("Meeting started, writing to {:?}", notes_path);
+        change_tray_icon(app, TrayIconState::Recording);
+        utils::show_overlay_notification(app, "meetingStarted", &[]);
+        Ok(status)
+    }
+
+    /// Ends the session. Segments still being transcribed are finished in
+    /// the background.
+    pub fn stop(&self) -> Option<MeetingStatus> {
+        let session = self.session.lock().unwrap().take()?;
+        self.running.store(false, Ordering::SeqCst);
+
+        // The recorder sends the rest of the audio as a last segment
+        let rm = self.app_handle.state::<Arc<AudioRecordingManager>>();
+        rm.stop_recording(MEETING_BINDING_ID);
+        // Dropping the last sender lets the workers run dry and finish
+        drop(session.segment_tx);
+
+        info!("Meeting stopped");
+        change_tray_icon(&self.app_handle, TrayIconState::Idle);
+        utils::show_overlay_notification(&self.app_handle, "meetingSaved", &[]);
+        Some(session.status)
+    }
+}
+
+/// Where the Markdown document of a session started at `started_at` goes.
+fn notes_path(
+    app: &AppHandle,
+    settings: &AppSettings,
+    started_at: DateTime<Utc>,
+) -> Result<PathBuf, String> {
+    let dir = match &settings.meeting_notes_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => app
+            .path()
+            .app_data_dir()
+            .map_err(|e| format!("Failed to get app data directory: {}", e))?
+            .join("meetings"),
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+
+    let local = started_at.with_timezone(&Local);
+    Ok(dir.join(format!("meeting-{}.md", local.format("%Y-%m-%d-%H%M%S"))))
+}
+
+fn create_wav(path: &Path) -> Result<WavWriter<BufWriter<File>>, String> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: WHISPER_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    WavWriter::create(path, spec).map_err(|e| e.to_string())
+}
+
+/// Starts the document with the local start time as its heading, which reads
+/// the same in every language.
+fn create_notes(path: &Path, started_at: DateTime<Utc>) -> Result<File, String> {
+    let mut file = File::create(path).map_err(|e| e.to_string())?;
+    let heading = started_at.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+    write!(file, "# {}\n\n", heading).map_err(|e| e.to_string())?;
+    Ok(file)
+}
+
+/// Places each segment on the session timeline and appends it to the session
+/// audio right away, then queues it for transcription.
+fn store_segments(
+    segment_rx: mpsc::Receiver<AudioSegment>,
+    pending_tx: mpsc::Sender<PendingSegment>,
+    mut audio: WavWriter<BufWriter<File>>,
+    clock: Instant,
+) {
+    while let Ok(segment) = segment_rx.recv() {
+        if segment.samples.is_empty() {
+            continue;
+        }
+
+        for &sample in &segment.samples {
+            let _ = audio.write_sample((sample * i16::MAX as f32) as i16);
+        }
+        // Keep the file playable if the app goes away mid-meeting
+        if let Err(e) = audio.flush() {
+            warn!("Failed to flush meeting audio: {}", e);
+        }
+
+        let _ = pending_tx.send(PendingSegment {
+            start_ms: offset_ms(clock, segment.started_at),
+            end_ms: offset_ms(clock, segment.ended_at),
+            samples: segment.samples,
+        });
+    }
+
+    if let Err(e) = audio.finalize() {
+        error!("Failed to finalize meeting audio: {}", e);
+    }
+}
+
+/// Milliseconds from the session start to `at`.
+fn offset_ms(clock: Instant, at: Instant) -> u64 {
+    at.saturating_duration_since(clock).as_millis() as u64
+}
+
+fn transcribe_segments(
+    app: AppHandle,
+    history_id: i64,
+    pending_rx: mpsc::Receiver<PendingSegment>,
+    mut notes: File,
+) {
+    let tm = Arc::clone(&app.state::<Arc<TranscriptionManager>>());
+    let hm = Arc::clone(&app.state::<Arc<HistoryManager>>());
+
+    while let Ok(pending) = pending_rx.recv() {
+        let settings = get_settings(&app);
+        let text = match tauri::async_runtime::block_on(perform_transcription(
+            &settings,
+            &tm,
+            pending.samples,
+        )) {
+            Ok(text) => text.trim().to_string(),
+            Err(e) => {
+                error!("Failed to transcribe meeting segment: {}", e);
+                continue;
+            }
+        };
+        if text.is_empty() {
+            continue;
+        }
+
+        let segment = TranscriptSegment {
+            start_ms: pending.start_ms,
+            end_ms: pending.end_ms,
+            text,
+        };
+        debug!(
+            "Meeting segment at {}: '{}'",
+            format_offset(segment.start_ms),
+            segment.text
+        );
+
+        if let Err(e) = notes
+            .write_all(markdown_line(&segment).as_bytes())
+            .and_then(|_| notes.flush())
+        {
+            error!("Failed to write meeting notes: {}", e);
+        }
+        if let Err(e) = hm.append_segment(history_id, segment.clone()) {
+            error!("Failed to add meeting segment to history: {}", e);
+        }
+        let _ = app.emit(
+            "meeting-segment",
+            MeetingSegmentEvent {
+                history_id,
+                segment,
+            },
+        );
+    }
+
+    debug!("Meeting {} fully transcribed", history_id);
+}
+
+/// `HH:MM:SS` offset into the session.
+fn format_offset(ms: u64) -> String {
+    let seconds = ms / 1000;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn markdown_line(segment: &TranscriptSegment) -> String {
+    format!(
+        "**[{}]** {}\n\n",
+        format_offset(segment.start_ms),
+        segment.text
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_segments_with_offsets() {
+        assert_eq!(format_offset(0), "00:00:00");
+        assert_eq!(format_offset(3_723_999), "01:02:03");
+
+        let segment = TranscriptSegment {
+            start_ms: 65_000,
+            end_ms: 70_000,
+            text: "Next item.".to_string(),
+        };
+        assert_eq!(markdown_line(&segment), "**[00:01:05]** Next item.\n\n");
+    }
+}
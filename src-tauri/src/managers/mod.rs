@@ -1,4 +1,6 @@
 pub mod audio;
 pub mod history;
+pub mod meeting;
 pub mod model;
+pub mod settings;
 pub mod transcription;
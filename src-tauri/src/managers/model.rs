@@ -1,4 +1,4 @@
-use crate::settings::{get_settings, write_settings};
+use crate::settings::{get_settings, update_settings};
 use anyhow::Result;
 use flate2::read::GzDecoder;
 use futures_util::StreamExt;
@@ -320,9 +320,9 @@ impl ModelManager {
                 );
 
                 // Update settings with the selected model
-                let mut updated_settings = settings;
-                updated_settings.selected_model = available_model.id.clone();
-                write_settings(&self.app_handle, updated_settings);
+                update_settings(&self.app_handle, |settings| {
+                    settings.selected_model = available_model.id.clone();
+                });
 
                 info!("Successfully auto-selected model: {}", available_model.id);
             }
@@ -0,0 +1,156 @@
+use crate::settings::{load_or_create_app_settings, AppSettings, SETTINGS_STORE_PATH};
+use anyhow::Result;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use specta::Type;
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_store::StoreExt;
+
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
+/// Payload of the `settings-changed` event, emitted after every write that
+/// actually changed something.
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+pub struct SettingsChangedEvent {
+    /// Top-level `AppSettings` field names whose value changed
+    pub changed: Vec<String>,
+}
+
+impl SettingsChangedEvent {
+    pub fn contains(&self, field: &str) -> bool {
+        self.changed.iter().any(|f| f == field)
+    }
+}
+
+/// Keeps the parsed settings in memory so reads don't go through the store.
+/// Writes update the cache and the store together.
+pub struct SettingsManager {
+    app_handle: AppHandle,
+    cache: RwLock<AppSettings>,
+}
+
+impl SettingsManager {
+    pub fn new(app_handle: &AppHandle) -> Result<Self> {
+        let settings = load_or_create_app_settings(app_handle);
+
+        Ok(Self {
+            app_handle: app_handle.clone(),
+            cache: RwLock::new(settings),
+        })
+    }
+
+    pub fn get(&self) -> AppSettings {
+        self.cache.read().unwrap().clone()
+    }
+
+    /// Replaces the cached settings, persists them and notifies listeners of
+    /// the fields that changed.
+    pub fn replace(&self, settings: AppSettings) {
+        self.update(|current| *current = settings);
+    }
+
+    /// Changes the settings in place. The write lock is held from reading to
+    /// persisting, so concurrent updates can't overwrite each other. `f` must
+    /// not read the settings itself. Listeners are notified of the fields that
+    /// changed once the lock is released.
+    pub fn update<R>(&self, f: impl FnOnce(&mut AppSettings) -> R) -> R {
+        let (result, changed) = {
+            let mut cache = self.cache.write().unwrap();
+            let (result, changed, new_value) = apply_update(&mut cache, f);
+            if !changed.is_empty() {
+                match self.app_handle.store(SETTINGS_STORE_PATH) {
+                    Ok(store) => store.set("settings", new_value),
+                    Err(e) => warn!("Failed to open settings store: {}", e),
+                }
+            }
+            (result, changed)
+        };
+
+        if !changed.is_empty() {
+            debug!("Settings changed: {}", changed.join(", "));
+            if let Err(e) = self
+                .app_handle
+                .emit(SETTINGS_CHANGED_EVENT, SettingsChangedEvent { changed })
+            {
+                warn!("Failed to emit settings-changed event: {}", e);
+            }
+        }
+        result
+    }
+}
+
+/// Runs `f` on `settings` and returns its result, the fields it changed and
+/// the new serialized settings.
+fn apply_update<R>(
+    settings: &mut AppSettings,
+    f: impl FnOnce(&mut AppSettings) -> R,
+) -> (R, Vec<String>, Value) {
+    let old_value = serde_json::to_value(&*settings).unwrap_or(Value::Null);
+    let result = f(settings);
+    match serde_json::to_value(&*settings) {
+        Ok(new_value) => {
+            let changed = changed_fields(&old_value, &new_value);
+            (result, changed, new_value)
+        }
+        Err(e) => {
+            warn!("Failed to serialize settings: {}", e);
+            (result, Vec::new(), Value::Null)
+        }
+    }
+}
+
+/// Names of the top-level fields that differ between two serialized settings.
+fn changed_fields(old: &Value, new: &Value) -> Vec<String> {
+    let (Value::Object(old), Value::Object(new)) = (old, new) else {
+        return vec!["settings".to_string()];
+    };
+
+    let mut changed: Vec<String> = new
+        .iter()
+        .filter(|(key, value)| old.get(*key) != Some(*value))
+        .map(|(key, _)| key.clone())
+        .chain(old.keys().filter(|key| !new.contains_key(*key)).cloned())
+        .collect();
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reports_only_changed_fields() {
+        let old = json!({"push_to_talk": true, "selected_language": "en", "custom_words": []});
+        let new =
+            json!({"push_to_talk": false, "selected_language": "en", "custom_words": ["Handy"]});
+        assert_eq!(
+            changed_fields(&old, &new),
+            vec!["custom_words".to_string(), "push_to_talk".to_string()]
+        );
+        assert!(changed_fields(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn replacing_settings_reports_exactly_the_changed_keys() {
+        let mut current = crate::settings::get_default_settings();
+        let mut replacement = current.clone();
+        replacement.audio_source = crate::settings::AudioSource::SystemAudio;
+        replacement.selected_microphone = Some("USB Mic".to_string());
+
+        let ((), changed, _) = apply_update(&mut current, |s| *s = replacement);
+        assert_eq!(
+            changed,
+            vec![
+                "audio_source".to_string(),
+                "selected_microphone".to_string()
+            ]
+        );
+
+        let ((), changed, _) = apply_update(&mut current, |_| {});
+        assert!(changed.is_empty());
+    }
+}
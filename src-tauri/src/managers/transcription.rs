@@ -336,6 +336,20 @@ impl TranscriptionManager {
         });
     }
 
+    /// Blocks while a model is being loaded.
+    pub fn wait_for_model_load(&self) {
+        let mut is_loading = self.is_loading.lock().unwrap();
+        while *is_loading {
+            is_loading = self.loading_condvar.wait(is_loading).unwrap();
+        }
+    }
+
+    /// Whether the loaded model transcribes in `selected_language`. Only
+    /// Whisper takes a language, the others pick it themselves.
+    pub fn follows_language_setting(&self) -> bool {
+        matches!(*self.engine.lock().unwrap(), Some(LoadedEngine::Whisper(_)))
+    }
+
     pub fn get_current_model(&self) -> Option<String> {
         let current_model = self.current_model_id.lock().unwrap();
         current_model.clone()
@@ -363,11 +377,7 @@ impl TranscriptionManager {
 
         // Check if model is loaded, if not try to load it
         {
-            // If the model is loading, wait for it to complete.
-            let mut is_loading = self.is_loading.lock().unwrap();
-            while *is_loading {
-                is_loading = self.loading_condvar.wait(is_loading).unwrap();
-            }
+            self.wait_for_model_load();
 
             let engine_guard = self.engine.lock().unwrap();
             if engine_guard.is_none() {
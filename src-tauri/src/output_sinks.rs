@@ -0,0 +1,228 @@
+use crate::settings::{AppSettings, OutputSink, OutputSinkKind};
+use crate::utils::expand_template;
+use log::{debug, error};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Commands still running after this are killed
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Runs every enabled output sink for `text` in the background.
+/// Failures are logged per sink and never affect pasting.
+pub fn dispatch(settings: &AppSettings, text: &str) {
+    let sinks: Vec<OutputSink> = settings
+        .output_sinks
+        .iter()
+        .filter(|sink| sink.enabled)
+        .cloned()
+        .collect();
+
+    if sinks.is_empty() {
+        return;
+    }
+
+    let text = text.to_string();
+    tauri::async_runtime::spawn(async move {
+        for sink in sinks {
+            match run_sink(&sink, &text).await {
+                Ok(()) => debug!("Output sink '{}' completed", sink.name),
+                Err(e) => error!("Output sink '{}' failed: {}", sink.name, e),
+            }
+        }
+    });
+}
+
+async fn run_sink(sink: &OutputSink, text: &str) -> Result<(), String> {
+    let now = chrono::Local::now();
+    let payload = render_template(sink.effective_template(), text, &now);
+
+    match &sink.kind {
+        OutputSinkKind::AppendToFile { path } => {
+            let path = expand_path(&render_template(path, text, &now));
+            tauri::async_runtime::spawn_blocking(move || append_to_file(&path, &payload))
+                .await
+                .map_err(|e| e.to_string())?
+        }
+        OutputSinkKind::RunCommand { command } => {
+            let command = command.clone();
+            tauri::async_runtime::spawn_blocking(move || run_command(&command, &payload))
+                .await
+                .map_err(|e| e.to_string())?
+        }
+        OutputSinkKind::Webhook { url } => post_webhook(url, payload).await,
+    }
+}
+
+/// Expands `${text}`, `${text_json}`, `${timestamp}` and `${date}` in a sink template.
+fn render_template(template: &str, text: &str, now: &chrono::DateTime<chrono::Local>) -> String {
+    let text_json = serde_json::to_string(text).unwrap_or_default();
+    expand_template(
+        template,
+        &[
+            ("text_json", &text_json),
+            ("text", text),
+            ("timestamp", &now.to_rfc3339()),
+            ("date", &now.format("%Y-%m-%d").to_string()),
+        ],
+    )
+}
+
+fn expand_path(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+            return PathBuf::from(home).join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
+
+fn append_to_file(path: &PathBuf, payload: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    file.write_all(payload.as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Runs `command` through the platform shell with the payload on stdin, and
+/// kills it after `COMMAND_TIMEOUT`.
+fn run_command(command: &str, payload: &str) -> Result<(), String> {
+    run_command_with_timeout(command, payload, COMMAND_TIMEOUT)
+}
+
+fn run_command_with_timeout(command: &str, payload: &str, timeout: Duration) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    let mut cmd = {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    };
+    #[cfg(not(target_os = "windows"))]
+    let mut cmd = {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to spawn command: {}", e))?;
+
+    // Pipes are served from threads, so a command that doesn't read its
+    // input or writes a lot of errors can't block us past the timeout
+    let stdin = child.stdin.take().map(|mut stdin| {
+        let payload = payload.to_string();
+        std::thread::spawn(move || stdin.write_all(payload.as_bytes()))
+    });
+    let stderr = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stderr.read_to_string(&mut output);
+            output
+        })
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if started.elapsed() >= timeout => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("Command timed out after {:?}", timeout));
+            }
+            Ok(None) => std::thread::sleep(COMMAND_POLL_INTERVAL),
+            Err(e) => return Err(format!("Failed to wait for command: {}", e)),
+        }
+    };
+
+    if let Some(Ok(Err(e))) = stdin.map(|handle| handle.join()) {
+        debug!("Command didn't read all of its input: {}", e);
+    }
+    if !status.success() {
+        let stderr = stderr
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default();
+        return Err(format!("Command exited with {}: {}", status, stderr.trim()));
+    }
+
+    Ok(())
+}
+
+async fn post_webhook(url: &str, payload: String) -> Result<(), String> {
+    let response = reqwest::Client::new()
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .timeout(WEBHOOK_TIMEOUT)
+        .body(payload)
+        .send()
+        .await
+        .map_err(|e| format!("Webhook request failed: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Webhook returned status {}", status));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn renders_template_variables() {
+        let now = chrono::Local
+            .with_ymd_and_hms(2024, 5, 1, 9, 30, 0)
+            .unwrap();
+        let rendered = render_template(
+            "{\"text\": ${text_json}, \"day\": \"${date}\"} ${text}",
+            "say \"hi\"",
+            &now,
+        );
+        assert_eq!(
+            rendered,
+            "{\"text\": \"say \\\"hi\\\"\", \"day\": \"2024-05-01\"} say \"hi\""
+        );
+    }
+    #[test]
+    fn values_are_not_expanded_again() {
+        let now = chrono::Local
+            .with_ymd_and_hms(2024, 5, 1, 9, 30, 0)
+            .unwrap();
+        assert_eq!(
+            render_template("${text} on ${date} ${unknown}", "type ${date}", &now),
+            "type ${date} on 2024-05-01 ${unknown}"
+        );
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn commands_are_killed_after_the_timeout() {
+        let started = Instant::now();
+        let result = run_command_with_timeout("sleep 5", "", Duration::from_millis(200));
+        assert!(result.unwrap_err().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        assert!(run_command_with_timeout("cat > /dev/null", "hello", COMMAND_TIMEOUT).is_ok());
+        let failed = run_command_with_timeout("echo oops >&2; exit 3", "", COMMAND_TIMEOUT);
+        assert!(failed.unwrap_err().contains("oops"));
+    }
+}
@@ -1,6 +1,9 @@
 use crate::input;
 use crate::settings;
 use crate::settings::OverlayPosition;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize};
 
 #[cfg(not(target_os = "macos"))]
@@ -39,6 +42,12 @@ const OVERLAY_BOTTOM_OFFSET: f64 = 15.0;
 #[cfg(any(target_os = "windows", target_os = "linux"))]
 const OVERLAY_BOTTOM_OFFSET: f64 = 40.0;
 
+const NOTIFICATION_DURATION_MS: u64 = 1500;
+
+/// Bumped whenever the overlay changes state, so a pending notification
+/// auto-hide does not hide a recording that started in the meantime.
+static OVERLAY_GENERATION: AtomicU64 = AtomicU64::new(0);
+
 /// Forces a window to be topmost using Win32 API (Windows only)
 /// This is more reliable than Tauri's set_always_on_top which can be overridden
 #[cfg(target_os = "windows")]
@@ -206,6 +215,8 @@ pub fn create_recording_overlay(app_handle: &AppHandle) {
 
 /// Shows the recording overlay window with fade-in animation
 pub fn show_recording_overlay(app_handle: &AppHandle) {
+    OVERLAY_GENERATION.fetch_add(1, Ordering::SeqCst);
+
     // Check if overlay should be shown based on position setting
     let settings = settings::get_settings(app_handle);
     if settings.overlay_position == OverlayPosition::None {
@@ -232,6 +243,8 @@ pub fn show_recording_overlay(app_handle: &AppHandle) {
 
 /// Shows the transcribing overlay window
 pub fn show_transcribing_overlay(app_handle: &AppHandle) {
+    OVERLAY_GENERATION.fetch_add(1, Ordering::SeqCst);
+
     // Check if overlay should be shown based on position setting
     let settings = settings::get_settings(app_handle);
     if settings.overlay_position == OverlayPosition::None {
@@ -252,6 +265,52 @@ pub fn show_transcribing_overlay(app_handle: &AppHandle) {
     }
 }
 
+/// Payload of `overlay-notification`, translated by the overlay.
+#[derive(Clone, Debug, Serialize)]
+struct OverlayNotification {
+    /// Key under `overlay.notifications` in the frontend translations
+    key: String,
+    /// Interpolation values for the message
+    params: HashMap<String, String>,
+}
+
+/// Shows a short message in the overlay and hides it again after a moment,
+/// unless a recording has taken over the overlay by then. `key` names the
+/// message in `overlay.notifications`, `params` fill in its placeholders.
+pub fn show_overlay_notification(app_handle: &AppHandle, key: &str, params: &[(&str, &str)]) {
+    let settings = settings::get_settings(app_handle);
+    if settings.overlay_position == OverlayPosition::None {
+        return;
+    }
+
+    let generation = OVERLAY_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    update_overlay_position(app_handle);
+
+    if let Some(overlay_window) = app_handle.get_webview_window("recording_overlay") {
+        let _ = overlay_window.show();
+
+        #[cfg(target_os = "windows")]
+        force_overlay_topmost(&overlay_window);
+
+        let notification = OverlayNotification {
+            key: key.to_string(),
+            params: params
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        };
+        let _ = overlay_window.emit("overlay-notification", notification);
+
+        let app_handle = app_handle.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(NOTIFICATION_DURATION_MS));
+            if OVERLAY_GENERATION.load(Ordering::SeqCst) == generation {
+                hide_recording_overlay(&app_handle);
+            }
+        });
+    }
+}
+
 /// Updates the overlay window position based on current settings
 pub fn update_overlay_position(app_handle: &AppHandle) {
     if let Some(overlay_window) = app_handle.get_webview_window("recording_overlay") {
@@ -0,0 +1,459 @@
+//! Transcript post-processing pipeline
+//!
+//! Runs the ordered list of stages returned by
+//! `AppSettings::effective_post_process_pipeline` over a transcription. Every
+//! stage receives the output of the previous one; a stage that fails leaves the
+//! text untouched so the rest of the pipeline can still run. The per-stage
+//! results are kept so they can be stored alongside the history entry.
+
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+use crate::apple_intelligence;
+use crate::audio_toolkit::{apply_custom_words, filter_transcription_output};
+use crate::settings::{
+    AppSettings, ChineseConversion, PostProcessProvider, PostProcessStage, PostProcessStageKind,
+    APPLE_INTELLIGENCE_PROVIDER_ID,
+};
+use ferrous_opencc::{config::BuiltinConfig, OpenCC};
+use log::{debug, error};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Outcome of a single pipeline stage, persisted with the history entry.
+#[derive(Clone, Debug, Serialize, Deserialize, Type)]
+pub struct PostProcessStageResult {
+    pub stage_id: String,
+    pub name: String,
+    /// Whether the stage changed or produced text. Skipped and failed stages are `false`.
+    pub applied: bool,
+    /// Text after this stage ran (identical to the input when not applied)
+    pub output: String,
+    pub error: Option<String>,
+}
+
+pub struct PipelineOutput {
+    pub text: String,
+    pub stages: Vec<PostProcessStageResult>,
+    /// Prompt text of the last LLM stage that produced output
+    pub prompt: Option<String>,
+}
+
+enum StageOutcome {
+    Applied(String),
+    Skipped(String),
+    Failed(String),
+}
+
+pub async fn run_pipeline(settings: &AppSettings, transcription: &str) -> PipelineOutput {
+    let mut text = transcription.to_string();
+    let mut stages = Vec::new();
+    let mut prompt = None;
+
+    for stage in settings.effective_post_process_pipeline() {
+        if !stage.enabled {
+            continue;
+        }
+
+        debug!("Running post-process stage '{}' ({})", stage.name, stage.id);
+
+        let (applied, error) = match run_stage(settings, &stage, &text).await {
+            StageOutcome::Applied(output) => {
+                if let PostProcessStageKind::LlmPrompt { prompt_id, .. } = &stage.kind {
+                    prompt = find_prompt(settings, prompt_id);
+                }
+                text = output;
+                (true, None)
+            }
+            StageOutcome::Skipped(reason) => {
+                debug!("Post-process stage '{}' skipped: {}", stage.name, reason);
+                (false, None)
+            }
+            StageOutcome::Failed(err) => {
+                error!(
+                    "Post-process stage '{}' failed: {}. Continuing with its input.",
+                    stage.name, err
+                );
+                (false, Some(err))
+            }
+        };
+
+        stages.push(PostProcessStageResult {
+            stage_id: stage.id,
+            name: stage.name,
+            applied,
+            output: text.clone(),
+            error,
+        });
+    }
+
+    PipelineOutput {
+        text,
+        stages,
+        prompt,
+    }
+}
+
+async fn run_stage(settings: &AppSettings, stage: &PostProcessStage, text: &str) -> StageOutcome {
+    match &stage.kind {
+        PostProcessStageKind::FilterOutput => {
+            StageOutcome::Applied(filter_transcription_output(text))
+        }
+        PostProcessStageKind::CustomWords => {
+            if settings.custom_words.is_empty() {
+                return StageOutcome::Skipped("no custom words configured".to_string());
+            }
+            StageOutcome::Applied(apply_custom_words(
+                text,
+                &settings.custom_words,
+                settings.word_correction_threshold,
+            ))
+        }
+        PostProcessStageKind::OpenCC { conversion } => match convert_chinese(*conversion, text) {
+            Ok(converted) => StageOutcome::Applied(converted),
+            Err(e) => StageOutcome::Failed(e),
+        },
+        PostProcessStageKind::RegexReplace {
+            pattern,
+            replacement,
+        } => match Regex::new(pattern) {
+            Ok(re) => {
+                StageOutcome::Applied(re.replace_all(text, replacement.as_str()).into_owned())
+            }
+            Err(e) => StageOutcome::Failed(format!("Invalid regex '{}': {}", pattern, e)),
+        },
+        PostProcessStageKind::LlmPrompt {
+            prompt_id,
+            provider_id,
+            model,
+        } => {
+            if !settings.post_process_enabled {
+                return StageOutcome::Skipped("post-processing is disabled".to_string());
+            }
+
+            let Some(prompt) = find_prompt(settings, prompt_id) else {
+                return StageOutcome::Failed(format!("Prompt '{}' was not found", prompt_id));
+            };
+            if prompt.trim().is_empty() {
+                return StageOutcome::Skipped("prompt is empty".to_string());
+            }
+
+            let (provider, model) =
+                match resolve_llm_target(settings, provider_id.as_deref(), model.as_deref()) {
+                    Ok(target) => target,
+                    Err(reason) => return StageOutcome::Skipped(reason),
+                };
+
+            // Replace ${output} variable in the prompt with the actual text
+            let processed_prompt = prompt.replace("${output}", text);
+
+            match run_llm_prompt(settings, &provider, &model, processed_prompt).await {
+                Ok(output) => StageOutcome::Applied(output),
+                Err(e) => StageOutcome::Failed(e),
+            }
+        }
+    }
+}
+
+fn find_prompt(settings: &AppSettings, prompt_id: &str) -> Option<String> {
+    settings
+        .post_process_prompts
+        .iter()
+        .find(|prompt| prompt.id == prompt_id)
+        .map(|prompt| prompt.prompt.clone())
+}
+
+fn convert_chinese(conversion: ChineseConversion, text: &str) -> Result<String, String> {
+    let config = match conversion {
+        // Convert Traditional Chinese to Simplified Chinese
+        ChineseConversion::ToSimplified => BuiltinConfig::Tw2sp,
+        // Convert Simplified Chinese to Traditional Chinese
+        ChineseConversion::ToTraditional => BuiltinConfig::S2twp,
+    };
+
+    let converter = OpenCC::from_config(config)
+        .map_err(|e| format!("Failed to initialize OpenCC converter: {}", e))?;
+    let converted = converter.convert(text);
+    debug!(
+        "OpenCC translation completed. Input length: {}, Output length: {}",
+        text.len(),
+        converted.len()
+    );
+    Ok(converted)
+}
+
+/// Resolves the provider and model an LLM request should use, falling back to the
+/// globally selected provider and its configured model.
+pub fn resolve_llm_target(
+    settings: &AppSettings,
+    provider_id: Option<&str>,
+    model: Option<&str>,
+) -> Result<(PostProcessProvider, String), String> {
+    let provider = match provider_id {
+        Some(id) => settings.post_process_provider(id).cloned(),
+        None => settings.active_post_process_provider().cloned(),
+    }
+    .ok_or_else(|| "no provider is selected".to_string())?;
+
+    let model = match model.filter(|m| !m.trim().is_empty()) {
+        Some(model) => model.to_string(),
+        None => settings
+            .post_process_models
+            .get(&provider.id)
+            .cloned()
+            .unwrap_or_default(),
+    };
+
+    if model.trim().is_empty() {
+        return Err(format!(
+            "provider '{}' has no model configured",
+            provider.id
+        ));
+    }
+
+    Ok((provider, model))
+}
+
+/// Sends an already-expanded prompt to the given provider and returns the cleaned response.
+pub async fn run_llm_prompt(
+    settings: &AppSettings,
+    provider: &PostProcessProvider,
+    model: &str,
+    prompt: String,
+) -> Result<String, String> {
+    debug!(
+        "Starting LLM post-processing with provider '{}' (model: {})",
+        provider.id, model
+    );
+    debug!("Processed prompt length: {} chars", prompt.len());
+
+    if provider.id == APPLE_INTELLIGENCE_PROVIDER_ID {
+        #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+        {
+            if !apple_intelligence::check_apple_intelligence_availability() {
+                return Err(
+                    "Apple Intelligence selected but not currently available on this device"
+                        .to_string(),
+                );
+            }
+
+            let token_limit = model.trim().parse::<i32>().unwrap_or(0);
+            let result = apple_intelligence::process_text(&prompt, token_limit)
+                .map_err(|e| format!("Apple Intelligence post-processing failed: {}", e))?;
+            if result.trim().is_empty() {
+                return Err("Apple Intelligence returned an empty response".to_string());
+            }
+            debug!(
+                "Apple Intelligence post-processing succeeded. Output length: {} chars",
+                result.len()
+            );
+            return Ok(result);
+        }
+
+        #[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+        {
+            return Err("Apple Intelligence provider selected on unsupported platform".to_string());
+        }
+    }
+
+    let api_key = crate::secrets::resolve_api_key(&settings.post_process_api_keys, &provider.id);
+
+    match crate::llm_client::send_chat_completion(provider, api_key, model, prompt).await {
+        Ok(Some(content)) => {
+            let content = strip_invisible_characters(&content);
+            debug!(
+                "LLM post-processing succeeded for provider '{}'. Output length: {} chars",
+                provider.id,
+                content.len()
+            );
+            Ok(content)
+        }
+        Ok(None) => Err("LLM API response has no content".to_string()),
+        Err(e) => Err(format!(
+            "LLM post-processing failed for provider '{}': {}",
+            provider.id, e
+        )),
+    }
+}
+
+/// Prompt for `generate_title`
+const TITLE_PROMPT: &str = "Write a title of at most six words for the following dictation. \
+Reply with the title only, without quotes or punctuation at the end.\n\n${output}";
+/// Titles are cut to this many characters
+const MAX_TITLE_CHARS: usize = 60;
+
+/// Asks the selected post-processing LLM for a short title for `text`.
+pub async fn generate_title(settings: &AppSettings, text: &str) -> Result<String, String> {
+    if !settings.post_process_enabled {
+        return Err("post-processing is disabled".to_string());
+    }
+    if text.trim().is_empty() {
+        return Err("the transcription is empty".to_string());
+    }
+
+    let (provider, model) = resolve_llm_target(settings, None, None)?;
+    let response = run_llm_prompt(
+        settings,
+        &provider,
+        &model,
+        TITLE_PROMPT.replace("${output}", text),
+    )
+    .await?;
+
+    clean_title(&response).ok_or_else(|| "the LLM returned no title".to_string())
+}
+
+/// First line of an LLM reply without quotes and trailing punctuation.
+fn clean_title(response: &str) -> Option<String> {
+    let line = response.lines().map(str::trim).find(|l| !l.is_empty())?;
+    let title = line
+        .trim_start_matches(|c: char| c == '"' || c == '\'' || c == '#' || c.is_whitespace())
+        .trim_end_matches(|c: char| matches!(c, '"' | '\'' | '.' | '!' | ':') || c.is_whitespace());
+    if title.is_empty() {
+        return None;
+    }
+    Some(title.chars().take(MAX_TITLE_CHARS).collect())
+}
+
+/// Strip invisible Unicode characters that some LLMs (e.g., Qwen) may insert
+fn strip_invisible_characters(content: &str) -> String {
+    content
+        .replace('\u{200B}', "") // Zero-Width Space
+        .replace('\u{200C}', "") // Zero-Width Non-Joiner
+        .replace('\u{200D}', "") // Zero-Width Joiner
+        .replace('\u{FEFF}', "") // Byte Order Mark / Zero-Width No-Break Space
+}
+
+/// Validates user-supplied stages before they are persisted.
+pub fn validate_stage(settings: &AppSettings, stage: &PostProcessStage) -> Result<(), String> {
+    match &stage.kind {
+        PostProcessStageKind::RegexReplace { pattern, .. } => Regex::new(pattern)
+            .map(|_| ())
+            .map_err(|e| format!("Stage '{}' has an invalid regex: {}", stage.name, e)),
+        PostProcessStageKind::LlmPrompt {
+            prompt_id,
+            provider_id,
+            ..
+        } => {
+            if find_prompt(settings, prompt_id).is_none() {
+                return Err(format!(
+                    "Stage '{}' references unknown prompt '{}'",
+                    stage.name, prompt_id
+                ));
+            }
+            if let Some(provider_id) = provider_id {
+                if settings.post_process_provider(provider_id).is_none() {
+                    return Err(format!(
+                        "Stage '{}' references unknown provider '{}'",
+                        stage.name, provider_id
+                    ));
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::get_default_settings;
+
+    fn stage(id: &str, kind: PostProcessStageKind) -> PostProcessStage {
+        PostProcessStage {
+            id: id.to_string(),
+            name: id.to_string(),
+            enabled: true,
+            kind,
+        }
+    }
+
+    #[test]
+    fn empty_pipeline_falls_back_to_legacy_stages() {
+        let mut settings = get_default_settings();
+        settings.selected_language = "zh-Hans".to_string();
+        settings.post_process_selected_prompt_id = Some("default_improve_transcriptions".into());
+
+        let stages = settings.effective_post_process_pipeline();
+        assert_eq!(stages.len(), 2);
+        assert!(matches!(
+            stages[0].kind,
+            PostProcessStageKind::OpenCC { .. }
+        ));
+        assert!(matches!(
+            stages[1].kind,
+            PostProcessStageKind::LlmPrompt { .. }
+        ));
+    }
+
+    #[test]
+    fn stages_run_in_order_and_record_outputs() {
+        let mut settings = get_default_settings();
+        settings.post_process_pipeline = vec![
+            stage("filter", PostProcessStageKind::FilterOutput),
+            stage(
+                "regex",
+                PostProcessStageKind::RegexReplace {
+                    pattern: r"\bteh\b".to_string(),
+                    replacement: "the".to_string(),
+                },
+            ),
+        ];
+
+        let output = tauri::async_runtime::block_on(run_pipeline(&settings, "um teh  cat"));
+        assert_eq!(output.text, "the cat");
+        assert_eq!(output.stages.len(), 2);
+        assert_eq!(output.stages[0].output, "teh cat");
+        assert_eq!(output.stages[1].output, "the cat");
+        assert!(output.prompt.is_none());
+    }
+
+    #[test]
+    fn failed_stage_keeps_its_input() {
+        let mut settings = get_default_settings();
+        settings.post_process_pipeline = vec![stage(
+            "bad_regex",
+            PostProcessStageKind::RegexReplace {
+                pattern: "(".to_string(),
+                replacement: String::new(),
+            },
+        )];
+
+        let output = tauri::async_runtime::block_on(run_pipeline(&settings, "hello"));
+        assert_eq!(output.text, "hello");
+        assert!(!output.stages[0].applied);
+        assert!(output.stages[0].error.is_some());
+    }
+
+    #[test]
+    fn llm_stage_is_skipped_when_post_processing_disabled() {
+        let mut settings = get_default_settings();
+        settings.post_process_enabled = false;
+        settings.post_process_pipeline = vec![stage(
+            "llm",
+            PostProcessStageKind::LlmPrompt {
+                prompt_id: "default_improve_transcriptions".to_string(),
+                provider_id: None,
+                model: None,
+            },
+        )];
+
+        let output = tauri::async_runtime::block_on(run_pipeline(&settings, "hello"));
+        assert_eq!(output.text, "hello");
+        assert!(!output.stages[0].applied);
+        assert!(output.stages[0].error.is_none());
+    }
+
+    #[test]
+    fn titles_are_cleaned_up() {
+        assert_eq!(
+            clean_title("\n\"Quarterly budget review.\"\nSure, here it is").as_deref(),
+            Some("Quarterly budget review")
+        );
+        assert_eq!(clean_title("  \n\"\""), None);
+        assert_eq!(
+            clean_title(&"word ".repeat(40)).unwrap().chars().count(),
+            60
+        );
+    }
+}
@@ -0,0 +1,472 @@
+//! Storage for API keys outside of `settings_store.json`.
+//!
+//! Secrets live in the OS keyring (Keychain, Credential Manager, Secret Service)
+//! when one is reachable. Otherwise they go into `secrets.enc` in the app data
+//! directory, encrypted with ChaCha20-Poly1305 under a key generated on first
+//! use and kept next to it in `secrets.key`.
+//!
+//! Settings only hold references of the form `secret:<name>`. Values resolved
+//! here are never written back into settings or returned to the frontend;
+//! commands hand out `mask`ed values instead.
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use log::{debug, info, warn};
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::settings::{self, AppSettings};
+
+/// Prefix marking a settings value as a reference into the secret store
+pub const SECRET_REF_PREFIX: &str = "secret:";
+
+const SECRETS_FILE: &str = "secrets.enc";
+const KEY_FILE: &str = "secrets.key";
+const NONCE_LEN: usize = 12;
+
+/// Set to `file` to skip the OS keyring, e.g. on headless machines
+const BACKEND_ENV: &str = "HANDY_SECRETS_BACKEND";
+
+static STORE: OnceCell<SecretStore> = OnceCell::new();
+
+enum Backend {
+    Keyring { service: String },
+    File(FileVault),
+}
+
+struct SecretStore {
+    backend: Backend,
+    /// Resolved values, so the keyring is only asked once per secret
+    cache: Mutex<HashMap<String, String>>,
+}
+
+/// Encrypted key-value file used when no OS keyring is available.
+pub struct FileVault {
+    data_path: PathBuf,
+    key_path: PathBuf,
+    /// Held from reading the file to writing it back, so concurrent writers
+    /// don't drop each other's secrets
+    lock: Mutex<()>,
+}
+
+impl FileVault {
+    pub fn new(dir: &Path) -> Self {
+        FileVault {
+            data_path: dir.join(SECRETS_FILE),
+            key_path: dir.join(KEY_FILE),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<String>, String> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.load()?.remove(name))
+    }
+
+    pub fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.load()?;
+        secrets.insert(name.to_string(), value.to_string());
+        self.save(&secrets)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.load()?;
+        if secrets.remove(name).is_some() {
+            self.save(&secrets)?;
+        }
+        Ok(())
+    }
+
+    fn cipher(&self) -> Result<ChaCha20Poly1305, String> {
+        let key = match std::fs::read(&self.key_path) {
+            Ok(bytes) if bytes.len() == 32 => Key::clone_from_slice(&bytes),
+            Ok(_) => return Err(format!("{} is corrupt", self.key_path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                write_private_file(&self.key_path, key.as_slice())?;
+                info!("Generated secrets key at {}", self.key_path.display());
+                key
+            }
+            Err(e) => return Err(format!("Failed to read {}: {}", self.key_path.display(), e)),
+        };
+        Ok(ChaCha20Poly1305::new(&key))
+    }
+
+    fn load(&self) -> Result<HashMap<String, String>, String> {
+        let bytes = match std::fs::read(&self.data_path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read {}: {}",
+                    self.data_path.display(),
+                    e
+                ))
+            }
+        };
+        if bytes.len() < NONCE_LEN {
+            return Err(format!("{} is corrupt", self.data_path.display()));
+        }
+
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher()?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| format!("Failed to decrypt {}", self.data_path.display()))?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Failed to parse {}: {}", self.data_path.display(), e))
+    }
+
+    fn save(&self, secrets: &HashMap<String, String>) -> Result<(), String> {
+        let plaintext = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()?
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| "Failed to encrypt secrets".to_string())?;
+
+        let mut bytes = nonce.to_vec();
+        bytes.extend_from_slice(&ciphertext);
+        write_private_file(&self.data_path, &bytes)
+    }
+}
+
+/// Writes `bytes` through a temporary file, readable only by the current user.
+/// The file is created with its final permissions, so the key is never
+/// readable by others, not even briefly.
+fn write_private_file(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    // A leftover from an interrupted write may have other permissions
+    let tmp_path = path.with_extension("tmp");
+    let _ = std::fs::remove_file(&tmp_path);
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(&tmp_path)
+        .map_err(|e| format!("Failed to create {}: {}", tmp_path.display(), e))?;
+    file.write_all(bytes)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    drop(file);
+
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+}
+
+impl SecretStore {
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        if let Some(value) = self.cache.lock().unwrap().get(name) {
+            return Ok(Some(value.clone()));
+        }
+
+        let value = match &self.backend {
+            Backend::Keyring { service } => match keyring_entry(service, name)?.get_password() {
+                Ok(value) => Some(value),
+                Err(keyring::Error::NoEntry) => None,
+                Err(e) => return Err(format!("Failed to read '{}' from keyring: {}", name, e)),
+            },
+            Backend::File(vault) => vault.get(name)?,
+        };
+
+        if let Some(value) = &value {
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(name.to_string(), value.clone());
+        }
+        Ok(value)
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        match &self.backend {
+            Backend::Keyring { service } => keyring_entry(service, name)?
+                .set_password(value)
+                .map_err(|e| format!("Failed to store '{}' in keyring: {}", name, e))?,
+            Backend::File(vault) => vault.set(name, value)?,
+        }
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn delete(&self, name: &str) -> Result<(), String> {
+        self.cache.lock().unwrap().remove(name);
+        match &self.backend {
+            Backend::Keyring { service } => match keyring_entry(service, name)?.delete_credential()
+            {
+                Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+                Err(e) => Err(format!("Failed to delete '{}' from keyring: {}", name, e)),
+            },
+            Backend::File(vault) => vault.delete(name),
+        }
+    }
+}
+
+fn keyring_entry(service: &str, name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(service, name).map_err(|e| format!("Keyring unavailable: {}", e))
+}
+
+/// Whether the OS keyring answers at all; a missing entry counts as working.
+fn keyring_available(service: &str) -> bool {
+    match keyring_entry(service, "availability-check").map(|entry| entry.get_password()) {
+        Ok(Ok(_)) | Ok(Err(keyring::Error::NoEntry)) => true,
+        Ok(Err(e)) => {
+            debug!("OS keyring not usable: {}", e);
+            false
+        }
+        Err(e) => {
+            debug!("{}", e);
+            false
+        }
+    }
+}
+
+/// Picks the secret backend and moves any API keys still stored in plain text
+/// out of the settings. Must run before anything reads API keys.
+pub fn init(app: &AppHandle) {
+    let service = app.config().identifier.clone();
+    let force_file = std::env::var(BACKEND_ENV).is_ok_and(|v| v == "file");
+
+    let backend = if !force_file && keyring_available(&service) {
+        info!("Storing secrets in the OS keyring");
+        Backend::Keyring { service }
+    } else {
+        let dir = app
+            .path()
+            .app_data_dir()
+            .expect("Failed to get app data directory");
+        info!("Storing secrets in encrypted file in {}", dir.display());
+        Backend::File(FileVault::new(&dir))
+    };
+
+    let _ = STORE.set(SecretStore {
+        backend,
+        cache: Mutex::new(HashMap::new()),
+    });
+
+    settings::update_settings(app, seal_plaintext_keys);
+}
+
+fn store() -> Result<&'static SecretStore, String> {
+    STORE
+        .get()
+        .ok_or_else(|| "Secret store not initialized".to_string())
+}
+
+fn secret_name(scope: &str, provider_id: &str) -> String {
+    format!("{}/{}", scope, provider_id)
+}
+
+fn secret_ref(name: &str) -> String {
+    format!("{}{}", SECRET_REF_PREFIX, name)
+}
+
+/// Moves plain-text API keys into the secret store, leaving references behind.
+/// Returns whether `settings` changed. Keys that fail to move are kept so they
+/// are not lost, and retried on the next start.
+fn seal_plaintext_keys(settings: &mut AppSettings) -> bool {
+    let mut changed = false;
+    for (scope, keys) in [
+        ("post_process", &mut settings.post_process_api_keys),
+        ("cloud_stt", &mut settings.cloud_stt_api_keys),
+    ] {
+        for (provider_id, value) in keys.iter_mut() {
+            if value.is_empty() || value.starts_with(SECRET_REF_PREFIX) {
+                continue;
+            }
+            let name = secret_name(scope, provider_id);
+            match store().and_then(|store| store.set(&name, value)) {
+                Ok(()) => {
+                    info!(
+                        "Moved {} API key for '{}' to secret store",
+                        scope, provider_id
+                    );
+                    *value = secret_ref(&name);
+                    changed = true;
+                }
+                Err(e) => warn!("Failed to move API key '{}': {}", name, e),
+            }
+        }
+    }
+    changed
+}
+
+/// Stores `api_key` and returns the reference to keep in settings. An empty
+/// key deletes the secret and returns an empty reference.
+pub fn store_api_key(scope: &str, provider_id: &str, api_key: &str) -> Result<String, String> {
+    let name = secret_name(scope, provider_id);
+    let store = store()?;
+    if api_key.is_empty() {
+        store.delete(&name)?;
+        return Ok(String::new());
+    }
+    store.set(&name, api_key)?;
+    Ok(secret_ref(&name))
+}
+
+/// Resolves a settings value to the API key it refers to. Values that are not
+/// references (not migrated yet) are returned as-is.
+pub fn resolve(value: &str) -> String {
+    let Some(name) = value.strip_prefix(SECRET_REF_PREFIX) else {
+        return value.to_string();
+    };
+    match store().and_then(|store| store.get(name)) {
+        Ok(Some(secret)) => secret,
+        Ok(None) => {
+            warn!("Secret '{}' is missing from the secret store", name);
+            String::new()
+        }
+        Err(e) => {
+            warn!("Failed to resolve secret '{}': {}", name, e);
+            String::new()
+        }
+    }
+}
+
+/// Looks up and resolves the API key for `provider_id` in one of the key maps.
+pub fn resolve_api_key(keys: &HashMap<String, String>, provider_id: &str) -> String {
+    keys.get(provider_id)
+        .map(String::as_str)
+        .map(resolve)
+        .unwrap_or_default()
+}
+
+/// Hides all but the last four characters of a secret.
+pub fn mask(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.is_empty() {
+        return String::new();
+    }
+    if chars.len() <= 8 {
+        return "•".repeat(8);
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}{}", "•".repeat(8), tail)
+}
+
+/// Masks every key in a key map for display.
+pub fn mask_api_keys(keys: &HashMap<String, String>) -> HashMap<String, String> {
+    keys.iter()
+        .map(|(provider_id, value)| (provider_id.clone(), mask(&resolve(value))))
+        .collect()
+}
+
+/// Whether `value` is just the masked form of the key already stored, as
+/// returned to the frontend, rather than a newly entered key.
+pub fn is_unchanged_mask(keys: &HashMap<String, String>, provider_id: &str, value: &str) -> bool {
+    !value.is_empty() && mask(&resolve_api_key(keys, provider_id)) == value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("handy-secrets-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn file_vault_round_trips_encrypted() {
+        let dir = temp_dir("round-trip");
+        let vault = FileVault::new(&dir);
+
+        vault.set("cloud_stt/openai", "sk-test-1234").unwrap();
+        assert_eq!(
+            vault.get("cloud_stt/openai").unwrap(),
+            Some("sk-test-1234".to_string())
+        );
+
+        let raw = std::fs::read(dir.join(SECRETS_FILE)).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("sk-test-1234"));
+
+        vault.delete("cloud_stt/openai").unwrap();
+        assert_eq!(vault.get("cloud_stt/openai").unwrap(), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_vault_keeps_concurrent_writes() {
+        let dir = temp_dir("concurrent");
+        let vault = std::sync::Arc::new(FileVault::new(&dir));
+
+        let writers: Vec<_> = (0..8)
+            .map(|i| {
+                let vault = std::sync::Arc::clone(&vault);
+                std::thread::spawn(move || {
+                    vault.set(&format!("provider_{}", i), "sk-test").unwrap()
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        for i in 0..8 {
+            assert!(vault.get(&format!("provider_{}", i)).unwrap().is_some());
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn file_vault_rejects_foreign_key() {
+        let dir = temp_dir("foreign-key");
+        let vault = FileVault::new(&dir);
+        vault.set("post_process/openai", "sk-secret").unwrap();
+
+        std::fs::write(dir.join(KEY_FILE), [7u8; 32]).unwrap();
+        assert!(vault.get("post_process/openai").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_files_are_never_readable_by_others() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("private-file");
+        let path = dir.join(KEY_FILE);
+        // A stale temporary file with open permissions must not be reused
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(path.with_extension("tmp"), b"stale").unwrap();
+        std::fs::set_permissions(
+            path.with_extension("tmp"),
+            std::fs::Permissions::from_mode(0o644),
+        )
+        .unwrap();
+
+        write_private_file(&path, &[1u8; 32]).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(std::fs::read(&path).unwrap(), [1u8; 32]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn masks_all_but_last_four_characters() {
+        assert_eq!(mask(""), "");
+        assert_eq!(mask("short"), "••••••••");
+        assert_eq!(mask("sk-abcdefgh1234"), "••••••••1234");
+    }
+}
@@ -1,11 +1,15 @@
-use log::{debug, warn};
+use log::{debug, info, warn};
 use serde::de::{self, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
 use specta::Type;
 use std::collections::HashMap;
-use tauri::AppHandle;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
 use tauri_plugin_store::StoreExt;
 
+use crate::managers::settings::SettingsManager;
+use crate::settings_migration;
+
 pub const APPLE_INTELLIGENCE_PROVIDER_ID: &str = "apple_intelligence";
 pub const APPLE_INTELLIGENCE_DEFAULT_MODEL_ID: &str = "Apple Intelligence";
 
@@ -103,6 +107,295 @@ pub struct PostProcessProvider {
     pub models_endpoint: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ChineseConversion {
+    ToSimplified,
+    ToTraditional,
+}
+
+/// A single step of the post-processing pipeline. Each kind receives the
+/// output of the previous stage and produces the input for the next one.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PostProcessStageKind {
+    FilterOutput,
+    CustomWords,
+    #[serde(rename = "opencc")]
+    OpenCC {
+        conversion: ChineseConversion,
+    },
+    RegexReplace {
+        pattern: String,
+        replacement: String,
+    },
+    LlmPrompt {
+        prompt_id: String,
+        /// Falls back to `post_process_provider_id` when unset
+        #[serde(default)]
+        provider_id: Option<String>,
+        /// Falls back to the provider's entry in `post_process_models` when unset
+        #[serde(default)]
+        model: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct PostProcessStage {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_stage_enabled")]
+    pub enabled: bool,
+    pub kind: PostProcessStageKind,
+}
+
+/// Where an output sink delivers the final text.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputSinkKind {
+    /// `path` may contain `${date}` and a leading `~/`
+    AppendToFile { path: String },
+    /// Runs through the platform shell with the payload on stdin
+    RunCommand { command: String },
+    /// POSTs the payload with a JSON content type
+    Webhook { url: String },
+}
+
+/// An extra destination for the final text, run after pasting. Templates may use
+/// `${text}`, `${text_json}`, `${timestamp}` and `${date}`.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct OutputSink {
+    pub id: String,
+    pub name: String,
+    #[serde(default = "default_stage_enabled")]
+    pub enabled: bool,
+    pub kind: OutputSinkKind,
+    /// Uses a default for the sink kind when empty
+    #[serde(default)]
+    pub template: String,
+}
+
+impl OutputSink {
+    pub fn effective_template(&self) -> &str {
+        if !self.template.is_empty() {
+            return &self.template;
+        }
+        match self.kind {
+            OutputSinkKind::AppendToFile { .. } => "${text}\n",
+            OutputSinkKind::RunCommand { .. } => "${text}",
+            OutputSinkKind::Webhook { .. } => {
+                "{\"text\": ${text_json}, \"timestamp\": \"${timestamp}\"}"
+            }
+        }
+    }
+}
+
+/// Overrides applied while a matching application has focus. Fields left unset
+/// keep the global value.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct AppRule {
+    pub id: String,
+    pub name: String,
+    /// Case-insensitive substring of the focused application's identifier
+    /// (X11 `WM_CLASS`, macOS bundle id or Windows executable name)
+    pub app_match: String,
+    #[serde(default)]
+    pub paste_method: Option<PasteMethod>,
+    #[serde(default)]
+    pub append_trailing_space: Option<bool>,
+    #[serde(default)]
+    pub typing_shift_enter_newlines: Option<bool>,
+    #[serde(default)]
+    pub post_process_enabled: Option<bool>,
+    /// Replaces the prompt of the first LLM stage, or the selected prompt
+    /// when there is no pipeline
+    #[serde(default)]
+    pub prompt_id: Option<String>,
+}
+
+impl AppRule {
+    pub fn matches(&self, app_id: &str) -> bool {
+        let pattern = self.app_match.trim().to_lowercase();
+        !pattern.is_empty() && app_id.to_lowercase().contains(&pattern)
+    }
+}
+
+/// When a microphone rule applies.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum MicrophoneCondition {
+    #[default]
+    Always,
+    /// Only while a laptop lid is closed with an external display attached
+    Clamshell,
+}
+
+/// One entry of the microphone priority list. The first rule whose condition
+/// holds and whose pattern matches a connected device picks the microphone.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Type)]
+pub struct MicrophoneRule {
+    /// Device name, case-insensitive; `*` matches any run of characters
+    pub pattern: String,
+    #[serde(default)]
+    pub condition: MicrophoneCondition,
+}
+
+impl MicrophoneRule {
+    pub fn matches(&self, device_name: &str) -> bool {
+        wildcard_match(
+            &self.pattern.trim().to_lowercase(),
+            &device_name.to_lowercase(),
+        )
+    }
+}
+
+/// How a multi-channel microphone is mixed down to mono.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum MicrophoneChannel {
+    #[default]
+    Average,
+    /// Zero-based input channel
+    Channel(u16),
+    /// The channel with the most energy, e.g. whichever input a person speaks into
+    Loudest,
+}
+
+/// What gets recorded.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioSource {
+    #[default]
+    Microphone,
+    /// What is playing on the machine, through a PulseAudio/PipeWire monitor
+    SystemAudio,
+    /// Microphone and system audio mixed together, e.g. for calls
+    MicrophoneAndSystemAudio,
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard, exact match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// The subset of `AppSettings` captured by a profile. API keys are deliberately
+/// not part of it, so profiles can be exported and shared.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct ProfileSettings {
+    pub selected_model: String,
+    pub selected_language: String,
+    #[serde(default)]
+    pub translate_to_english: bool,
+    #[serde(default)]
+    pub custom_words: Vec<String>,
+    #[serde(default = "default_word_correction_threshold")]
+    pub word_correction_threshold: f64,
+    #[serde(default)]
+    pub paste_method: PasteMethod,
+    #[serde(default)]
+    pub append_trailing_space: bool,
+    #[serde(default)]
+    pub post_process_enabled: bool,
+    #[serde(default = "default_post_process_provider_id")]
+    pub post_process_provider_id: String,
+    #[serde(default)]
+    pub post_process_models: HashMap<String, String>,
+    #[serde(default)]
+    pub post_process_prompts: Vec<LLMPrompt>,
+    #[serde(default)]
+    pub post_process_selected_prompt_id: Option<String>,
+    #[serde(default)]
+    pub post_process_pipeline: Vec<PostProcessStage>,
+    #[serde(default)]
+    pub cloud_stt_enabled: bool,
+    #[serde(default)]
+    pub cloud_stt_provider: Option<String>,
+    #[serde(default)]
+    pub cloud_stt_models: HashMap<String, String>,
+}
+
+impl ProfileSettings {
+    pub fn capture(settings: &AppSettings) -> Self {
+        ProfileSettings {
+            selected_model: settings.selected_model.clone(),
+            selected_language: settings.selected_language.clone(),
+            translate_to_english: settings.translate_to_english,
+            custom_words: settings.custom_words.clone(),
+            word_correction_threshold: settings.word_correction_threshold,
+            paste_method: settings.paste_method,
+            append_trailing_space: settings.append_trailing_space,
+            post_process_enabled: settings.post_process_enabled,
+            post_process_provider_id: settings.post_process_provider_id.clone(),
+            post_process_models: settings.post_process_models.clone(),
+            post_process_prompts: settings.post_process_prompts.clone(),
+            post_process_selected_prompt_id: settings.post_process_selected_prompt_id.clone(),
+            post_process_pipeline: settings.post_process_pipeline.clone(),
+            cloud_stt_enabled: settings.cloud_stt_enabled,
+            cloud_stt_provider: settings.cloud_stt_provider.clone(),
+            cloud_stt_models: settings.cloud_stt_models.clone(),
+        }
+    }
+
+    /// Writes the profile into `settings`. A post-processing provider that
+    /// doesn't exist on this machine keeps the current one.
+    pub fn apply_to(&self, settings: &mut AppSettings) {
+        settings.selected_model = self.selected_model.clone();
+        settings.selected_language = self.selected_language.clone();
+        settings.translate_to_english = self.translate_to_english;
+        settings.custom_words = self.custom_words.clone();
+        settings.word_correction_threshold = self.word_correction_threshold;
+        settings.paste_method = self.paste_method;
+        settings.append_trailing_space = self.append_trailing_space;
+        settings.post_process_enabled = self.post_process_enabled;
+        if settings
+            .post_process_provider(&self.post_process_provider_id)
+            .is_some()
+        {
+            settings.post_process_provider_id = self.post_process_provider_id.clone();
+        } else {
+            warn!(
+                "Profile provider '{}' not found, keeping '{}'",
+                self.post_process_provider_id, settings.post_process_provider_id
+            );
+        }
+        settings
+            .post_process_models
+            .extend(self.post_process_models.clone());
+        settings.post_process_prompts = self.post_process_prompts.clone();
+        settings.post_process_selected_prompt_id = self.post_process_selected_prompt_id.clone();
+        settings.post_process_pipeline = self.post_process_pipeline.clone();
+        settings.cloud_stt_enabled = self.cloud_stt_enabled;
+        settings.cloud_stt_provider = self.cloud_stt_provider.clone();
+        settings
+            .cloud_stt_models
+            .extend(self.cloud_stt_models.clone());
+    }
+}
+
+/// A named snapshot of `ProfileSettings` the user can switch to.
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct SettingsProfile {
+    pub id: String,
+    pub name: String,
+    pub settings: ProfileSettings,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
 #[serde(rename_all = "lowercase")]
 pub enum OverlayPosition {
@@ -141,6 +434,24 @@ pub enum ClipboardHandling {
     CopyToClipboard,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseSuppression {
+    #[default]
+    Off,
+    Spectral,
+    Rnnoise,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingFormat {
+    #[default]
+    Wav,
+    Flac,
+    Opus,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
 #[serde(rename_all = "snake_case")]
 pub enum RecordingRetentionPeriod {
@@ -151,6 +462,31 @@ pub enum RecordingRetentionPeriod {
     Months3,
 }
 
+impl RecordingRetentionPeriod {
+    /// The same rule as a `RetentionPolicy`
+    pub fn as_policy(self, history_limit: usize) -> RetentionPolicy {
+        match self {
+            RecordingRetentionPeriod::Never => RetentionPolicy::Forever,
+            RecordingRetentionPeriod::PreserveLimit => RetentionPolicy::Latest(history_limit),
+            RecordingRetentionPeriod::Days3 => RetentionPolicy::Days(3),
+            RecordingRetentionPeriod::Weeks2 => RetentionPolicy::Days(14),
+            RecordingRetentionPeriod::Months3 => RetentionPolicy::Months(3),
+        }
+    }
+}
+
+/// How long unsaved history entries, or just their recordings, are kept.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionPolicy {
+    Forever,
+    /// Only the newest entries
+    Latest(usize),
+    Days(u32),
+    /// Calendar months
+    Months(u32),
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
 #[serde(rename_all = "snake_case")]
 pub enum KeyboardImplementation {
@@ -244,8 +580,21 @@ impl SoundTheme {
 /* still handy for composing the initial JSON in the store ------------- */
 #[derive(Serialize, Deserialize, Debug, Clone, Type)]
 pub struct AppSettings {
+    /// Schema version of the stored object, see `settings_migration`
+    #[serde(default)]
+    pub settings_version: u32,
     pub bindings: HashMap<String, ShortcutBinding>,
     pub push_to_talk: bool,
+    /// Tap to toggle, hold for push-to-talk; overrides `push_to_talk` for recording bindings
+    #[serde(default)]
+    pub gesture_mode_enabled: bool,
+    #[serde(default = "default_gesture_hold_threshold_ms")]
+    pub gesture_hold_threshold_ms: u64,
+    #[serde(default = "default_gesture_double_tap_window_ms")]
+    pub gesture_double_tap_window_ms: u64,
+    /// `ACTION_MAP` entry run on double tap; `None` disables double tap
+    #[serde(default)]
+    pub gesture_double_tap_action: Option<String>,
     pub audio_feedback: bool,
     #[serde(default = "default_audio_feedback_volume")]
     pub audio_feedback_volume: f32,
@@ -261,16 +610,32 @@ pub struct AppSettings {
     pub selected_model: String,
     #[serde(default = "default_always_on_microphone")]
     pub always_on_microphone: bool,
+    /// Ordered microphone preferences, see `MicrophoneRule`
+    #[serde(default)]
+    pub microphone_priority: Vec<MicrophoneRule>,
+    /// Pattern of the first `Always` rule, mirrored for the single-device selector
     #[serde(default)]
     pub selected_microphone: Option<String>,
+    /// Pattern of the first `Clamshell` rule, mirrored for the single-device selector
     #[serde(default)]
     pub clamshell_microphone: Option<String>,
+    /// Channel selection per device name; devices not listed are averaged
+    #[serde(default)]
+    pub microphone_channels: HashMap<String, MicrophoneChannel>,
+    #[serde(default)]
+    pub audio_source: AudioSource,
+    /// Monitor source to record system audio from; `None` follows the default output
+    #[serde(default)]
+    pub system_audio_source: Option<String>,
     #[serde(default)]
     pub selected_output_device: Option<String>,
     #[serde(default = "default_translate_to_english")]
     pub translate_to_english: bool,
     #[serde(default = "default_selected_language")]
     pub selected_language: String,
+    /// Languages the "cycle language" shortcut steps through, in order
+    #[serde(default)]
+    pub language_cycle: Vec<String>,
     #[serde(default = "default_overlay_position")]
     pub overlay_position: OverlayPosition,
     #[serde(default = "default_debug_mode")]
@@ -287,12 +652,32 @@ pub struct AppSettings {
     pub history_limit: usize,
     #[serde(default = "default_recording_retention_period")]
     pub recording_retention_period: RecordingRetentionPeriod,
+    /// How long recordings are kept; the transcript may outlive them
+    #[serde(default = "default_retention_policy")]
+    pub audio_retention: RetentionPolicy,
+    /// How long history entries are kept, recording included
+    #[serde(default = "default_retention_policy")]
+    pub text_retention: RetentionPolicy,
+    /// Oldest unsaved recordings are removed once all of them take up more
+    #[serde(default)]
+    pub recordings_quota_mb: Option<u64>,
+    /// File format new recordings are stored in
+    #[serde(default)]
+    pub recording_format: RecordingFormat,
+    #[serde(default = "default_recording_opus_bitrate_kbps")]
+    pub recording_opus_bitrate_kbps: u32,
+    /// Folder for meeting transcripts; `None` uses `meetings` in the app data dir
+    #[serde(default)]
+    pub meeting_notes_dir: Option<String>,
     #[serde(default)]
     pub paste_method: PasteMethod,
     #[serde(default)]
     pub clipboard_handling: ClipboardHandling,
     #[serde(default = "default_post_process_enabled")]
     pub post_process_enabled: bool,
+    /// Name new history entries with the post-processing LLM
+    #[serde(default)]
+    pub history_auto_title: bool,
     #[serde(default = "default_post_process_provider_id")]
     pub post_process_provider_id: String,
     #[serde(default = "default_post_process_providers")]
@@ -306,9 +691,45 @@ pub struct AppSettings {
     #[serde(default)]
     pub post_process_selected_prompt_id: Option<String>,
     #[serde(default)]
+    pub post_process_pipeline: Vec<PostProcessStage>,
+    #[serde(default = "default_edit_selection_prompt")]
+    pub edit_selection_prompt: String,
+    #[serde(default)]
+    pub app_rules: Vec<AppRule>,
+    #[serde(default)]
+    pub output_sinks: Vec<OutputSink>,
+    #[serde(default)]
+    pub profiles: Vec<SettingsProfile>,
+    /// The profile applied last; settings may have been changed since
+    #[serde(default)]
+    pub active_profile_id: Option<String>,
+    #[serde(default)]
     pub mute_while_recording: bool,
+    /// Remove low-frequency rumble from the microphone signal
+    #[serde(default)]
+    pub audio_high_pass_filter: bool,
+    /// Even out quiet and loud microphones while recording
+    #[serde(default)]
+    pub audio_gain_control: bool,
+    /// Remove steady background noise before VAD and transcription
+    #[serde(default)]
+    pub audio_noise_suppression: NoiseSuppression,
+    /// Bring every finished recording to the same loudness
+    #[serde(default)]
+    pub audio_normalize_loudness: bool,
     #[serde(default)]
     pub append_trailing_space: bool,
+    /// Pause around clipboard writes and paste keystrokes
+    #[serde(default = "default_clipboard_delay_ms")]
+    pub clipboard_delay_ms: u64,
+    /// Characters per chunk for direct typing; 0 types everything at once
+    #[serde(default)]
+    pub typing_chunk_size: usize,
+    #[serde(default = "default_typing_chunk_delay_ms")]
+    pub typing_chunk_delay_ms: u64,
+    /// Type line breaks as Shift+Enter instead of Enter during chunked typing
+    #[serde(default)]
+    pub typing_shift_enter_newlines: bool,
     #[serde(default = "default_app_language")]
     pub app_language: String,
     #[serde(default)]
@@ -380,6 +801,22 @@ fn default_recording_retention_period() -> RecordingRetentionPeriod {
     RecordingRetentionPeriod::PreserveLimit
 }
 
+fn default_retention_policy() -> RetentionPolicy {
+    default_recording_retention_period().as_policy(default_history_limit())
+}
+
+fn default_recording_opus_bitrate_kbps() -> u32 {
+    24
+}
+
+fn default_gesture_hold_threshold_ms() -> u64 {
+    350
+}
+
+fn default_gesture_double_tap_window_ms() -> u64 {
+    300
+}
+
 fn default_audio_feedback_volume() -> f32 {
     1.0
 }
@@ -392,6 +829,10 @@ fn default_post_process_enabled() -> bool {
     false
 }
 
+fn default_stage_enabled() -> bool {
+    true
+}
+
 fn default_app_language() -> String {
     tauri_plugin_os::locale()
         .and_then(|l| l.split(['-', '_']).next().map(String::from))
@@ -502,6 +943,18 @@ fn default_post_process_prompts() -> Vec<LLMPrompt> {
     }]
 }
 
+fn default_edit_selection_prompt() -> String {
+    "Apply the following instruction to the text below.\n\nInstruction: ${instruction}\n\nReturn only the resulting text, without quotes, explanations or commentary.\n\nText:\n${selection}".to_string()
+}
+
+fn default_clipboard_delay_ms() -> u64 {
+    50
+}
+
+fn default_typing_chunk_delay_ms() -> u64 {
+    20
+}
+
 fn default_cloud_stt_enabled() -> bool {
     false
 }
@@ -579,6 +1032,71 @@ pub fn get_default_settings() -> AppSettings {
             current_binding: default_shortcut.to_string(),
         },
     );
+    bindings.insert(
+        "edit_selection".to_string(),
+        ShortcutBinding {
+            id: "edit_selection".to_string(),
+            name: "Edit Selection".to_string(),
+            description: "Rewrites the selected text using a spoken instruction.".to_string(),
+            // Unbound, a new default would take the keys from other apps
+            default_binding: String::new(),
+            current_binding: String::new(),
+        },
+    );
+    bindings.insert(
+        "meeting".to_string(),
+        ShortcutBinding {
+            id: "meeting".to_string(),
+            name: "Meeting".to_string(),
+            description: "Starts or stops transcribing a long meeting to a document.".to_string(),
+            default_binding: String::new(),
+            current_binding: String::new(),
+        },
+    );
+    // Quick actions ship unbound; the user assigns keys in settings
+    for (id, name, description) in [
+        (
+            "paste_last_transcript",
+            "Paste Last Transcript",
+            "Pastes the most recent transcription again.",
+        ),
+        (
+            "undo_last_paste",
+            "Undo Last Paste",
+            "Deletes the text inserted by the last paste.",
+        ),
+        (
+            "cycle_language",
+            "Cycle Language",
+            "Switches to the next language in your language list.",
+        ),
+        (
+            "toggle_post_processing",
+            "Toggle Post-Processing",
+            "Turns post-processing on or off.",
+        ),
+        (
+            "cycle_post_process_prompt",
+            "Cycle Post-Processing Prompt",
+            "Switches to the next post-processing prompt.",
+        ),
+        (
+            "cycle_profile",
+            "Switch Profile",
+            "Switches to the next settings profile.",
+        ),
+    ] {
+        bindings.insert(
+            id.to_string(),
+            ShortcutBinding {
+                id: id.to_string(),
+                name: name.to_string(),
+                description: description.to_string(),
+                default_binding: String::new(),
+                current_binding: String::new(),
+            },
+        );
+    }
     bindings.insert(
         "cancel".to_string(),
         ShortcutBinding {
@@ -591,8 +1109,13 @@ pub fn get_default_settings() -> AppSettings {
     );
 
     AppSettings {
+        settings_version: settings_migration::current_version(),
         bindings,
         push_to_talk: true,
+        gesture_mode_enabled: false,
+        gesture_hold_threshold_ms: default_gesture_hold_threshold_ms(),
+        gesture_double_tap_window_ms: default_gesture_double_tap_window_ms(),
+        gesture_double_tap_action: None,
         audio_feedback: false,
         audio_feedback_volume: default_audio_feedback_volume(),
         sound_theme: default_sound_theme(),
@@ -601,11 +1124,16 @@ pub fn get_default_settings() -> AppSettings {
         update_checks_enabled: default_update_checks_enabled(),
         selected_model: "".to_string(),
         always_on_microphone: false,
+        microphone_priority: Vec::new(),
         selected_microphone: None,
         clamshell_microphone: None,
+        microphone_channels: HashMap::new(),
+        audio_source: AudioSource::default(),
+        system_audio_source: None,
         selected_output_device: None,
         translate_to_english: false,
         selected_language: "auto".to_string(),
+        language_cycle: Vec::new(),
         overlay_position: default_overlay_position(),
         debug_mode: false,
         log_level: default_log_level(),
@@ -614,17 +1142,38 @@ pub fn get_default_settings() -> AppSettings {
         word_correction_threshold: default_word_correction_threshold(),
         history_limit: default_history_limit(),
         recording_retention_period: default_recording_retention_period(),
+        audio_retention: default_retention_policy(),
+        text_retention: default_retention_policy(),
+        recordings_quota_mb: None,
+        recording_format: RecordingFormat::default(),
+        recording_opus_bitrate_kbps: default_recording_opus_bitrate_kbps(),
+        meeting_notes_dir: None,
         paste_method: PasteMethod::default(),
         clipboard_handling: ClipboardHandling::default(),
         post_process_enabled: default_post_process_enabled(),
+        history_auto_title: false,
         post_process_provider_id: default_post_process_provider_id(),
         post_process_providers: default_post_process_providers(),
         post_process_api_keys: default_post_process_api_keys(),
         post_process_models: default_post_process_models(),
         post_process_prompts: default_post_process_prompts(),
         post_process_selected_prompt_id: None,
+        post_process_pipeline: Vec::new(),
+        edit_selection_prompt: default_edit_selection_prompt(),
+        app_rules: Vec::new(),
+        output_sinks: Vec::new(),
+        profiles: Vec::new(),
+        active_profile_id: None,
         mute_while_recording: false,
+        audio_high_pass_filter: false,
+        audio_gain_control: false,
+        audio_noise_suppression: NoiseSuppression::default(),
+        audio_normalize_loudness: false,
         append_trailing_space: false,
+        clipboard_delay_ms: default_clipboard_delay_ms(),
+        typing_chunk_size: 0,
+        typing_chunk_delay_ms: default_typing_chunk_delay_ms(),
+        typing_shift_enter_newlines: false,
         app_language: default_app_language(),
         experimental_enabled: false,
         keyboard_implementation: KeyboardImplementation::default(),
@@ -648,6 +1197,159 @@ impl AppSettings {
             .find(|provider| provider.id == provider_id)
     }
 
+    /// Returns the stages that should run for a new transcription.
+    ///
+    /// An empty `post_process_pipeline` keeps the behaviour from before pipelines
+    /// existed: OpenCC conversion derived from `selected_language`, followed by
+    /// the selected prompt on the active provider.
+    pub fn effective_post_process_pipeline(&self) -> Vec<PostProcessStage> {
+        if !self.post_process_pipeline.is_empty() {
+            return self.post_process_pipeline.clone();
+        }
+
+        let mut stages = Vec::new();
+
+        let conversion = match self.selected_language.as_str() {
+            "zh-Hans" => Some(ChineseConversion::ToSimplified),
+            "zh-Hant" => Some(ChineseConversion::ToTraditional),
+            _ => None,
+        };
+        if let Some(conversion) = conversion {
+            stages.push(PostProcessStage {
+                id: "legacy_opencc".to_string(),
+                name: "Chinese conversion".to_string(),
+                enabled: true,
+                kind: PostProcessStageKind::OpenCC { conversion },
+            });
+        }
+
+        if let Some(prompt_id) = &self.post_process_selected_prompt_id {
+            stages.push(PostProcessStage {
+                id: "legacy_llm_prompt".to_string(),
+                name: "LLM prompt".to_string(),
+                enabled: true,
+                kind: PostProcessStageKind::LlmPrompt {
+                    prompt_id: prompt_id.clone(),
+                    provider_id: None,
+                    model: None,
+                },
+            });
+        }
+
+        stages
+    }
+
+    /// Returns a copy of these settings with the first rule matching `app_id`
+    /// applied. Without an app id or a matching rule the copy is unchanged.
+    pub fn with_app_overrides(&self, app_id: Option<&str>) -> AppSettings {
+        let mut settings = self.clone();
+        let Some(rule) = app_id.and_then(|id| self.app_rules.iter().find(|r| r.matches(id))) else {
+            return settings;
+        };
+
+        debug!("Applying app rule '{}' for {:?}", rule.name, app_id);
+
+        if let Some(paste_method) = rule.paste_method {
+            settings.paste_method = paste_method;
+        }
+        if let Some(append_trailing_space) = rule.append_trailing_space {
+            settings.append_trailing_space = append_trailing_space;
+        }
+        if let Some(shift_enter) = rule.typing_shift_enter_newlines {
+            settings.typing_shift_enter_newlines = shift_enter;
+        }
+        if let Some(enabled) = rule.post_process_enabled {
+            settings.post_process_enabled = enabled;
+        }
+        if let Some(prompt_id) = &rule.prompt_id {
+            settings.post_process_selected_prompt_id = Some(prompt_id.clone());
+            // Later LLM stages usually refine the first one's output, so they
+            // keep their own prompts
+            let first_llm_prompt =
+                settings
+                    .post_process_pipeline
+                    .iter_mut()
+                    .find_map(|stage| match &mut stage.kind {
+                        PostProcessStageKind::LlmPrompt {
+                            prompt_id: stage_prompt,
+                            ..
+                        } => Some(stage_prompt),
+                        _ => None,
+                    });
+            if let Some(stage_prompt) = first_llm_prompt {
+                *stage_prompt = prompt_id.clone();
+            }
+        }
+
+        settings
+    }
+
+    /// Applies the legacy `recording_retention_period` and `history_limit`.
+    /// Audio and text policies that still match the old legacy rule follow
+    /// along; ones set independently are left alone.
+    pub fn set_legacy_retention(&mut self, period: RecordingRetentionPeriod, history_limit: usize) {
+        let previous = self
+            .recording_retention_period
+            .as_policy(self.history_limit);
+        let policy = period.as_policy(history_limit);
+        self.recording_retention_period = period;
+        self.history_limit = history_limit;
+
+        if self.audio_retention == previous {
+            self.audio_retention = policy;
+        }
+        if self.text_retention == previous {
+            self.text_retention = policy;
+        }
+    }
+
+    /// Makes `device_name` the top rule for `condition`, or removes every rule
+    /// for `condition` when `None` (use the system default).
+    pub fn set_top_microphone(
+        &mut self,
+        condition: MicrophoneCondition,
+        device_name: Option<String>,
+    ) {
+        let Some(device_name) = device_name else {
+            self.microphone_priority
+                .retain(|r| r.condition != condition);
+            self.sync_microphone_selectors();
+            return;
+        };
+
+        self.microphone_priority
+            .retain(|r| !(r.condition == condition && r.pattern == device_name));
+        // Clamshell rules come first so they win while the lid is closed
+        let index = self
+            .microphone_priority
+            .iter()
+            .position(|r| r.condition == condition)
+            .unwrap_or(match condition {
+                MicrophoneCondition::Clamshell => 0,
+                MicrophoneCondition::Always => self.microphone_priority.len(),
+            });
+        self.microphone_priority.insert(
+            index,
+            MicrophoneRule {
+                pattern: device_name,
+                condition,
+            },
+        );
+        self.sync_microphone_selectors();
+    }
+
+    /// Updates `selected_microphone` and `clamshell_microphone` from the rules.
+    pub fn sync_microphone_selectors(&mut self) {
+        let top = |condition| {
+            self.microphone_priority
+                .iter()
+                .find(|r| r.condition == condition)
+                .map(|r| r.pattern.clone())
+        };
+        self.selected_microphone = top(MicrophoneCondition::Always);
+        self.clamshell_microphone = top(MicrophoneCondition::Clamshell);
+    }
+
     pub fn post_process_provider_mut(
         &mut self,
         provider_id: &str,
@@ -659,72 +1361,46 @@ impl AppSettings {
 }
 
 pub fn load_or_create_app_settings(app: &AppHandle) -> AppSettings {
-    // Initialize store
     let store = app
         .store(SETTINGS_STORE_PATH)
         .expect("Failed to initialize store");
 
-    let mut settings = if let Some(settings_value) = store.get("settings") {
-        // Parse the entire settings object
-        match serde_json::from_value::<AppSettings>(settings_value) {
-            Ok(mut settings) => {
-                debug!("Found existing settings: {:?}", settings);
-                let default_settings = get_default_settings();
-                let mut updated = false;
-
-                // Merge default bindings into existing settings
-                for (key, value) in default_settings.bindings {
-                    if !settings.bindings.contains_key(&key) {
-                        debug!("Adding missing binding: {}", key);
-                        settings.bindings.insert(key, value);
-                        updated = true;
-                    }
-                }
-
-                if updated {
-                    debug!("Settings updated with new bindings");
-                    store.set("settings", serde_json::to_value(&settings).unwrap());
-                }
+    let mut settings = read_settings(app, &store);
+    debug!("Loaded settings: {:?}", settings);
 
-                settings
-            }
-            Err(e) => {
-                warn!("Failed to parse settings: {}", e);
-                // Fall back to default settings if parsing fails
-                let default_settings = get_default_settings();
-                store.set("settings", serde_json::to_value(&default_settings).unwrap());
-                default_settings
-            }
+    // Merge default bindings into existing settings
+    let mut updated = false;
+    for (key, value) in get_default_settings().bindings {
+        if !settings.bindings.contains_key(&key) {
+            debug!("Adding missing binding: {}", key);
+            settings.bindings.insert(key, value);
+            updated = true;
         }
-    } else {
-        let default_settings = get_default_settings();
-        store.set("settings", serde_json::to_value(&default_settings).unwrap());
-        default_settings
-    };
+    }
 
     if ensure_post_process_defaults(&mut settings) {
+        updated = true;
+    }
+
+    if updated {
         store.set("settings", serde_json::to_value(&settings).unwrap());
     }
 
     settings
 }
 
+/// Returns the current settings. Served from the `SettingsManager` cache once
+/// it is managed; before that (early startup) the store is read directly.
 pub fn get_settings(app: &AppHandle) -> AppSettings {
+    if let Some(manager) = app.try_state::<Arc<SettingsManager>>() {
+        return manager.get();
+    }
+
     let store = app
         .store(SETTINGS_STORE_PATH)
         .expect("Failed to initialize store");
 
-    let mut settings = if let Some(settings_value) = store.get("settings") {
-        serde_json::from_value::<AppSettings>(settings_value).unwrap_or_else(|_| {
-            let default_settings = get_default_settings();
-            store.set("settings", serde_json::to_value(&default_settings).unwrap());
-            default_settings
-        })
-    } else {
-        let default_settings = get_default_settings();
-        store.set("settings", serde_json::to_value(&default_settings).unwrap());
-        default_settings
-    };
+    let mut settings = read_settings(app, &store);
 
     if ensure_post_process_defaults(&mut settings) {
         store.set("settings", serde_json::to_value(&settings).unwrap());
@@ -733,7 +1409,62 @@ pub fn get_settings(app: &AppHandle) -> AppSettings {
     settings
 }
 
+/// Reads the stored settings, migrating them to the current version and
+/// repairing invalid fields individually. The previous object is backed up
+/// before anything in it is rewritten.
+fn read_settings(app: &AppHandle, store: &tauri_plugin_store::Store<tauri::Wry>) -> AppSettings {
+    let Some(value) = store.get("settings") else {
+        let default_settings = get_default_settings();
+        store.set("settings", serde_json::to_value(&default_settings).unwrap());
+        return default_settings;
+    };
+
+    let outcome = settings_migration::upgrade(value.clone());
+    if outcome.changed() {
+        backup_settings(app, &value);
+        store.set("settings", serde_json::to_value(&outcome.settings).unwrap());
+    }
+    outcome.settings
+}
+
+/// Backs `value` up next to the settings store, without plain-text API keys.
+fn backup_settings(app: &AppHandle, value: &serde_json::Value) {
+    let dir = match app.path().app_data_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            warn!("Cannot back up settings, no app data directory: {}", e);
+            return;
+        }
+    };
+
+    match settings_migration::write_backup(&dir, value) {
+        Ok(path) => info!("Backed up previous settings to {}", path.display()),
+        Err(e) => warn!("Failed to back up settings to {}: {}", dir.display(), e),
+    }
+}
+
+/// Changes the settings in place and returns what `f` returns. The whole
+/// read-modify-write holds the settings lock, so writers running at the same
+/// time don't lose each other's changes. `f` must not read the settings.
+pub fn update_settings<R>(app: &AppHandle, f: impl FnOnce(&mut AppSettings) -> R) -> R {
+    if let Some(manager) = app.try_state::<Arc<SettingsManager>>() {
+        return manager.update(f);
+    }
+
+    let mut settings = get_settings(app);
+    let result = f(&mut settings);
+    write_settings(app, settings);
+    result
+}
+
+/// Replaces all settings at once. Prefer `update_settings` for changes, which
+/// can't overwrite a concurrent write.
 pub fn write_settings(app: &AppHandle, settings: AppSettings) {
+    if let Some(manager) = app.try_state::<Arc<SettingsManager>>() {
+        manager.replace(settings);
+        return;
+    }
+
     let store = app
         .store(SETTINGS_STORE_PATH)
         .expect("Failed to initialize store");
@@ -755,12 +1486,91 @@ pub fn get_stored_binding(app: &AppHandle, id: &str) -> ShortcutBinding {
     binding
 }
 
-pub fn get_history_limit(app: &AppHandle) -> usize {
-    let settings = get_settings(app);
-    settings.history_limit
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_retention_keeps_independent_policies() {
+        let mut settings = get_default_settings();
+        settings.set_legacy_retention(RecordingRetentionPeriod::PreserveLimit, 5);
+        assert_eq!(settings.audio_retention, RetentionPolicy::Latest(5));
+        assert_eq!(settings.text_retention, RetentionPolicy::Latest(5));
+
+        // Text set on its own no longer follows the legacy rule
+        settings.text_retention = RetentionPolicy::Forever;
+        settings.set_legacy_retention(RecordingRetentionPeriod::Days3, 5);
+        assert_eq!(settings.audio_retention, RetentionPolicy::Days(3));
+        assert_eq!(settings.text_retention, RetentionPolicy::Forever);
+
+        settings.set_legacy_retention(RecordingRetentionPeriod::Days3, 10);
+        assert_eq!(settings.history_limit, 10);
+        assert_eq!(settings.text_retention, RetentionPolicy::Forever);
+    }
 
-pub fn get_recording_retention_period(app: &AppHandle) -> RecordingRetentionPeriod {
-    let settings = get_settings(app);
-    settings.recording_retention_period
+    fn llm_stage(id: &str, prompt_id: &str) -> PostProcessStage {
+        PostProcessStage {
+            id: id.to_string(),
+            name: id.to_string(),
+            enabled: true,
+            kind: PostProcessStageKind::LlmPrompt {
+                prompt_id: prompt_id.to_string(),
+                provider_id: None,
+                model: None,
+            },
+        }
+    }
+
+    fn stage_prompt(stage: &PostProcessStage) -> &str {
+        match &stage.kind {
+            PostProcessStageKind::LlmPrompt { prompt_id, .. } => prompt_id,
+            _ => panic!("not an LLM stage"),
+        }
+    }
+
+    #[test]
+    fn app_rule_prompt_replaces_only_the_first_llm_stage() {
+        let mut settings = get_default_settings();
+        settings.post_process_pipeline = vec![
+            llm_stage("clean_up", "clean"),
+            llm_stage("translate", "german"),
+        ];
+        settings.app_rules = vec![AppRule {
+            id: "rule_1".to_string(),
+            name: "Slack".to_string(),
+            app_match: "slack".to_string(),
+            paste_method: None,
+            append_trailing_space: None,
+            typing_shift_enter_newlines: None,
+            post_process_enabled: None,
+            prompt_id: Some("casual".to_string()),
+        }];
+
+        let overridden = settings.with_app_overrides(Some("com.tinyspeck.slackmacgap"));
+        let prompts: Vec<&str> = overridden
+            .post_process_pipeline
+            .iter()
+            .map(stage_prompt)
+            .collect();
+        assert_eq!(prompts, vec!["casual", "german"]);
+
+        let untouched = settings.with_app_overrides(Some("firefox"));
+        assert_eq!(stage_prompt(&untouched.post_process_pipeline[0]), "clean");
+    }
+
+    #[test]
+    fn microphone_patterns_match_whole_names_case_insensitively() {
+        let rule = |pattern: &str| MicrophoneRule {
+            pattern: pattern.to_string(),
+            condition: MicrophoneCondition::Always,
+        };
+
+        let exact = rule("USB Mic");
+        assert!(exact.matches("usb mic"));
+        assert!(!exact.matches("USB Mic 2"));
+
+        let wildcard = rule("usb*2");
+        assert!(wildcard.matches("USB Mic 2"));
+        assert!(!wildcard.matches("USB Mic 3"));
+    }
 }
@@ -0,0 +1,578 @@
+//! Upgrading and repairing the stored settings object.
+//!
+//! The stored JSON carries a `settings_version`. Each entry in `MIGRATIONS`
+//! upgrades the raw object by one version, so stores written by any older
+//! release are walked forward step by step before being parsed.
+//!
+//! If the upgraded object still doesn't parse, fields are checked one at a time
+//! against the defaults and only the broken ones are replaced. Lists and maps
+//! lose just their invalid entries. Values that parse but make no sense (out of
+//! range numbers, dangling ids) are repaired afterwards by `repair_values`.
+//!
+//! Whatever is rewritten is first saved by `write_backup`, with any API keys
+//! not yet moved into the secret store blanked out.
+
+use log::{info, warn};
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::secrets::SECRET_REF_PREFIX;
+use crate::settings::{get_default_settings, AppSettings, RecordingRetentionPeriod};
+
+/// Settings fields holding API keys, in plain text until `secrets::init` runs
+const API_KEY_FIELDS: &[&str] = &["post_process_api_keys", "cloud_stt_api_keys"];
+
+const BACKUP_PREFIX: &str = "settings_backup_";
+/// Older backups are deleted once there are more than this many
+const MAX_BACKUPS: usize = 10;
+
+/// Numbers the backups written by this process
+static BACKUP_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+type Migration = fn(&mut Map<String, Value>);
+
+/// Migration `i` upgrades a store from version `i` to `i + 1`.
+/// Unversioned stores are version 0. Only ever append to this list.
+static MIGRATIONS: &[Migration] = &[
+    log_level_to_name,
+    microphone_priority_list,
+    independent_retention_policies,
+];
+
+/// v1: `log_level` used to be stored as a number from 1 (trace) to 5 (error).
+fn log_level_to_name(settings: &mut Map<String, Value>) {
+    if let Some(level) = settings.get("log_level").and_then(Value::as_u64) {
+        let name = match level {
+            1 => "trace",
+            2 => "debug",
+            3 => "info",
+            4 => "warn",
+            _ => "error",
+        };
+        settings.insert("log_level".to_string(), Value::from(name));
+    }
+}
+
+/// v2: the single selected and clamshell microphones become priority rules.
+fn microphone_priority_list(settings: &mut Map<String, Value>) {
+    let mut rules = Vec::new();
+    for (field, condition) in [
+        ("clamshell_microphone", "clamshell"),
+        ("selected_microphone", "always"),
+    ] {
+        if let Some(name) = settings.get(field).and_then(Value::as_str) {
+            rules.push(serde_json::json!({ "pattern": name, "condition": condition }));
+        }
+    }
+    settings.insert("microphone_priority".to_string(), Value::Array(rules));
+}
+
+/// v3: the shared retention period splits into audio and text policies, both
+/// starting out as the old rule.
+fn independent_retention_policies(settings: &mut Map<String, Value>) {
+    let defaults = get_default_settings();
+    let period = settings
+        .get("recording_retention_period")
+        .and_then(|v| serde_json::from_value::<RecordingRetentionPeriod>(v.clone()).ok())
+        .unwrap_or(defaults.recording_retention_period);
+    let limit = settings
+        .get("history_limit")
+        .and_then(Value::as_u64)
+        .map_or(defaults.history_limit, |limit| limit as usize);
+
+    let policy = serde_json::to_value(period.as_policy(limit)).expect("policy serializes");
+    settings.insert("audio_retention".to_string(), policy.clone());
+    settings.insert("text_retention".to_string(), policy);
+}
+
+/// The schema version written by this build.
+pub fn current_version() -> u32 {
+    MIGRATIONS.len() as u32
+}
+
+/// Result of bringing a stored settings object up to date.
+pub struct UpgradeOutcome {
+    pub settings: AppSettings,
+    /// Version the store had before migrating, if any migration ran
+    pub migrated_from: Option<u32>,
+    /// Fields replaced or trimmed because they were invalid
+    pub repaired: Vec<String>,
+}
+
+impl UpgradeOutcome {
+    /// Whether the stored object must be rewritten.
+    pub fn changed(&self) -> bool {
+        self.migrated_from.is_some() || !self.repaired.is_empty()
+    }
+}
+
+/// Migrates, parses and repairs a raw settings object.
+pub fn upgrade(value: Value) -> UpgradeOutcome {
+    let Value::Object(mut map) = value else {
+        warn!("Stored settings are not an object, using defaults");
+        return UpgradeOutcome {
+            settings: get_default_settings(),
+            migrated_from: None,
+            repaired: vec!["settings".to_string()],
+        };
+    };
+
+    let version = map
+        .get("settings_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u32;
+    let mut migrated_from = None;
+    if version < current_version() {
+        for migration in &MIGRATIONS[version as usize..] {
+            migration(&mut map);
+        }
+        info!(
+            "Migrated settings from version {} to {}",
+            version,
+            current_version()
+        );
+        migrated_from = Some(version);
+    } else if version > current_version() {
+        warn!(
+            "Settings were written by a newer version ({} > {}), unknown fields are ignored",
+            version,
+            current_version()
+        );
+    }
+    map.insert(
+        "settings_version".to_string(),
+        Value::from(version.max(current_version())),
+    );
+
+    let mut repaired = Vec::new();
+    let mut settings = match serde_json::from_value::<AppSettings>(Value::Object(map.clone())) {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!(
+                "Stored settings are invalid ({}), repairing field by field",
+                e
+            );
+            repair_fields(map, &mut repaired)
+        }
+    };
+    repair_values(&mut settings, &mut repaired);
+
+    if !repaired.is_empty() {
+        warn!("Repaired settings fields: {}", repaired.join(", "));
+    }
+
+    UpgradeOutcome {
+        settings,
+        migrated_from,
+        repaired,
+    }
+}
+
+/// Writes `value` to `dir` as `settings_backup_<timestamp>_<n>.json` and returns
+/// the path. API keys still stored in plain text are left empty, references
+/// into the secret store are kept. Only the newest `MAX_BACKUPS` are kept.
+pub fn write_backup(dir: &Path, value: &Value) -> Result<PathBuf, String> {
+    let mut value = value.clone();
+    if let Value::Object(map) = &mut value {
+        for field in API_KEY_FIELDS {
+            if let Some(Value::Object(keys)) = map.get_mut(*field) {
+                for key in keys.values_mut() {
+                    if !key
+                        .as_str()
+                        .is_some_and(|k| k.starts_with(SECRET_REF_PREFIX))
+                    {
+                        *key = Value::from("");
+                    }
+                }
+            }
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?;
+
+    // Milliseconds keep backups apart; the sequence orders writes within one
+    let stem = format!(
+        "{}{}",
+        BACKUP_PREFIX,
+        chrono::Local::now().format("%Y%m%d_%H%M%S%3f")
+    );
+    let path = loop {
+        let sequence = BACKUP_SEQUENCE.fetch_add(1, Ordering::Relaxed) % 1000;
+        let path = dir.join(format!("{}_{:03}.json", stem, sequence));
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                use std::io::Write;
+                file.write_all(json.as_bytes()).map_err(|e| e.to_string())?;
+                break path;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+    };
+
+    prune_backups(dir);
+    Ok(path)
+}
+
+/// Deletes all but the newest `MAX_BACKUPS` backups in `dir`. Their names
+/// sort in the order they were written.
+fn prune_backups(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut backups: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(".json"))
+        })
+        .collect();
+    if backups.len() <= MAX_BACKUPS {
+        return;
+    }
+
+    backups.sort();
+    for path in &backups[..backups.len() - MAX_BACKUPS] {
+        if let Err(e) = std::fs::remove_file(path) {
+            warn!(
+                "Failed to remove old settings backup {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Keeps every stored field that parses on top of the defaults and replaces
+/// the rest. Lists and maps are trimmed to their valid entries first.
+fn repair_fields(stored: Map<String, Value>, repaired: &mut Vec<String>) -> AppSettings {
+    let defaults = match serde_json::to_value(get_default_settings()) {
+        Ok(Value::Object(defaults)) => defaults,
+        _ => unreachable!("default settings serialize to an object"),
+    };
+
+    let mut merged = defaults.clone();
+    for (key, value) in stored {
+        if !defaults.contains_key(&key) {
+            // Fields from newer versions are kept as-is and ignored when parsing
+            merged.insert(key, value);
+            continue;
+        }
+
+        if field_is_valid(&defaults, &key, &value) {
+            merged.insert(key, value);
+            continue;
+        }
+
+        match keep_valid_entries(&defaults, &key, value) {
+            Some(trimmed) => {
+                merged.insert(key.clone(), trimmed);
+                repaired.push(format!("{} (invalid entries removed)", key));
+            }
+            None => repaired.push(key),
+        }
+    }
+
+    serde_json::from_value(Value::Object(merged)).unwrap_or_else(|e| {
+        warn!("Repaired settings still invalid ({}), using defaults", e);
+        repaired.push("settings".to_string());
+        get_default_settings()
+    })
+}
+
+/// Whether `value` parses as field `key` when every other field is a default.
+fn field_is_valid(defaults: &Map<String, Value>, key: &str, value: &Value) -> bool {
+    let mut candidate = defaults.clone();
+    candidate.insert(key.to_string(), value.clone());
+    serde_json::from_value::<AppSettings>(Value::Object(candidate)).is_ok()
+}
+
+/// For list and map fields, drops the entries that don't parse on their own.
+/// Returns `None` if the value isn't a collection or nothing usable is left.
+fn keep_valid_entries(defaults: &Map<String, Value>, key: &str, value: Value) -> Option<Value> {
+    let trimmed = match value {
+        Value::Array(items) => {
+            let valid: Vec<Value> = items
+                .into_iter()
+                .filter(|item| field_is_valid(defaults, key, &Value::Array(vec![item.clone()])))
+                .collect();
+            if valid.is_empty() {
+                return None;
+            }
+            Value::Array(valid)
+        }
+        Value::Object(entries) => {
+            // Start from the defaults so required entries (e.g. bindings) survive
+            let mut valid = match defaults.get(key) {
+                Some(Value::Object(default_entries)) => default_entries.clone(),
+                _ => Map::new(),
+            };
+            let mut kept_any = false;
+            for (entry_key, entry) in entries {
+                let mut single = Map::new();
+                single.insert(entry_key.clone(), entry.clone());
+                if field_is_valid(defaults, key, &Value::Object(single)) {
+                    valid.insert(entry_key, entry);
+                    kept_any = true;
+                }
+            }
+            if !kept_any {
+                return None;
+            }
+            Value::Object(valid)
+        }
+        _ => return None,
+    };
+
+    field_is_valid(defaults, key, &trimmed).then_some(trimmed)
+}
+
+/// Repairs values that parse but are out of range or point at missing items.
+fn repair_values(settings: &mut AppSettings, repaired: &mut Vec<String>) {
+    let defaults = get_default_settings();
+    let mut repair = |field: &str, invalid: bool| {
+        if invalid {
+            repaired.push(field.to_string());
+        }
+        invalid
+    };
+
+    if repair(
+        "audio_feedback_volume",
+        !(0.0..=1.0).contains(&settings.audio_feedback_volume),
+    ) {
+        settings.audio_feedback_volume = defaults.audio_feedback_volume;
+    }
+    if repair(
+        "word_correction_threshold",
+        !(0.0..=1.0).contains(&settings.word_correction_threshold),
+    ) {
+        settings.word_correction_threshold = defaults.word_correction_threshold;
+    }
+    if repair(
+        "clipboard_delay_ms",
+        !(10..=1000).contains(&settings.clipboard_delay_ms),
+    ) {
+        settings.clipboard_delay_ms = defaults.clipboard_delay_ms;
+    }
+    if repair(
+        "gesture_hold_threshold_ms",
+        settings.gesture_hold_threshold_ms == 0,
+    ) {
+        settings.gesture_hold_threshold_ms = defaults.gesture_hold_threshold_ms;
+    }
+    if repair(
+        "gesture_double_tap_window_ms",
+        settings.gesture_double_tap_window_ms == 0,
+    ) {
+        settings.gesture_double_tap_window_ms = defaults.gesture_double_tap_window_ms;
+    }
+
+    let prompt_missing = settings
+        .post_process_selected_prompt_id
+        .as_ref()
+        .is_some_and(|id| !settings.post_process_prompts.iter().any(|p| &p.id == id));
+    if repair("post_process_selected_prompt_id", prompt_missing) {
+        settings.post_process_selected_prompt_id = None;
+    }
+
+    let profile_missing = settings
+        .active_profile_id
+        .as_ref()
+        .is_some_and(|id| !settings.profiles.iter().any(|p| &p.id == id));
+    if repair("active_profile_id", profile_missing) {
+        settings.active_profile_id = None;
+    }
+
+    let double_tap_missing = settings
+        .gesture_double_tap_action
+        .as_ref()
+        .is_some_and(|id| !settings.bindings.contains_key(id));
+    if repair("gesture_double_tap_action", double_tap_missing) {
+        settings.gesture_double_tap_action = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{MicrophoneCondition, RetentionPolicy};
+    use serde_json::json;
+
+    fn stored_defaults() -> Map<String, Value> {
+        match serde_json::to_value(get_default_settings()).unwrap() {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn migrates_numeric_log_level() {
+        let mut stored = stored_defaults();
+        stored.remove("settings_version");
+        stored.insert("log_level".to_string(), json!(4));
+
+        let outcome = upgrade(Value::Object(stored));
+        assert_eq!(outcome.migrated_from, Some(0));
+        assert_eq!(outcome.settings.settings_version, current_version());
+        assert!(matches!(
+            outcome.settings.log_level,
+            crate::settings::LogLevel::Warn
+        ));
+    }
+
+    #[test]
+    fn turns_selected_microphones_into_rules() {
+        let mut stored = stored_defaults();
+        stored.insert("settings_version".to_string(), json!(1));
+        stored.insert("selected_microphone".to_string(), json!("USB Mic"));
+        stored.insert("clamshell_microphone".to_string(), json!("Dock Mic"));
+
+        let outcome = upgrade(Value::Object(stored));
+        let rules: Vec<(&str, MicrophoneCondition)> = outcome
+            .settings
+            .microphone_priority
+            .iter()
+            .map(|r| (r.pattern.as_str(), r.condition))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                ("Dock Mic", MicrophoneCondition::Clamshell),
+                ("USB Mic", MicrophoneCondition::Always)
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_retention_into_audio_and_text() {
+        let mut stored = stored_defaults();
+        stored.insert("settings_version".to_string(), json!(2));
+        stored.remove("audio_retention");
+        stored.remove("text_retention");
+        stored.insert("recording_retention_period".to_string(), json!("months3"));
+
+        let outcome = upgrade(Value::Object(stored));
+        assert_eq!(outcome.settings.audio_retention, RetentionPolicy::Months(3));
+        assert_eq!(outcome.settings.text_retention, RetentionPolicy::Months(3));
+
+        let mut stored = stored_defaults();
+        stored.insert("settings_version".to_string(), json!(2));
+        stored.insert(
+            "recording_retention_period".to_string(),
+            json!("preserve_limit"),
+        );
+        stored.insert("history_limit".to_string(), json!(20));
+
+        let outcome = upgrade(Value::Object(stored));
+        assert_eq!(outcome.settings.text_retention, RetentionPolicy::Latest(20));
+    }
+
+    #[test]
+    fn repairs_only_the_broken_field() {
+        let mut stored = stored_defaults();
+        stored.insert("push_to_talk".to_string(), json!("sometimes"));
+        stored.insert("custom_words".to_string(), json!(["Handy", "Tauri"]));
+
+        let outcome = upgrade(Value::Object(stored));
+        assert_eq!(outcome.repaired, vec!["push_to_talk".to_string()]);
+        assert_eq!(outcome.settings.custom_words, vec!["Handy", "Tauri"]);
+        assert!(outcome.settings.push_to_talk);
+    }
+
+    #[test]
+    fn drops_invalid_entries_but_keeps_the_rest() {
+        let mut stored = stored_defaults();
+        stored.insert(
+            "post_process_prompts".to_string(),
+            json!([
+                {"id": "ok", "name": "Fine", "prompt": "${output}"},
+                {"id": "broken"}
+            ]),
+        );
+
+        let outcome = upgrade(Value::Object(stored));
+        let ids: Vec<&str> = outcome
+            .settings
+            .post_process_prompts
+            .iter()
+            .map(|p| p.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["ok"]);
+        assert_eq!(
+            outcome.repaired,
+            vec!["post_process_prompts (invalid entries removed)".to_string()]
+        );
+    }
+
+    #[test]
+    fn clamps_out_of_range_values() {
+        let mut stored = stored_defaults();
+        stored.insert("audio_feedback_volume".to_string(), json!(7.5));
+
+        let outcome = upgrade(Value::Object(stored));
+        assert_eq!(outcome.settings.audio_feedback_volume, 1.0);
+        assert_eq!(outcome.repaired, vec!["audio_feedback_volume".to_string()]);
+    }
+
+    #[test]
+    fn backup_never_contains_plaintext_api_keys() {
+        let dir =
+            std::env::temp_dir().join(format!("handy-settings-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut stored = stored_defaults();
+        stored.insert(
+            "post_process_api_keys".to_string(),
+            json!({"openai": "sk-plain-post-process", "groq": "secret:post_process/groq"}),
+        );
+        stored.insert(
+            "cloud_stt_api_keys".to_string(),
+            json!({"openai": "sk-plain-cloud-stt"}),
+        );
+
+        let path = write_backup(&dir, &Value::Object(stored)).unwrap();
+        let backup = std::fs::read_to_string(&path).unwrap();
+        assert!(!backup.contains("sk-plain"));
+        let backup: Value = serde_json::from_str(&backup).unwrap();
+        assert_eq!(backup["post_process_api_keys"]["openai"], json!(""));
+        assert_eq!(
+            backup["post_process_api_keys"]["groq"],
+            json!("secret:post_process/groq")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn backups_get_unique_names_and_are_pruned() {
+        let dir = std::env::temp_dir().join(format!(
+            "handy-settings-backup-prune-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let paths: Vec<PathBuf> = (0..MAX_BACKUPS + 3)
+            .map(|_| write_backup(&dir, &Value::Object(stored_defaults())).unwrap())
+            .collect();
+
+        let unique: std::collections::HashSet<_> = paths.iter().collect();
+        assert_eq!(unique.len(), paths.len());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), MAX_BACKUPS);
+        assert!(paths.last().unwrap().exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn current_store_is_left_alone() {
+        let outcome = upgrade(Value::Object(stored_defaults()));
+        assert!(!outcome.changed());
+    }
+}
@@ -0,0 +1,236 @@
+//! Tap / hold / double-tap recognition for recording shortcuts
+//!
+//! In gesture mode a binding starts recording as soon as it is pressed. What
+//! happens next depends on how the key is released:
+//!
+//! - released before the hold threshold: a tap, recording keeps running hands-free
+//!   until the next press stops it
+//! - released after the hold threshold: push-to-talk, recording stops on release
+//! - pressed again within the double-tap window after a tap: the recording started
+//!   by the first tap is discarded and the double-tap action runs instead
+//!
+//! The recognizer is a pure state machine fed with timestamps, so it can be
+//! driven by synthetic press/release sequences in tests.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy)]
+pub struct GestureConfig {
+    pub hold_threshold: Duration,
+    /// `None` disables double-tap detection, so a second tap simply stops recording
+    pub double_tap_window: Option<Duration>,
+}
+
+/// What the shortcut handler should do in response to a key event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GestureAction {
+    Start,
+    Stop,
+    /// Discard the recording started by the first tap and run the double-tap action
+    DoubleTap,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum GestureState {
+    #[default]
+    Idle,
+    /// Key is down and recording has started; tap or hold is decided on release
+    Pressed { at: Instant },
+    /// A tap left recording running hands-free
+    Latched { released_at: Instant },
+    /// The gesture has fired; wait for the key to come back up
+    AwaitRelease,
+}
+
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    state: GestureState,
+    /// Whether the key is down. Kept across `reset`, so auto-repeat of a key
+    /// held through a cancel isn't taken for a new press.
+    held: bool,
+}
+
+impl GestureRecognizer {
+    /// Handles a key press. `is_active` reports whether the binding is still
+    /// recording, so a latched recording cancelled elsewhere starts afresh.
+    pub fn on_press(
+        &mut self,
+        now: Instant,
+        is_active: bool,
+        config: &GestureConfig,
+    ) -> Option<GestureAction> {
+        // Key repeat while held
+        if std::mem::replace(&mut self.held, true) {
+            return None;
+        }
+
+        match self.state {
+            GestureState::Idle => {
+                self.state = GestureState::Pressed { at: now };
+                Some(GestureAction::Start)
+            }
+            GestureState::Latched { .. } if !is_active => {
+                self.state = GestureState::Pressed { at: now };
+                Some(GestureAction::Start)
+            }
+            GestureState::Latched { released_at } => {
+                self.state = GestureState::AwaitRelease;
+                let is_double_tap = config
+                    .double_tap_window
+                    .is_some_and(|window| now.duration_since(released_at) <= window);
+                if is_double_tap {
+                    Some(GestureAction::DoubleTap)
+                } else {
+                    Some(GestureAction::Stop)
+                }
+            }
+            // Only after a missed release; wait for the next one
+            GestureState::Pressed { .. } | GestureState::AwaitRelease => None,
+        }
+    }
+
+    pub fn on_release(&mut self, now: Instant, config: &GestureConfig) -> Option<GestureAction> {
+        self.held = false;
+        match self.state {
+            GestureState::Pressed { at } if now.duration_since(at) >= config.hold_threshold => {
+                self.state = GestureState::Idle;
+                Some(GestureAction::Stop)
+            }
+            GestureState::Pressed { .. } => {
+                self.state = GestureState::Latched { released_at: now };
+                None
+            }
+            GestureState::AwaitRelease => {
+                self.state = GestureState::Idle;
+                None
+            }
+            GestureState::Idle | GestureState::Latched { .. } => None,
+        }
+    }
+
+    /// Forgets the gesture in progress, e.g. after the recording was cancelled.
+    pub fn reset(&mut self) {
+        self.state = GestureState::Idle;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: GestureConfig = GestureConfig {
+        hold_threshold: Duration::from_millis(300),
+        double_tap_window: Some(Duration::from_millis(250)),
+    };
+
+    /// Replays `(ms, pressed)` events and collects the resulting actions.
+    fn replay(events: &[(u64, bool)], config: &GestureConfig) -> Vec<GestureAction> {
+        let base = Instant::now();
+        let mut recognizer = GestureRecognizer::default();
+        let mut active = false;
+        let mut actions = Vec::new();
+
+        for &(ms, pressed) in events {
+            let now = base + Duration::from_millis(ms);
+            let action = if pressed {
+                recognizer.on_press(now, active, config)
+            } else {
+                recognizer.on_release(now, config)
+            };
+            if let Some(action) = action {
+                active = action == GestureAction::Start;
+                actions.push(action);
+            }
+        }
+        actions
+    }
+
+    #[test]
+    fn hold_acts_as_push_to_talk() {
+        assert_eq!(
+            replay(&[(0, true), (800, false)], &CONFIG),
+            vec![GestureAction::Start, GestureAction::Stop]
+        );
+    }
+
+    #[test]
+    fn tap_latches_until_next_press() {
+        assert_eq!(
+            replay(
+                &[(0, true), (100, false), (2000, true), (2100, false)],
+                &CONFIG
+            ),
+            vec![GestureAction::Start, GestureAction::Stop]
+        );
+    }
+
+    #[test]
+    fn double_tap_replaces_recording() {
+        assert_eq!(
+            replay(
+                &[(0, true), (80, false), (200, true), (260, false)],
+                &CONFIG
+            ),
+            vec![GestureAction::Start, GestureAction::DoubleTap]
+        );
+    }
+
+    #[test]
+    fn second_tap_stops_when_double_tap_disabled() {
+        let config = GestureConfig {
+            double_tap_window: None,
+            ..CONFIG
+        };
+        assert_eq!(
+            replay(
+                &[(0, true), (80, false), (200, true), (260, false)],
+                &config
+            ),
+            vec![GestureAction::Start, GestureAction::Stop]
+        );
+    }
+
+    #[test]
+    fn key_repeat_is_ignored() {
+        assert_eq!(
+            replay(&[(0, true), (30, true), (60, true), (900, false)], &CONFIG),
+            vec![GestureAction::Start, GestureAction::Stop]
+        );
+    }
+
+    #[test]
+    fn key_repeat_after_reset_is_ignored() {
+        let base = Instant::now();
+        let at = |ms| base + Duration::from_millis(ms);
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.on_press(at(0), false, &CONFIG);
+        recognizer.on_release(at(80), &CONFIG);
+        assert_eq!(
+            recognizer.on_press(at(200), true, &CONFIG),
+            Some(GestureAction::DoubleTap)
+        );
+
+        // The double-tap cancels the recording while the key is still held
+        recognizer.reset();
+        assert_eq!(recognizer.on_press(at(700), false, &CONFIG), None);
+        assert_eq!(recognizer.on_press(at(730), false, &CONFIG), None);
+        assert_eq!(recognizer.on_release(at(900), &CONFIG), None);
+
+        assert_eq!(
+            recognizer.on_press(at(3000), false, &CONFIG),
+            Some(GestureAction::Start)
+        );
+    }
+
+    #[test]
+    fn restarts_when_latched_recording_was_cancelled() {
+        let base = Instant::now();
+        let mut recognizer = GestureRecognizer::default();
+        recognizer.on_press(base, false, &CONFIG);
+        recognizer.on_release(base + Duration::from_millis(50), &CONFIG);
+
+        // Recording was cancelled elsewhere, so the next press starts a new one
+        let action = recognizer.on_press(base + Duration::from_millis(5000), false, &CONFIG);
+        assert_eq!(action, Some(GestureAction::Start));
+    }
+}
@@ -3,13 +3,17 @@
 //! This module contains the common logic for handling shortcut events,
 //! used by both the Tauri and handy-keys implementations.
 
-use log::warn;
+use log::{debug, warn};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Manager};
 
+use super::gesture::{GestureAction, GestureConfig};
 use crate::actions::ACTION_MAP;
+use crate::clipboard;
 use crate::managers::audio::AudioRecordingManager;
-use crate::settings::get_settings;
+use crate::settings::{get_settings, AppSettings};
+use crate::utils;
 use crate::ManagedToggleState;
 
 /// Handle a shortcut event from either implementation.
@@ -17,7 +21,9 @@ use crate::ManagedToggleState;
 /// This function contains the shared logic for:
 /// - Looking up the action in ACTION_MAP
 /// - Handling the cancel binding (only fires when recording)
+/// - Handling instant actions that don't record (start on press, stop on release)
 /// - Handling push-to-talk mode (start on press, stop on release)
+/// - Handling gesture mode (tap, hold and double tap, see `gesture`)
 /// - Handling toggle mode (toggle state on press only)
 ///
 /// # Arguments
@@ -41,15 +47,31 @@ pub fn handle_shortcut_event(
         return;
     };
 
-    // Cancel binding: only fires when recording and key is pressed
+    // Cancel binding: only fires when recording or typing and key is pressed
     if binding_id == "cancel" {
         let audio_manager = app.state::<Arc<AudioRecordingManager>>();
-        if audio_manager.is_recording() && is_pressed {
+        if (audio_manager.is_recording() || clipboard::is_typing()) && is_pressed {
             action.start(app, binding_id, hotkey_string);
         }
         return;
     }
 
+    // Instant actions: run on press, whatever the recording mode
+    if !action.records_audio() {
+        if is_pressed {
+            action.start(app, binding_id, hotkey_string);
+        } else {
+            action.stop(app, binding_id, hotkey_string);
+        }
+        return;
+    }
+
+    // Gesture mode: tap toggles, hold is push-to-talk, double tap runs another action
+    if settings.gesture_mode_enabled {
+        handle_gesture_event(app, binding_id, hotkey_string, is_pressed, &settings);
+        return;
+    }
+
     // Push-to-talk mode: start on press, stop on release
     if settings.push_to_talk {
         if is_pressed {
@@ -89,3 +111,75 @@ pub fn handle_shortcut_event(
         }
     }
 }
+
+/// Feed a key event through the binding's gesture recognizer and run the result.
+fn handle_gesture_event(
+    app: &AppHandle,
+    binding_id: &str,
+    hotkey_string: &str,
+    is_pressed: bool,
+    settings: &AppSettings,
+) {
+    let config = GestureConfig {
+        hold_threshold: Duration::from_millis(settings.gesture_hold_threshold_ms),
+        double_tap_window: settings
+            .gesture_double_tap_action
+            .as_ref()
+            .map(|_| Duration::from_millis(settings.gesture_double_tap_window_ms)),
+    };
+    let now = Instant::now();
+
+    // Update state while holding the lock, but release it before calling actions
+    let gesture_action = {
+        let toggle_state_manager = app.state::<ManagedToggleState>();
+        let mut states = toggle_state_manager
+            .lock()
+            .expect("Failed to lock toggle state manager");
+
+        let is_active = states
+            .active_toggles
+            .get(binding_id)
+            .copied()
+            .unwrap_or(false);
+        let recognizer = states.gestures.entry(binding_id.to_string()).or_default();
+        let gesture_action = if is_pressed {
+            recognizer.on_press(now, is_active, &config)
+        } else {
+            recognizer.on_release(now, &config)
+        };
+
+        match gesture_action {
+            Some(GestureAction::Start) => {
+                states.active_toggles.insert(binding_id.to_string(), true);
+            }
+            Some(GestureAction::Stop) | Some(GestureAction::DoubleTap) => {
+                states.active_toggles.insert(binding_id.to_string(), false);
+            }
+            None => {}
+        }
+        gesture_action
+    }; // Lock released here
+
+    let Some(action) = ACTION_MAP.get(binding_id) else {
+        return;
+    };
+
+    match gesture_action {
+        Some(GestureAction::Start) => action.start(app, binding_id, hotkey_string),
+        Some(GestureAction::Stop) => action.stop(app, binding_id, hotkey_string),
+        Some(GestureAction::DoubleTap) => {
+            debug!("Double tap on '{}'", binding_id);
+            utils::cancel_current_operation(app);
+
+            let double_tap_id = settings.gesture_double_tap_action.as_deref();
+            match double_tap_id.and_then(|id| ACTION_MAP.get(id).map(|a| (id, a))) {
+                Some((id, double_tap_action)) => {
+                    double_tap_action.start(app, id, hotkey_string);
+                    double_tap_action.stop(app, id, hotkey_string);
+                }
+                None => warn!("Double-tap action {:?} is not defined", double_tap_id),
+            }
+        }
+        None => {}
+    }
+}
@@ -228,6 +228,11 @@ impl HandyKeysState {
 
     /// Register a shortcut binding
     pub fn register(&self, binding: &ShortcutBinding) -> Result<(), String> {
+        // Actions without a key combination are simply left unbound
+        if binding.current_binding.is_empty() {
+            return Ok(());
+        }
+
         let (tx, rx) = mpsc::channel();
         self.command_sender
             .lock()
@@ -426,7 +431,7 @@ pub fn init_shortcuts(app: &AppHandle) -> Result<(), String> {
     let state = HandyKeysState::new(app.clone())?;
 
     let default_bindings = settings::get_default_settings().bindings;
-    let user_settings = settings::load_or_create_app_settings(app);
+    let user_settings = settings::get_settings(app);
 
     // Register all bindings except cancel (which is dynamic)
     for (id, default_binding) in default_bindings {
@@ -7,8 +7,10 @@
 //! - `handy_keys`: Uses the handy-keys library for more control
 //!
 //! The active implementation is determined by the `keyboard_implementation`
-//! setting and can be changed at runtime.
+//! setting and can be changed at runtime. Both feed `handler`, which applies
+//! push-to-talk, toggle or gesture handling before dispatching to `ACTION_MAP`.
 
+pub mod gesture;
 mod handler;
 pub mod handy_keys;
 mod tauri_impl;
@@ -16,12 +18,15 @@ mod tauri_impl;
 use log::{error, info, warn};
 use serde::Serialize;
 use specta::Type;
-use tauri::{AppHandle, Emitter, Manager};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
 use tauri_plugin_autostart::ManagerExt;
 
 use crate::settings::{
-    self, get_settings, ClipboardHandling, KeyboardImplementation, LLMPrompt, OverlayPosition,
-    PasteMethod, ShortcutBinding, SoundTheme, APPLE_INTELLIGENCE_DEFAULT_MODEL_ID,
+    self, get_settings, AppRule, ClipboardHandling, KeyboardImplementation, LLMPrompt,
+    NoiseSuppression, OutputSink, OutputSinkKind, OverlayPosition, PasteMethod, PostProcessStage,
+    PostProcessStageKind, ShortcutBinding, SoundTheme, APPLE_INTELLIGENCE_DEFAULT_MODEL_ID,
     APPLE_INTELLIGENCE_PROVIDER_ID,
 };
 use crate::tray;
@@ -30,7 +35,7 @@ use crate::tray;
 
 /// Initialize shortcuts using the configured implementation
 pub fn init_shortcuts(app: &AppHandle) {
-    let user_settings = settings::load_or_create_app_settings(app);
+    let user_settings = settings::get_settings(app);
 
     // Check which implementation to use
     match user_settings.keyboard_implementation {
@@ -44,14 +49,64 @@ pub fn init_shortcuts(app: &AppHandle) {
                 warn!("Falling back to Tauri global shortcut implementation and saving fallback to settings");
 
                 // Update settings to persist the fallback so we don't retry HandyKeys on next launch
-                let mut settings = settings::get_settings(app);
-                settings.keyboard_implementation = KeyboardImplementation::Tauri;
-                settings::write_settings(app, settings);
+                settings::update_settings(app, |settings| {
+                    settings.keyboard_implementation = KeyboardImplementation::Tauri;
+                });
 
                 tauri_impl::init_shortcuts(app);
             }
         }
     }
+
+    app.manage(AppliedBindings(Mutex::new(settings::get_bindings(app))));
+}
+
+/// Bindings as last registered, so a `bindings` change can be applied as a
+/// diff against them.
+struct AppliedBindings(Mutex<HashMap<String, ShortcutBinding>>);
+
+/// Records `binding` as registered, so `apply_binding_changes` leaves it be.
+fn mark_binding_applied(app: &AppHandle, binding: &ShortcutBinding) {
+    if let Some(applied) = app.try_state::<AppliedBindings>() {
+        applied
+            .0
+            .lock()
+            .unwrap()
+            .insert(binding.id.clone(), binding.clone());
+    }
+}
+
+/// Re-registers the bindings whose key combination changed since they were
+/// last registered, for writes to `bindings` that didn't register them.
+pub fn apply_binding_changes(app: &AppHandle) {
+    let Some(applied) = app.try_state::<AppliedBindings>() else {
+        return;
+    };
+    let bindings = settings::get_bindings(app);
+    let mut applied = applied.0.lock().unwrap();
+
+    for (id, binding) in &bindings {
+        // The cancel shortcut is registered for the length of a recording
+        if id == "cancel" {
+            continue;
+        }
+
+        let previous = applied.get(id);
+        if previous.is_some_and(|p| p.current_binding == binding.current_binding) {
+            continue;
+        }
+
+        if let Some(previous) = previous {
+            if let Err(e) = unregister_shortcut(app, previous.clone()) {
+                warn!("Failed to unregister shortcut '{}': {}", id, e);
+            }
+        }
+        if let Err(e) = register_shortcut(app, binding.clone()) {
+            error!("Failed to register shortcut '{}': {}", id, e);
+        }
+    }
+
+    *applied = bindings;
 }
 
 /// Register the cancel shortcut (called when recording starts)
@@ -108,7 +163,7 @@ pub fn change_binding(
     id: String,
     binding: String,
 ) -> Result<BindingResponse, String> {
-    let mut settings = settings::get_settings(&app);
+    let settings = settings::get_settings(&app);
 
     // Get the binding to modify
     let binding_to_modify = match settings.bindings.get(&id) {
@@ -129,8 +184,9 @@ pub fn change_binding(
     if id == "cancel" {
         if let Some(mut b) = settings.bindings.get(&id).cloned() {
             b.current_binding = binding;
-            settings.bindings.insert(id.clone(), b.clone());
-            settings::write_settings(&app, settings);
+            settings::update_settings(&app, |settings| {
+                settings.bindings.insert(id.clone(), b.clone());
+            });
             return Ok(BindingResponse {
                 success: true,
                 binding: Some(b.clone()),
@@ -168,10 +224,10 @@ pub fn change_binding(
     }
 
     // Update the binding in the settings
-    settings.bindings.insert(id, updated_binding.clone());
-
-    // Save the settings
-    settings::write_settings(&app, settings);
+    mark_binding_applied(&app, &updated_binding);
+    settings::update_settings(&app, |settings| {
+        settings.bindings.insert(id, updated_binding.clone());
+    });
 
     // Return the updated binding
     Ok(BindingResponse {
@@ -258,9 +314,9 @@ pub fn change_keyboard_implementation_setting(
     unregister_all_shortcuts(&app, current_impl);
 
     // Update the setting
-    let mut settings = settings::get_settings(&app);
-    settings.keyboard_implementation = new_impl;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.keyboard_implementation = new_impl;
+    });
 
     // Initialize new implementation if needed (HandyKeys needs state)
     if new_impl == KeyboardImplementation::HandyKeys {
@@ -276,16 +332,6 @@ pub fn change_keyboard_implementation_setting(
     // Register all shortcuts with new implementation, resetting invalid ones
     let reset_bindings = register_all_shortcuts_for_implementation(&app, new_impl);
 
-    // Emit event to notify frontend of the change
-    let _ = app.emit(
-        "settings-changed",
-        serde_json::json!({
-            "setting": "keyboard_implementation",
-            "value": implementation,
-            "reset_bindings": reset_bindings
-        }),
-    );
-
     info!("Keyboard implementation switched to {:?}", new_impl);
 
     Ok(ImplementationChangeResult {
@@ -309,11 +355,15 @@ pub fn get_keyboard_implementation(app: AppHandle) -> String {
 // Validation Helpers
 // ============================================================================
 
-/// Validate a shortcut for a specific implementation
+/// Validate a shortcut for a specific implementation. An empty shortcut is
+/// valid and leaves the action unbound.
 fn validate_shortcut_for_implementation(
     raw: &str,
     implementation: KeyboardImplementation,
 ) -> Result<(), String> {
+    if raw.is_empty() {
+        return Ok(());
+    }
     match implementation {
         KeyboardImplementation::Tauri => tauri_impl::validate_shortcut(raw),
         KeyboardImplementation::HandyKeys => handy_keys::validate_shortcut(raw),
@@ -366,7 +416,7 @@ fn register_all_shortcuts_for_implementation(
 ) -> Vec<String> {
     let mut reset_bindings = Vec::new();
     let default_bindings = settings::get_default_settings().bindings;
-    let mut current_settings = settings::get_settings(app);
+    let current_settings = settings::get_settings(app);
 
     for (id, default_binding) in &default_bindings {
         // Skip cancel shortcut as it's dynamically registered
@@ -391,10 +441,7 @@ fn register_all_shortcuts_for_implementation(
 
             // Reset to default
             binding.current_binding = default_binding.current_binding.clone();
-            current_settings
-                .bindings
-                .insert(id.clone(), binding.clone());
-            reset_bindings.push(id.clone());
+            reset_bindings.push((id.clone(), binding.clone()));
         }
 
         // Register with the appropriate implementation
@@ -413,10 +460,17 @@ fn register_all_shortcuts_for_implementation(
 
     // Save settings if any bindings were reset
     if !reset_bindings.is_empty() {
-        settings::write_settings(app, current_settings);
+        for (_, binding) in &reset_bindings {
+            mark_binding_applied(app, binding);
+        }
+        settings::update_settings(app, |settings| {
+            for (id, binding) in &reset_bindings {
+                settings.bindings.insert(id.clone(), binding.clone());
+            }
+        });
     }
 
-    reset_bindings
+    reset_bindings.into_iter().map(|(id, _)| id).collect()
 }
 
 /// Initialize HandyKeys if not already initialized, with rollback on failure
@@ -428,9 +482,9 @@ fn initialize_handy_keys_with_rollback(app: &AppHandle) -> Result<bool, String>
     if let Err(e) = handy_keys::init_shortcuts(app) {
         error!("Failed to initialize HandyKeys: {}", e);
         // Rollback to Tauri
-        let mut settings = settings::get_settings(app);
-        settings.keyboard_implementation = KeyboardImplementation::Tauri;
-        settings::write_settings(app, settings);
+        settings::update_settings(app, |settings| {
+            settings.keyboard_implementation = KeyboardImplementation::Tauri;
+        });
         tauri_impl::init_shortcuts(app);
         return Err(format!(
             "Failed to initialize HandyKeys: {}. Reverted to Tauri.",
@@ -449,81 +503,129 @@ fn initialize_handy_keys_with_rollback(app: &AppHandle) -> Result<bool, String>
 #[tauri::command]
 #[specta::specta]
 pub fn change_ptt_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.push_to_talk = enabled;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.push_to_talk = enabled;
+    });
+    Ok(())
+}
+
+/// Configure gesture mode. `double_tap_action` names an entry of `ACTION_MAP`;
+/// `None` disables double-tap detection.
+#[tauri::command]
+#[specta::specta]
+pub fn change_gesture_settings(
+    app: AppHandle,
+    enabled: bool,
+    hold_threshold_ms: u64,
+    double_tap_window_ms: u64,
+    double_tap_action: Option<String>,
+) -> Result<(), String> {
+    if !(100..=2000).contains(&hold_threshold_ms) {
+        return Err("Hold threshold must be between 100 and 2000 ms".to_string());
+    }
+    if !(100..=1000).contains(&double_tap_window_ms) {
+        return Err("Double-tap window must be between 100 and 1000 ms".to_string());
+    }
+    if let Some(id) = &double_tap_action {
+        match crate::actions::ACTION_MAP.get(id) {
+            Some(action) if !action.records_audio() && id != "cancel" => {}
+            Some(_) => return Err(format!("Action '{}' cannot be used for double tap", id)),
+            None => return Err(format!("Action '{}' not found", id)),
+        }
+    }
+
+    settings::update_settings(&app, |settings| {
+        settings.gesture_mode_enabled = enabled;
+        settings.gesture_hold_threshold_ms = hold_threshold_ms;
+        settings.gesture_double_tap_window_ms = double_tap_window_ms;
+        settings.gesture_double_tap_action = double_tap_action;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_audio_feedback_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.audio_feedback = enabled;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.audio_feedback = enabled;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_audio_feedback_volume_setting(app: AppHandle, volume: f32) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.audio_feedback_volume = volume;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.audio_feedback_volume = volume;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_sound_theme_setting(app: AppHandle, theme: String) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    let parsed = match theme.as_str() {
-        "marimba" => SoundTheme::Marimba,
-        "pop" => SoundTheme::Pop,
-        "custom" => SoundTheme::Custom,
-        other => {
-            warn!("Invalid sound theme '{}', defaulting to marimba", other);
-            SoundTheme::Marimba
-        }
-    };
-    settings.sound_theme = parsed;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        let parsed = match theme.as_str() {
+            "marimba" => SoundTheme::Marimba,
+            "pop" => SoundTheme::Pop,
+            "custom" => SoundTheme::Custom,
+            other => {
+                warn!("Invalid sound theme '{}', defaulting to marimba", other);
+                SoundTheme::Marimba
+            }
+        };
+        settings.sound_theme = parsed;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_translate_to_english_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.translate_to_english = enabled;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.translate_to_english = enabled;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_selected_language_setting(app: AppHandle, language: String) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.selected_language = language;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.selected_language = language;
+    });
+    Ok(())
+}
+
+/// Set the languages the "cycle language" shortcut steps through.
+#[tauri::command]
+#[specta::specta]
+pub fn set_language_cycle(app: AppHandle, languages: Vec<String>) -> Result<(), String> {
+    let mut languages = languages;
+    languages.retain(|l| !l.trim().is_empty());
+    languages.dedup();
+
+    settings::update_settings(&app, |settings| {
+        settings.language_cycle = languages;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_overlay_position_setting(app: AppHandle, position: String) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    let parsed = match position.as_str() {
-        "none" => OverlayPosition::None,
-        "top" => OverlayPosition::Top,
-        "bottom" => OverlayPosition::Bottom,
-        other => {
-            warn!("Invalid overlay position '{}', defaulting to bottom", other);
-            OverlayPosition::Bottom
-        }
-    };
-    settings.overlay_position = parsed;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        let parsed = match position.as_str() {
+            "none" => OverlayPosition::None,
+            "top" => OverlayPosition::Top,
+            "bottom" => OverlayPosition::Bottom,
+            other => {
+                warn!("Invalid overlay position '{}', defaulting to bottom", other);
+                OverlayPosition::Bottom
+            }
+        };
+        settings.overlay_position = parsed;
+    });
 
     // Update overlay position without recreating window
     crate::utils::update_overlay_position(&app);
@@ -534,18 +636,9 @@ pub fn change_overlay_position_setting(app: AppHandle, position: String) -> Resu
 #[tauri::command]
 #[specta::specta]
 pub fn change_debug_mode_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.debug_mode = enabled;
-    settings::write_settings(&app, settings);
-
-    // Emit event to notify frontend of debug mode change
-    let _ = app.emit(
-        "settings-changed",
-        serde_json::json!({
-            "setting": "debug_mode",
-            "value": enabled
-        }),
-    );
+    settings::update_settings(&app, |settings| {
+        settings.debug_mode = enabled;
+    });
 
     Ok(())
 }
@@ -553,18 +646,9 @@ pub fn change_debug_mode_setting(app: AppHandle, enabled: bool) -> Result<(), St
 #[tauri::command]
 #[specta::specta]
 pub fn change_start_hidden_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.start_hidden = enabled;
-    settings::write_settings(&app, settings);
-
-    // Notify frontend
-    let _ = app.emit(
-        "settings-changed",
-        serde_json::json!({
-            "setting": "start_hidden",
-            "value": enabled
-        }),
-    );
+    settings::update_settings(&app, |settings| {
+        settings.start_hidden = enabled;
+    });
 
     Ok(())
 }
@@ -572,9 +656,9 @@ pub fn change_start_hidden_setting(app: AppHandle, enabled: bool) -> Result<(),
 #[tauri::command]
 #[specta::specta]
 pub fn change_autostart_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.autostart_enabled = enabled;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.autostart_enabled = enabled;
+    });
 
     // Apply the autostart setting immediately
     let autostart_manager = app.autolaunch();
@@ -584,32 +668,15 @@ pub fn change_autostart_setting(app: AppHandle, enabled: bool) -> Result<(), Str
         let _ = autostart_manager.disable();
     }
 
-    // Notify frontend
-    let _ = app.emit(
-        "settings-changed",
-        serde_json::json!({
-            "setting": "autostart_enabled",
-            "value": enabled
-        }),
-    );
-
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_update_checks_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.update_checks_enabled = enabled;
-    settings::write_settings(&app, settings);
-
-    let _ = app.emit(
-        "settings-changed",
-        serde_json::json!({
-            "setting": "update_checks_enabled",
-            "value": enabled
-        }),
-    );
+    settings::update_settings(&app, |settings| {
+        settings.update_checks_enabled = enabled;
+    });
 
     Ok(())
 }
@@ -617,9 +684,9 @@ pub fn change_update_checks_setting(app: AppHandle, enabled: bool) -> Result<(),
 #[tauri::command]
 #[specta::specta]
 pub fn update_custom_words(app: AppHandle, words: Vec<String>) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.custom_words = words;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.custom_words = words;
+    });
     Ok(())
 }
 
@@ -629,67 +696,67 @@ pub fn change_word_correction_threshold_setting(
     app: AppHandle,
     threshold: f64,
 ) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.word_correction_threshold = threshold;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.word_correction_threshold = threshold;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_paste_method_setting(app: AppHandle, method: String) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    let parsed = match method.as_str() {
-        "ctrl_v" => PasteMethod::CtrlV,
-        "direct" => PasteMethod::Direct,
-        "none" => PasteMethod::None,
-        "shift_insert" => PasteMethod::ShiftInsert,
-        "ctrl_shift_v" => PasteMethod::CtrlShiftV,
-        other => {
-            warn!("Invalid paste method '{}', defaulting to ctrl_v", other);
-            PasteMethod::CtrlV
-        }
-    };
-    settings.paste_method = parsed;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        let parsed = match method.as_str() {
+            "ctrl_v" => PasteMethod::CtrlV,
+            "direct" => PasteMethod::Direct,
+            "none" => PasteMethod::None,
+            "shift_insert" => PasteMethod::ShiftInsert,
+            "ctrl_shift_v" => PasteMethod::CtrlShiftV,
+            other => {
+                warn!("Invalid paste method '{}', defaulting to ctrl_v", other);
+                PasteMethod::CtrlV
+            }
+        };
+        settings.paste_method = parsed;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_clipboard_handling_setting(app: AppHandle, handling: String) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    let parsed = match handling.as_str() {
-        "dont_modify" => ClipboardHandling::DontModify,
-        "copy_to_clipboard" => ClipboardHandling::CopyToClipboard,
-        other => {
-            warn!(
-                "Invalid clipboard handling '{}', defaulting to dont_modify",
-                other
-            );
-            ClipboardHandling::DontModify
-        }
-    };
-    settings.clipboard_handling = parsed;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        let parsed = match handling.as_str() {
+            "dont_modify" => ClipboardHandling::DontModify,
+            "copy_to_clipboard" => ClipboardHandling::CopyToClipboard,
+            other => {
+                warn!(
+                    "Invalid clipboard handling '{}', defaulting to dont_modify",
+                    other
+                );
+                ClipboardHandling::DontModify
+            }
+        };
+        settings.clipboard_handling = parsed;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_post_process_enabled_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.post_process_enabled = enabled;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.post_process_enabled = enabled;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_experimental_enabled_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.experimental_enabled = enabled;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.experimental_enabled = enabled;
+    });
     Ok(())
 }
 
@@ -700,26 +767,26 @@ pub fn change_post_process_base_url_setting(
     provider_id: String,
     base_url: String,
 ) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    let label = settings
-        .post_process_provider(&provider_id)
-        .map(|provider| provider.label.clone())
-        .ok_or_else(|| format!("Provider '{}' not found", provider_id))?;
-
-    let provider = settings
-        .post_process_provider_mut(&provider_id)
-        .expect("Provider looked up above must exist");
-
-    if provider.id != "custom" {
-        return Err(format!(
-            "Provider '{}' does not allow editing the base URL",
-            label
-        ));
-    }
+    settings::update_settings(&app, |settings| {
+        let label = settings
+            .post_process_provider(&provider_id)
+            .map(|provider| provider.label.clone())
+            .ok_or_else(|| format!("Provider '{}' not found", provider_id))?;
+
+        let provider = settings
+            .post_process_provider_mut(&provider_id)
+            .expect("Provider looked up above must exist");
+
+        if provider.id != "custom" {
+            return Err(format!(
+                "Provider '{}' does not allow editing the base URL",
+                label
+            ));
+        }
 
-    provider.base_url = base_url;
-    settings::write_settings(&app, settings);
-    Ok(())
+        provider.base_url = base_url;
+        Ok(())
+    })
 }
 
 /// Generic helper to validate provider exists
@@ -744,10 +811,20 @@ pub fn change_post_process_api_key_setting(
     provider_id: String,
     api_key: String,
 ) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
+    let settings = settings::get_settings(&app);
     validate_provider_exists(&settings, &provider_id)?;
-    settings.post_process_api_keys.insert(provider_id, api_key);
-    settings::write_settings(&app, settings);
+
+    // The frontend only ever sees the masked key; saving it back changes nothing
+    if crate::secrets::is_unchanged_mask(&settings.post_process_api_keys, &provider_id, &api_key) {
+        return Ok(());
+    }
+
+    let reference = crate::secrets::store_api_key("post_process", &provider_id, &api_key)?;
+    settings::update_settings(&app, |settings| {
+        settings
+            .post_process_api_keys
+            .insert(provider_id, reference);
+    });
     Ok(())
 }
 
@@ -758,21 +835,21 @@ pub fn change_post_process_model_setting(
     provider_id: String,
     model: String,
 ) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    validate_provider_exists(&settings, &provider_id)?;
-    settings.post_process_models.insert(provider_id, model);
-    settings::write_settings(&app, settings);
-    Ok(())
+    settings::update_settings(&app, |settings| {
+        validate_provider_exists(settings, &provider_id)?;
+        settings.post_process_models.insert(provider_id, model);
+        Ok(())
+    })
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn set_post_process_provider(app: AppHandle, provider_id: String) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    validate_provider_exists(&settings, &provider_id)?;
-    settings.post_process_provider_id = provider_id;
-    settings::write_settings(&app, settings);
-    Ok(())
+    settings::update_settings(&app, |settings| {
+        validate_provider_exists(settings, &provider_id)?;
+        settings.post_process_provider_id = provider_id;
+        Ok(())
+    })
 }
 
 #[tauri::command]
@@ -782,19 +859,19 @@ pub fn add_post_process_prompt(
     name: String,
     prompt: String,
 ) -> Result<LLMPrompt, String> {
-    let mut settings = settings::get_settings(&app);
-
-    // Generate unique ID using timestamp and random component
-    let id = format!("prompt_{}", chrono::Utc::now().timestamp_millis());
-
-    let new_prompt = LLMPrompt {
-        id: id.clone(),
-        name,
-        prompt,
-    };
+    let new_prompt = settings::update_settings(&app, |settings| {
+        // Generate unique ID using timestamp and random component
+        let id = format!("prompt_{}", chrono::Utc::now().timestamp_millis());
+
+        let new_prompt = LLMPrompt {
+            id: id.clone(),
+            name,
+            prompt,
+        };
 
-    settings.post_process_prompts.push(new_prompt.clone());
-    settings::write_settings(&app, settings);
+        settings.post_process_prompts.push(new_prompt.clone());
+        new_prompt
+    });
 
     Ok(new_prompt)
 }
@@ -807,48 +884,61 @@ pub fn update_post_process_prompt(
     name: String,
     prompt: String,
 ) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-
-    if let Some(existing_prompt) = settings
-        .post_process_prompts
-        .iter_mut()
-        .find(|p| p.id == id)
-    {
+    settings::update_settings(&app, |settings| {
+        let existing_prompt = settings
+            .post_process_prompts
+            .iter_mut()
+            .find(|p| p.id == id)
+            .ok_or_else(|| format!("Prompt with id '{}' not found", id))?;
         existing_prompt.name = name;
         existing_prompt.prompt = prompt;
-        settings::write_settings(&app, settings);
         Ok(())
-    } else {
-        Err(format!("Prompt with id '{}' not found", id))
-    }
+    })
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn delete_post_process_prompt(app: AppHandle, id: String) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
+    settings::update_settings(&app, |settings| {
+        // Don't allow deleting the last prompt
+        if settings.post_process_prompts.len() <= 1 {
+            return Err("Cannot delete the last prompt".to_string());
+        }
 
-    // Don't allow deleting the last prompt
-    if settings.post_process_prompts.len() <= 1 {
-        return Err("Cannot delete the last prompt".to_string());
-    }
+        // Don't allow deleting a prompt that a pipeline stage still depends on
+        if let Some(stage) = settings.post_process_pipeline.iter().find(|stage| {
+            matches!(&stage.kind, PostProcessStageKind::LlmPrompt { prompt_id, .. } if *prompt_id == id)
+        }) {
+            return Err(format!(
+                "Prompt is used by post-processing stage '{}'",
+                stage.name
+            ));
+        }
 
-    // Find and remove the prompt
-    let original_len = settings.post_process_prompts.len();
-    settings.post_process_prompts.retain(|p| p.id != id);
+        if let Some(rule) = settings
+            .app_rules
+            .iter()
+            .find(|rule| rule.prompt_id.as_deref() == Some(id.as_str()))
+        {
+            return Err(format!("Prompt is used by app rule '{}'", rule.name));
+        }
 
-    if settings.post_process_prompts.len() == original_len {
-        return Err(format!("Prompt with id '{}' not found", id));
-    }
+        // Find and remove the prompt
+        let original_len = settings.post_process_prompts.len();
+        settings.post_process_prompts.retain(|p| p.id != id);
 
-    // If the deleted prompt was selected, select the first one or None
-    if settings.post_process_selected_prompt_id.as_ref() == Some(&id) {
-        settings.post_process_selected_prompt_id =
-            settings.post_process_prompts.first().map(|p| p.id.clone());
-    }
+        if settings.post_process_prompts.len() == original_len {
+            return Err(format!("Prompt with id '{}' not found", id));
+        }
 
-    settings::write_settings(&app, settings);
-    Ok(())
+        // If the deleted prompt was selected, select the first one or None
+        if settings.post_process_selected_prompt_id.as_ref() == Some(&id) {
+            settings.post_process_selected_prompt_id =
+                settings.post_process_prompts.first().map(|p| p.id.clone());
+        }
+
+        Ok(())
+    })
 }
 
 #[tauri::command]
@@ -879,11 +969,7 @@ pub async fn fetch_post_process_models(
     }
 
     // Get API key
-    let api_key = settings
-        .post_process_api_keys
-        .get(&provider_id)
-        .cloned()
-        .unwrap_or_default();
+    let api_key = crate::secrets::resolve_api_key(&settings.post_process_api_keys, &provider_id);
 
     // Skip fetching if no API key for providers that typically need one
     if api_key.trim().is_empty() && provider.id != "custom" {
@@ -899,42 +985,255 @@ pub async fn fetch_post_process_models(
 #[tauri::command]
 #[specta::specta]
 pub fn set_post_process_selected_prompt(app: AppHandle, id: String) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
+    settings::update_settings(&app, |settings| {
+        // Verify the prompt exists
+        if !settings.post_process_prompts.iter().any(|p| p.id == id) {
+            return Err(format!("Prompt with id '{}' not found", id));
+        }
+
+        settings.post_process_selected_prompt_id = Some(id);
+        Ok(())
+    })
+}
+
+/// Replace the post-processing pipeline. An empty list restores the default
+/// behaviour (Chinese conversion plus the selected prompt).
+#[tauri::command]
+#[specta::specta]
+pub fn set_post_process_pipeline(
+    app: AppHandle,
+    stages: Vec<PostProcessStage>,
+) -> Result<(), String> {
+    settings::update_settings(&app, |settings| {
+        for stage in &stages {
+            crate::post_process::validate_stage(settings, stage)?;
+        }
+
+        let mut seen_ids = std::collections::HashSet::new();
+        if let Some(duplicate) = stages.iter().find(|s| !seen_ids.insert(s.id.as_str())) {
+            return Err(format!("Duplicate stage id '{}'", duplicate.id));
+        }
+
+        settings.post_process_pipeline = stages;
+        Ok(())
+    })
+}
+
+/// Replace the per-application override rules. Rules are evaluated in order
+/// and the first match wins.
+#[tauri::command]
+#[specta::specta]
+pub fn set_app_rules(app: AppHandle, rules: Vec<AppRule>) -> Result<(), String> {
+    settings::update_settings(&app, |settings| {
+        let mut seen_ids = std::collections::HashSet::new();
+        for rule in &rules {
+            if !seen_ids.insert(rule.id.as_str()) {
+                return Err(format!("Duplicate rule id '{}'", rule.id));
+            }
+            if rule.app_match.trim().is_empty() {
+                return Err(format!(
+                    "Rule '{}' has an empty application match",
+                    rule.name
+                ));
+            }
+            if let Some(prompt_id) = &rule.prompt_id {
+                if !settings
+                    .post_process_prompts
+                    .iter()
+                    .any(|p| &p.id == prompt_id)
+                {
+                    return Err(format!("Prompt with id '{}' not found", prompt_id));
+                }
+            }
+        }
+
+        settings.app_rules = rules;
+        Ok(())
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_output_sinks(app: AppHandle, sinks: Vec<OutputSink>) -> Result<(), String> {
+    let mut seen_ids = std::collections::HashSet::new();
+    for sink in &sinks {
+        if !seen_ids.insert(sink.id.as_str()) {
+            return Err(format!("Duplicate sink id '{}'", sink.id));
+        }
+        let target = match &sink.kind {
+            OutputSinkKind::AppendToFile { path } => path,
+            OutputSinkKind::RunCommand { command } => command,
+            OutputSinkKind::Webhook { url } => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(format!("Sink '{}' needs an http(s) URL", sink.name));
+                }
+                url
+            }
+        };
+        if target.trim().is_empty() {
+            return Err(format!("Sink '{}' has no target", sink.name));
+        }
+    }
+
+    settings::update_settings(&app, |settings| {
+        settings.output_sinks = sinks;
+    });
+    Ok(())
+}
 
-    // Verify the prompt exists
-    if !settings.post_process_prompts.iter().any(|p| p.id == id) {
-        return Err(format!("Prompt with id '{}' not found", id));
+#[tauri::command]
+#[specta::specta]
+pub fn change_edit_selection_prompt_setting(app: AppHandle, prompt: String) -> Result<(), String> {
+    if !prompt.contains("${instruction}") {
+        return Err("Prompt must contain the ${instruction} placeholder".to_string());
     }
 
-    settings.post_process_selected_prompt_id = Some(id);
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.edit_selection_prompt = prompt;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_mute_while_recording_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.mute_while_recording = enabled;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.mute_while_recording = enabled;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn change_audio_high_pass_filter_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::update_settings(&app, |settings| {
+        settings.audio_high_pass_filter = enabled;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn change_audio_gain_control_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::update_settings(&app, |settings| {
+        settings.audio_gain_control = enabled;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn change_audio_normalize_loudness_setting(
+    app: AppHandle,
+    enabled: bool,
+) -> Result<(), String> {
+    settings::update_settings(&app, |settings| {
+        settings.audio_normalize_loudness = enabled;
+    });
+    Ok(())
+}
+
+/// Sets the folder meeting transcripts are written to; `None` restores the default.
+#[tauri::command]
+#[specta::specta]
+pub fn change_meeting_notes_dir_setting(app: AppHandle, dir: Option<String>) -> Result<(), String> {
+    if let Some(dir) = &dir {
+        if !std::path::Path::new(dir).is_dir() {
+            return Err(format!("'{}' is not a folder", dir));
+        }
+    }
+
+    settings::update_settings(&app, |settings| {
+        settings.meeting_notes_dir = dir;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn change_audio_noise_suppression_setting(
+    app: AppHandle,
+    method: String,
+) -> Result<(), String> {
+    settings::update_settings(&app, |settings| {
+        let parsed = match method.as_str() {
+            "off" => NoiseSuppression::Off,
+            "spectral" => NoiseSuppression::Spectral,
+            "rnnoise" => NoiseSuppression::Rnnoise,
+            other => {
+                warn!("Invalid noise suppression '{}', defaulting to off", other);
+                NoiseSuppression::Off
+            }
+        };
+        settings.audio_noise_suppression = parsed;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn change_history_auto_title_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::update_settings(&app, |settings| {
+        settings.history_auto_title = enabled;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_append_trailing_space_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.append_trailing_space = enabled;
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.append_trailing_space = enabled;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn change_clipboard_delay_setting(app: AppHandle, delay_ms: u64) -> Result<(), String> {
+    if !(10..=1000).contains(&delay_ms) {
+        return Err("Clipboard delay must be between 10 and 1000 ms".to_string());
+    }
+
+    settings::update_settings(&app, |settings| {
+        settings.clipboard_delay_ms = delay_ms;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn change_typing_chunk_setting(
+    app: AppHandle,
+    chunk_size: usize,
+    delay_ms: u64,
+) -> Result<(), String> {
+    if delay_ms > 2000 {
+        return Err("Typing delay must be at most 2000 ms".to_string());
+    }
+
+    settings::update_settings(&app, |settings| {
+        settings.typing_chunk_size = chunk_size;
+        settings.typing_chunk_delay_ms = delay_ms;
+    });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn change_typing_shift_enter_setting(app: AppHandle, enabled: bool) -> Result<(), String> {
+    settings::update_settings(&app, |settings| {
+        settings.typing_shift_enter_newlines = enabled;
+    });
     Ok(())
 }
 
 #[tauri::command]
 #[specta::specta]
 pub fn change_app_language_setting(app: AppHandle, language: String) -> Result<(), String> {
-    let mut settings = settings::get_settings(&app);
-    settings.app_language = language.clone();
-    settings::write_settings(&app, settings);
+    settings::update_settings(&app, |settings| {
+        settings.app_language = language.clone();
+    });
 
     // Refresh the tray menu with the new language
     tray::update_tray_menu(&app, &tray::TrayIconState::Idle, Some(&language));
@@ -1,10 +1,11 @@
 use crate::managers::history::{HistoryEntry, HistoryManager};
+use crate::managers::meeting::MeetingManager;
 use crate::settings;
 use crate::tray_i18n::get_tray_translations;
 use log::{error, info, warn};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tauri::image::Image;
-use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::tray::TrayIcon;
 use tauri::{AppHandle, Manager, Theme};
 use tauri_plugin_clipboard_manager::ClipboardExt;
@@ -16,6 +17,12 @@ pub enum TrayIconState {
     Transcribing,
 }
 
+/// Menu id prefix for entries of the profiles submenu
+pub const PROFILE_MENU_PREFIX: &str = "profile:";
+
+/// State the tray menu was last built for, so it can be rebuilt after settings change
+static CURRENT_MENU_STATE: Mutex<TrayIconState> = Mutex::new(TrayIconState::Idle);
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum AppTheme {
     Dark,
@@ -61,6 +68,16 @@ pub fn get_icon_path(theme: AppTheme, state: TrayIconState) -> &'static str {
 }
 
 pub fn change_tray_icon(app: &AppHandle, icon: TrayIconState) {
+    // A running meeting keeps showing as recording, between dictations too
+    let meeting_running = app
+        .try_state::<Arc<MeetingManager>>()
+        .is_some_and(|mm| mm.is_running());
+    let icon = if icon == TrayIconState::Idle && meeting_running {
+        TrayIconState::Recording
+    } else {
+        icon
+    };
+
     let tray = app.state::<TrayIcon>();
     let theme = get_current_theme(app);
 
@@ -79,8 +96,20 @@ pub fn change_tray_icon(app: &AppHandle, icon: TrayIconState) {
     update_tray_menu(app, &icon, None);
 }
 
+/// Rebuilds the tray menu for its current state, e.g. after the profile list changed.
+pub fn refresh_tray_menu(app: &AppHandle) {
+    let state = CURRENT_MENU_STATE
+        .lock()
+        .map(|state| state.clone())
+        .unwrap_or(TrayIconState::Idle);
+    update_tray_menu(app, &state, None);
+}
+
 pub fn update_tray_menu(app: &AppHandle, state: &TrayIconState, locale: Option<&str>) {
     let settings = settings::get_settings(app);
+    if let Ok(mut current) = CURRENT_MENU_STATE.lock() {
+        *current = state.clone();
+    }
 
     let locale = locale.unwrap_or(&settings.app_language);
     let strings = get_tray_translations(Some(locale.to_string()));
@@ -127,6 +156,29 @@ pub fn update_tray_menu(app: &AppHandle, state: &TrayIconState, locale: Option<&
         .expect("failed to create quit item");
     let separator = || PredefinedMenuItem::separator(app).expect("failed to create separator");
 
+    // Profiles submenu, only shown once the user has saved a profile
+    let profile_items: Vec<CheckMenuItem<tauri::Wry>> = settings
+        .profiles
+        .iter()
+        .map(|profile| {
+            CheckMenuItem::with_id(
+                app,
+                format!("{}{}", PROFILE_MENU_PREFIX, profile.id),
+                &profile.name,
+                true,
+                settings.active_profile_id.as_deref() == Some(profile.id.as_str()),
+                None::<&str>,
+            )
+            .expect("failed to create profile item")
+        })
+        .collect();
+    let profile_refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> = profile_items
+        .iter()
+        .map(|item| item as &dyn tauri::menu::IsMenuItem<tauri::Wry>)
+        .collect();
+    let profiles_i = Submenu::with_items(app, &strings.profiles, true, &profile_refs)
+        .expect("failed to create profiles submenu");
+
     let menu = match state {
         TrayIconState::Recording | TrayIconState::Transcribing => {
             let cancel_i = MenuItem::with_id(app, "cancel", &strings.cancel, true, None::<&str>)
@@ -163,13 +215,29 @@ pub fn update_tray_menu(app: &AppHandle, state: &TrayIconState, locale: Option<&
         )
         .expect("failed to create menu"),
     };
+    if !profile_items.is_empty() {
+        // Sits right after "Copy Last Transcript", wherever the layout puts it
+        let position = menu.items().ok().and_then(|items| {
+            items
+                .iter()
+                .position(|item| *item.id() == "copy_last_transcript")
+        });
+        match position {
+            Some(position) => {
+                let _ = menu.insert(&profiles_i, position + 1);
+            }
+            None => {
+                let _ = menu.append(&profiles_i);
+            }
+        }
+    }
 
     let tray = app.state::<TrayIcon>();
     let _ = tray.set_menu(Some(menu));
     let _ = tray.set_icon_as_template(true);
 }
 
-fn last_transcript_text(entry: &HistoryEntry) -> &str {
+pub fn last_transcript_text(entry: &HistoryEntry) -> &str {
     entry
         .post_processed_text
         .as_deref()
@@ -213,6 +281,13 @@ mod tests {
             transcription_text: transcription.to_string(),
             post_processed_text: post_processed.map(|text| text.to_string()),
             post_process_prompt: None,
+            post_process_stages: Vec::new(),
+            microphone: None,
+            segments: Vec::new(),
+            audio_deleted: false,
+            corrected_text: None,
+            notes: None,
+            tags: Vec::new(),
         }
     }
 
@@ -0,0 +1,238 @@
+//! Dictation analytics over the history
+//!
+//! `summarize` turns the per-entry metadata stored with every transcription
+//! (duration, word count, model and latencies) into the totals, daily series
+//! and per-model latencies returned by `get_usage_stats`. Entries saved before
+//! the metadata existed only count with their words.
+
+use crate::corrections::{count_corrections, CorrectionCount};
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::BTreeMap;
+
+/// Typing speed the time saved is estimated against
+const TYPING_WORDS_PER_MINUTE: u64 = 40;
+/// Corrections listed in `UsageStats::top_corrections`
+const TOP_CORRECTIONS: usize = 10;
+
+/// The trailing window statistics are computed over.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum UsageRange {
+    /// Since midnight
+    Today,
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+impl UsageRange {
+    /// Unix timestamp of the start of the range, `None` for everything.
+    pub fn since(self, now: DateTime<Local>) -> Option<i64> {
+        let start = match self {
+            UsageRange::Today => Local
+                .from_local_datetime(&now.date_naive().and_time(NaiveTime::MIN))
+                .earliest()
+                .unwrap_or(now),
+            UsageRange::Week => now - Duration::days(7),
+            UsageRange::Month => now - Duration::days(30),
+            UsageRange::Year => now - Duration::days(365),
+            UsageRange::All => return None,
+        };
+        Some(start.timestamp())
+    }
+}
+
+/// What a history entry contributes to the statistics.
+pub struct UsageRow {
+    pub timestamp: i64,
+    /// Text the entry ended up as, for entries without a stored word count
+    pub text: String,
+    pub word_count: Option<u64>,
+    pub duration_ms: Option<u64>,
+    pub model: Option<String>,
+    pub transcription_ms: Option<u64>,
+    pub post_process_ms: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+pub struct DailyUsage {
+    /// Local date, `YYYY-MM-DD`
+    pub date: String,
+    pub entries: u64,
+    pub words: u64,
+    pub speaking_ms: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+pub struct ModelUsage {
+    pub model: String,
+    pub entries: u64,
+    pub average_transcription_ms: f64,
+    /// Only entries that went through post-processing
+    pub average_post_process_ms: Option<f64>,
+    /// Transcription time per second of audio; lower is faster
+    pub real_time_factor: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Type)]
+pub struct UsageStats {
+    pub entries: u64,
+    pub words: u64,
+    pub speaking_ms: u64,
+    /// Typing the same words at 40 WPM minus the time spent speaking
+    pub typing_time_saved_ms: u64,
+    /// Oldest first, days without dictation are left out
+    pub daily: Vec<DailyUsage>,
+    /// Fastest first
+    pub models: Vec<ModelUsage>,
+    pub top_corrections: Vec<CorrectionCount>,
+}
+
+#[derive(Default)]
+struct ModelTotals {
+    entries: u64,
+    transcription_ms: u64,
+    post_processed: u64,
+    post_process_ms: u64,
+    audio_ms: u64,
+    audio_transcription_ms: u64,
+}
+
+pub fn count_words(text: &str) -> u64 {
+    text.split_whitespace().count() as u64
+}
+
+/// Aggregates `rows` and the `(raw, corrected)` transcript pairs of the same range.
+pub fn summarize(rows: &[UsageRow], corrections: &[(String, String)]) -> UsageStats {
+    let mut words = 0;
+    let mut speaking_ms = 0;
+    let mut daily: BTreeMap<String, DailyUsage> = BTreeMap::new();
+    let mut models: BTreeMap<&str, ModelTotals> = BTreeMap::new();
+
+    for row in rows {
+        let row_words = row.word_count.unwrap_or_else(|| count_words(&row.text));
+        let row_speaking_ms = row.duration_ms.unwrap_or(0);
+        words += row_words;
+        speaking_ms += row_speaking_ms;
+
+        let date = DateTime::from_timestamp(row.timestamp, 0)
+            .map(|utc| utc.with_timezone(&Local).format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let day = daily.entry(date.clone()).or_insert_with(|| DailyUsage {
+            date,
+            entries: 0,
+            words: 0,
+            speaking_ms: 0,
+        });
+        day.entries += 1;
+        day.words += row_words;
+        day.speaking_ms += row_speaking_ms;
+
+        if let (Some(model), Some(transcription_ms)) = (&row.model, row.transcription_ms) {
+            let totals = models.entry(model).or_default();
+            totals.entries += 1;
+            totals.transcription_ms += transcription_ms;
+            if let Some(post_process_ms) = row.post_process_ms {
+                totals.post_processed += 1;
+                totals.post_process_ms += post_process_ms;
+            }
+            if let Some(duration_ms) = row.duration_ms.filter(|&d| d > 0) {
+                totals.audio_ms += duration_ms;
+                totals.audio_transcription_ms += transcription_ms;
+            }
+        }
+    }
+
+    let mut models: Vec<ModelUsage> = models
+        .into_iter()
+        .map(|(model, totals)| ModelUsage {
+            model: model.to_string(),
+            entries: totals.entries,
+            average_transcription_ms: totals.transcription_ms as f64 / totals.entries as f64,
+            average_post_process_ms: (totals.post_processed > 0)
+                .then(|| totals.post_process_ms as f64 / totals.post_processed as f64),
+            real_time_factor: (totals.audio_ms > 0)
+                .then(|| totals.audio_transcription_ms as f64 / totals.audio_ms as f64),
+        })
+        .collect();
+    models.sort_by(|a, b| {
+        let speed = |m: &ModelUsage| m.real_time_factor.unwrap_or(f64::INFINITY);
+        speed(a).total_cmp(&speed(b)).then_with(|| {
+            a.average_transcription_ms
+                .total_cmp(&b.average_transcription_ms)
+        })
+    });
+
+    let typing_ms = words * 60_000 / TYPING_WORDS_PER_MINUTE;
+    let mut top_corrections = count_corrections(corrections);
+    top_corrections.truncate(TOP_CORRECTIONS);
+
+    UsageStats {
+        entries: rows.len() as u64,
+        words,
+        speaking_ms,
+        typing_time_saved_ms: typing_ms.saturating_sub(speaking_ms),
+        daily: daily.into_values().collect(),
+        models,
+        top_corrections,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(day: i64, words: u64, duration_ms: u64, model: &str, transcription_ms: u64) -> UsageRow {
+        UsageRow {
+            // Midday keeps the local date stable across time zones
+            timestamp: day * 86_400 + 43_200,
+            text: String::new(),
+            word_count: Some(words),
+            duration_ms: Some(duration_ms),
+            model: Some(model.to_string()),
+            transcription_ms: Some(transcription_ms),
+            post_process_ms: None,
+        }
+    }
+
+    #[test]
+    fn aggregates_days_models_and_time_saved() {
+        let mut legacy = row(1, 0, 0, "", 0);
+        legacy.text = "saved before metadata existed".to_string();
+        legacy.word_count = None;
+        legacy.duration_ms = None;
+        legacy.model = None;
+
+        let mut post_processed = row(1, 20, 10_000, "whisper-small", 2_000);
+        post_processed.post_process_ms = Some(800);
+
+        let rows = vec![
+            row(0, 40, 20_000, "whisper-small", 4_000),
+            post_processed,
+            row(1, 30, 10_000, "parakeet", 500),
+            legacy,
+        ];
+        let stats = summarize(&rows, &[]);
+
+        assert_eq!(stats.entries, 4);
+        assert_eq!(stats.words, 94);
+        assert_eq!(stats.speaking_ms, 40_000);
+        // 94 words at 40 WPM is 141 s of typing
+        assert_eq!(stats.typing_time_saved_ms, 141_000 - 40_000);
+
+        assert_eq!(stats.daily.len(), 2);
+        assert_eq!((stats.daily[0].entries, stats.daily[0].words), (1, 40));
+        assert_eq!((stats.daily[1].entries, stats.daily[1].words), (3, 54));
+
+        let models: Vec<&str> = stats.models.iter().map(|m| m.model.as_str()).collect();
+        assert_eq!(models, vec!["parakeet", "whisper-small"]);
+        let whisper = &stats.models[1];
+        assert_eq!(whisper.entries, 2);
+        assert_eq!(whisper.average_transcription_ms, 3_000.0);
+        assert_eq!(whisper.average_post_process_ms, Some(800.0));
+        assert_eq!(whisper.real_time_factor, Some(0.2));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::managers::audio::AudioRecordingManager;
+use crate::managers::meeting::MeetingManager;
 use crate::managers::transcription::TranscriptionManager;
 use crate::shortcut;
 use crate::ManagedToggleState;
@@ -25,13 +26,23 @@ pub fn cancel_current_operation(app: &AppHandle) {
     let toggle_state_manager = app.state::<ManagedToggleState>();
     if let Ok(mut states) = toggle_state_manager.lock() {
         states.active_toggles.values_mut().for_each(|v| *v = false);
+        states.gestures.values_mut().for_each(|g| g.reset());
     } else {
         warn!("Failed to lock toggle state manager during cancellation");
     }
 
+    // A meeting only ends through its own binding or `stop_meeting`, so its
+    // recording is left running
+    let meeting_running = app.state::<Arc<MeetingManager>>().is_running();
+
     // Cancel any ongoing recording
-    let audio_manager = app.state::<Arc<AudioRecordingManager>>();
-    audio_manager.cancel_recording();
+    if !meeting_running {
+        let audio_manager = app.state::<Arc<AudioRecordingManager>>();
+        audio_manager.cancel_recording();
+    }
+
+    // Stop a chunked type-out before its next chunk
+    cancel_typing();
 
     // Update tray icon and hide overlay
     change_tray_icon(app, crate::tray::TrayIconState::Idle);
@@ -44,6 +55,36 @@ pub fn cancel_current_operation(app: &AppHandle) {
     info!("Operation cancellation completed - returned to idle state");
 }
 
+/// Replaces every `${name}` in `template` with its value from `variables` in
+/// a single pass, so `${...}` inside a value is kept as written. Unknown
+/// variables are left as they are.
+pub fn expand_template(template: &str, variables: &[(&str, &str)]) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let variable = after.find('}').and_then(|end| {
+            variables
+                .iter()
+                .find(|(name, _)| *name == &after[..end])
+                .map(|(_, value)| (end, *value))
+        });
+        match variable {
+            Some((end, value)) => {
+                expanded.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                expanded.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
 /// Check if using the Wayland display server protocol
 #[cfg(target_os = "linux")]
 pub fn is_wayland() -> bool {
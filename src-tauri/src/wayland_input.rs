@@ -0,0 +1,349 @@
+//! In-process keyboard input for Wayland through `zwp_virtual_keyboard_v1`.
+//!
+//! Each chunk of text gets a generated keymap that binds every distinct character
+//! to its own keycode and Unicode keysym. Non-ASCII text therefore types correctly
+//! regardless of the user's layout, without wtype, dotool or ydotool installed.
+//!
+//! The protocol is implemented by wlroots-based compositors (sway, Hyprland, river,
+//! labwc). Other compositors fall back to the external tools in `clipboard.rs`.
+//! Run the ignored test under a headless compositor to exercise it:
+//! `WLR_BACKENDS=headless sway` and then `cargo test wayland_input -- --ignored`.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsFd;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{Connection, Dispatch, EventQueue, QueueHandle};
+
+#[allow(dead_code, non_camel_case_types, non_upper_case_globals, clippy::all)]
+mod protocol {
+    use wayland_client;
+    use wayland_client::protocol::*;
+
+    pub mod __interfaces {
+        use wayland_client::protocol::__interfaces::*;
+        wayland_scanner::generate_interfaces!("protocols/virtual-keyboard-unstable-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_client_code!("protocols/virtual-keyboard-unstable-v1.xml");
+}
+
+use protocol::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
+use protocol::zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1;
+
+/// xkb keycodes are evdev codes offset by 8; 8 itself is reserved.
+const FIRST_KEYCODE: u32 = 9;
+const MAX_KEYCODE: u32 = 255;
+const MAX_KEYS_PER_KEYMAP: usize = (MAX_KEYCODE - FIRST_KEYCODE + 1) as usize;
+const EVDEV_OFFSET: u32 = 8;
+
+const KEYMAP_FORMAT_XKB_V1: u32 = 1;
+const KEY_STATE_RELEASED: u32 = 0;
+const KEY_STATE_PRESSED: u32 = 1;
+
+/// Real modifier masks in the generated keymap.
+pub const MOD_SHIFT: u32 = 1 << 0;
+pub const MOD_CONTROL: u32 = 1 << 2;
+
+static KEYMAP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Default)]
+struct State {
+    seat: Option<wl_seat::WlSeat>,
+    manager: Option<ZwpVirtualKeyboardManagerV1>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for State {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global {
+            name, interface, ..
+        } = event
+        {
+            match interface.as_str() {
+                "wl_seat" if state.seat.is_none() => {
+                    state.seat = Some(registry.bind(name, 1, qh, ()));
+                }
+                "zwp_virtual_keyboard_manager_v1" => {
+                    state.manager = Some(registry.bind(name, 1, qh, ()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+wayland_client::delegate_noop!(State: ignore wl_seat::WlSeat);
+wayland_client::delegate_noop!(State: ZwpVirtualKeyboardManagerV1);
+wayland_client::delegate_noop!(State: ZwpVirtualKeyboardV1);
+
+/// A keymap binding each keysym name to consecutive keycodes from `FIRST_KEYCODE`.
+struct Keymap {
+    keysyms: Vec<String>,
+}
+
+impl Keymap {
+    fn new(keysyms: Vec<String>) -> Self {
+        debug_assert!(keysyms.len() <= MAX_KEYS_PER_KEYMAP);
+        Keymap { keysyms }
+    }
+
+    /// Evdev code for the key carrying `keysym`.
+    fn evdev_code(&self, keysym: &str) -> Option<u32> {
+        self.keysyms
+            .iter()
+            .position(|k| k == keysym)
+            .map(|i| FIRST_KEYCODE + i as u32 - EVDEV_OFFSET)
+    }
+
+    fn to_xkb(&self) -> String {
+        let mut keycodes = String::new();
+        let mut symbols = String::new();
+        for (i, keysym) in self.keysyms.iter().enumerate() {
+            let code = FIRST_KEYCODE + i as u32;
+            keycodes.push_str(&format!("    <K{}> = {};\n", code, code));
+            symbols.push_str(&format!("    key <K{}> {{ [ {} ] }};\n", code, keysym));
+        }
+
+        format!(
+            "xkb_keymap {{\n\
+             xkb_keycodes \"handy\" {{\n    minimum = 8;\n    maximum = {};\n{}}};\n\
+             xkb_types \"handy\" {{ include \"complete\" }};\n\
+             xkb_compat \"handy\" {{ include \"complete\" }};\n\
+             xkb_symbols \"handy\" {{\n{}}};\n\
+             }};\n",
+            MAX_KEYCODE, keycodes, symbols
+        )
+    }
+}
+
+/// The xkb keysym name for a character, or `None` for unsupported control characters.
+fn keysym_name(c: char) -> Option<String> {
+    match c {
+        '\n' => Some("Return".to_string()),
+        '\t' => Some("Tab".to_string()),
+        c if c.is_control() => None,
+        c => Some(format!("U{:04X}", c as u32)),
+    }
+}
+
+/// Splits text into runs that each fit into a single keymap.
+fn split_into_keymap_chunks(text: &str) -> Vec<Vec<String>> {
+    let mut chunks = Vec::new();
+    let mut current: Vec<String> = Vec::new();
+    let mut distinct: Vec<String> = Vec::new();
+
+    for keysym in text.chars().filter_map(keysym_name) {
+        if !distinct.contains(&keysym) {
+            if distinct.len() == MAX_KEYS_PER_KEYMAP {
+                chunks.push(std::mem::take(&mut current));
+                distinct.clear();
+            }
+            distinct.push(keysym.clone());
+        }
+        current.push(keysym);
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Writes the keymap to an unlinked file the compositor can map.
+fn keymap_file(xkb: &str) -> Result<(File, u32), String> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir);
+    let path = dir.join(format!(
+        "handy-keymap-{}-{}",
+        std::process::id(),
+        KEYMAP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to create keymap file: {}", e))?;
+    let _ = std::fs::remove_file(&path);
+
+    file.write_all(xkb.as_bytes())
+        .and_then(|_| file.write_all(&[0]))
+        .map_err(|e| format!("Failed to write keymap: {}", e))?;
+
+    Ok((file, xkb.len() as u32 + 1))
+}
+
+pub struct VirtualKeyboard {
+    connection: Connection,
+    queue: EventQueue<State>,
+    state: State,
+    keyboard: ZwpVirtualKeyboardV1,
+    started: Instant,
+}
+
+impl VirtualKeyboard {
+    /// Connects to the compositor from `WAYLAND_DISPLAY` and creates a virtual keyboard.
+    pub fn connect() -> Result<Self, String> {
+        let connection = Connection::connect_to_env()
+            .map_err(|e| format!("Failed to connect to Wayland display: {}", e))?;
+        let mut queue = connection.new_event_queue();
+        let qh = queue.handle();
+        connection.display().get_registry(&qh, ());
+
+        let mut state = State::default();
+        queue
+            .roundtrip(&mut state)
+            .map_err(|e| format!("Wayland roundtrip failed: {}", e))?;
+
+        let seat = state.seat.as_ref().ok_or("Compositor exposes no wl_seat")?;
+        let manager = state
+            .manager
+            .as_ref()
+            .ok_or("Compositor does not support zwp_virtual_keyboard_v1")?;
+        let keyboard = manager.create_virtual_keyboard(seat, &qh, ());
+
+        Ok(VirtualKeyboard {
+            connection,
+            queue,
+            state,
+            keyboard,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn type_text(&mut self, text: &str) -> Result<(), String> {
+        for chunk in split_into_keymap_chunks(text) {
+            let mut distinct: Vec<String> = Vec::new();
+            for keysym in &chunk {
+                if !distinct.contains(keysym) {
+                    distinct.push(keysym.clone());
+                }
+            }
+            let keymap = Keymap::new(distinct);
+            self.upload_keymap(&keymap)?;
+
+            for keysym in &chunk {
+                if let Some(code) = keymap.evdev_code(keysym) {
+                    self.tap(code)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Presses `keysym` while `modifiers` (`MOD_SHIFT`, `MOD_CONTROL`) are held.
+    pub fn send_shortcut(&mut self, modifiers: u32, keysym: &str) -> Result<(), String> {
+        let keymap = Keymap::new(vec![keysym.to_string()]);
+        self.upload_keymap(&keymap)?;
+
+        let code = keymap
+            .evdev_code(keysym)
+            .ok_or_else(|| format!("Keysym '{}' missing from keymap", keysym))?;
+        self.keyboard.modifiers(modifiers, 0, 0, 0);
+        self.tap(code)?;
+        self.keyboard.modifiers(0, 0, 0, 0);
+        self.sync()
+    }
+
+    /// Taps `keysym` `count` times without modifiers.
+    pub fn repeat_key(&mut self, keysym: &str, count: usize) -> Result<(), String> {
+        let keymap = Keymap::new(vec![keysym.to_string()]);
+        self.upload_keymap(&keymap)?;
+
+        let code = keymap
+            .evdev_code(keysym)
+            .ok_or_else(|| format!("Keysym '{}' missing from keymap", keysym))?;
+        for _ in 0..count {
+            self.tap(code)?;
+        }
+        Ok(())
+    }
+
+    fn upload_keymap(&mut self, keymap: &Keymap) -> Result<(), String> {
+        let (file, size) = keymap_file(&keymap.to_xkb())?;
+        self.keyboard
+            .keymap(KEYMAP_FORMAT_XKB_V1, file.as_fd(), size);
+        self.sync()
+    }
+
+    fn tap(&mut self, code: u32) -> Result<(), String> {
+        let time = self.started.elapsed().as_millis() as u32;
+        self.keyboard.key(time, code, KEY_STATE_PRESSED);
+        self.keyboard.key(time + 1, code, KEY_STATE_RELEASED);
+        self.sync()
+    }
+
+    fn sync(&mut self) -> Result<(), String> {
+        self.queue
+            .roundtrip(&mut self.state)
+            .map(|_| ())
+            .map_err(|e| format!("Wayland roundtrip failed: {}", e))
+    }
+}
+
+impl Drop for VirtualKeyboard {
+    fn drop(&mut self) {
+        self.keyboard.destroy();
+        let _ = self.connection.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_characters_to_unicode_keysyms() {
+        assert_eq!(keysym_name('a').as_deref(), Some("U0061"));
+        assert_eq!(keysym_name('é').as_deref(), Some("U00E9"));
+        assert_eq!(keysym_name('✓').as_deref(), Some("U2713"));
+        assert_eq!(keysym_name('\n').as_deref(), Some("Return"));
+        assert_eq!(keysym_name('\u{7}'), None);
+    }
+
+    #[test]
+    fn splits_text_when_keymap_is_full() {
+        let text: String = (0..300u32)
+            .map(|i| char::from_u32(0x4E00 + i).unwrap())
+            .collect();
+        let chunks = split_into_keymap_chunks(&text);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].len(), MAX_KEYS_PER_KEYMAP);
+        assert_eq!(chunks[1].len(), 300 - MAX_KEYS_PER_KEYMAP);
+    }
+
+    #[test]
+    fn keymap_assigns_consecutive_keycodes() {
+        let keymap = Keymap::new(vec!["U0061".to_string(), "U00E9".to_string()]);
+        assert_eq!(keymap.evdev_code("U0061"), Some(1));
+        assert_eq!(keymap.evdev_code("U00E9"), Some(2));
+        let xkb = keymap.to_xkb();
+        assert!(xkb.contains("<K10> = 10;"));
+        assert!(xkb.contains("key <K10> { [ U00E9 ] };"));
+    }
+
+    #[test]
+    #[ignore = "requires a Wayland compositor with zwp_virtual_keyboard_v1"]
+    fn types_into_running_compositor() {
+        let mut keyboard = VirtualKeyboard::connect().expect("connecting to the compositor");
+        keyboard
+            .type_text("Handy héllo ✓\n")
+            .expect("typing through the virtual keyboard");
+        keyboard
+            .send_shortcut(MOD_CONTROL, "v")
+            .expect("sending Ctrl+V");
+    }
+}
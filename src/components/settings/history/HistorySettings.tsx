@@ -92,13 +92,19 @@ export const HistorySettings: React.FC = () => {
     }
   };
 
+  const audioMimeType = (fileName: string) => {
+    if (fileName.endsWith(".flac")) return "audio/flac";
+    if (fileName.endsWith(".opus")) return "audio/ogg; codecs=opus";
+    return "audio/wav";
+  };
+
   const getAudioUrl = async (fileName: string) => {
     try {
       const result = await commands.getAudioFilePath(fileName);
       if (result.status === "ok") {
         if (osType === "linux") {
           const fileData = await readFile(result.data);
-          const blob = new Blob([fileData], { type: "audio/wav" });
+          const blob = new Blob([fileData], { type: audioMimeType(fileName) });
 
           return URL.createObjectURL(blob);
         }
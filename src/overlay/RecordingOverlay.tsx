@@ -8,14 +8,20 @@ import {
 } from "../components/icons";
 import "./RecordingOverlay.css";
 import { commands } from "@/bindings";
-import { syncLanguageFromSettings } from "@/i18n";
+import i18n, { syncLanguageFromSettings } from "@/i18n";
 
-type OverlayState = "recording" | "transcribing";
+type OverlayState = "recording" | "transcribing" | "notification";
+
+interface OverlayNotification {
+  key: string;
+  params: Record<string, string>;
+}
 
 const RecordingOverlay: React.FC = () => {
   const { t } = useTranslation();
   const [isVisible, setIsVisible] = useState(false);
   const [state, setState] = useState<OverlayState>("recording");
+  const [notification, setNotification] = useState("");
   const [levels, setLevels] = useState<number[]>(Array(16).fill(0));
   const smoothedLevelsRef = useRef<number[]>(Array(16).fill(0));
 
@@ -30,6 +36,18 @@ const RecordingOverlay: React.FC = () => {
         setIsVisible(true);
       });
 
+      // Listen for short notifications, e.g. after switching language
+      const unlistenNotification = await listen<OverlayNotification>(
+        "overlay-notification",
+        async (event) => {
+          await syncLanguageFromSettings();
+          const { key, params } = event.payload;
+          setNotification(i18n.t(`overlay.notifications.${key}`, params));
+          setState("notification");
+          setIsVisible(true);
+        },
+      );
+
       // Listen for hide-overlay event from Rust
       const unlistenHide = await listen("hide-overlay", () => {
         setIsVisible(false);
@@ -53,6 +71,7 @@ const RecordingOverlay: React.FC = () => {
       return () => {
         unlistenShow();
         unlistenHide();
+        unlistenNotification();
         unlistenLevel();
       };
     };
@@ -91,6 +110,9 @@ const RecordingOverlay: React.FC = () => {
         {state === "transcribing" && (
           <div className="transcribing-text">{t("overlay.transcribing")}</div>
         )}
+        {state === "notification" && (
+          <div className="transcribing-text">{notification}</div>
+        )}
       </div>
 
       <div className="overlay-right">